@@ -0,0 +1,72 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::f64::consts::PI;
+use t_rex_core::core::screen;
+use t_rex_core::mvt::geom_encoder::EncodableGeom;
+
+/// Closed ring around (cx, cy) with a wiggly outline, like digitized coastlines
+fn ring(cx: f64, cy: f64, radius: f64, npoints: usize, clockwise: bool) -> screen::LineString {
+    let mut points: Vec<screen::Point> = (0..npoints)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f64 / npoints as f64;
+            let r = radius * (1.0 + 0.05 * (angle * 37.0).sin() + 0.02 * (angle * 113.0).cos());
+            screen::Point {
+                x: (cx + r * angle.cos()).round() as i32,
+                y: (cy + r * angle.sin()).round() as i32,
+            }
+        })
+        .collect();
+    if !clockwise {
+        points.reverse();
+    }
+    points.push(points[0].clone());
+    screen::LineString { points }
+}
+
+/// Polygon with 10k vertices in an outer ring and two holes
+fn large_polygon() -> screen::Polygon {
+    screen::Polygon {
+        rings: vec![
+            ring(2048.0, 2048.0, 1800.0, 8000, true),
+            ring(1500.0, 1500.0, 300.0, 1000, false),
+            ring(2600.0, 2600.0, 300.0, 1000, false),
+        ],
+    }
+}
+
+/// 50 meandering lines with 200 vertices each
+fn large_multilinestring() -> screen::MultiLineString {
+    let lines = (0..50)
+        .map(|l| {
+            let points = (0..200)
+                .map(|i| {
+                    let t = i as f64 / 200.0;
+                    screen::Point {
+                        x: (t * 4096.0) as i32,
+                        y: (l as f64 * 80.0 + 40.0 * (t * 20.0 + l as f64).sin()) as i32,
+                    }
+                })
+                .collect();
+            screen::LineString { points }
+        })
+        .collect();
+    screen::MultiLineString { lines }
+}
+
+fn encode_benchmark(c: &mut Criterion) {
+    let polygon = large_polygon();
+    c.bench_function("encode polygon 10k", |b| {
+        b.iter(|| black_box(&polygon).encode())
+    });
+    let multilinestring = large_multilinestring();
+    c.bench_function("encode multilinestring 10k", |b| {
+        b.iter(|| black_box(&multilinestring).encode())
+    });
+}
+
+criterion_group!(benches, encode_benchmark);
+criterion_main!(benches);
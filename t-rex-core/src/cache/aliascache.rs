@@ -0,0 +1,123 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Cache wrapper resolving tileset aliases
+
+use crate::cache::cache::{Cache, CacheError, Encoding, TileMeta};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+
+/// Cache decorator for tilesets published under additional names, e.g. versioned names.
+/// Reads of an alias fall back to the canonical tileset, writes and deletes
+/// always go to the canonical tileset.
+#[derive(Clone)]
+pub struct AliasCache<C: Cache> {
+    cache: C,
+    /// Alias tileset name => canonical tileset name
+    aliases: HashMap<String, String>,
+}
+
+impl<C: Cache> AliasCache<C> {
+    pub fn new(cache: C, aliases: HashMap<String, String>) -> AliasCache<C> {
+        AliasCache { cache, aliases }
+    }
+    /// Wrapped cache
+    pub fn inner(&self) -> &C {
+        &self.cache
+    }
+    /// Canonical name of tileset
+    pub fn canonical<'a>(&'a self, tileset: &'a str) -> &'a str {
+        self.aliases
+            .get(tileset)
+            .map_or(tileset, |name| name.as_str())
+    }
+    /// Path with tileset replaced by its canonical name, if it is an alias
+    fn canonical_path(&self, path: &str) -> Option<String> {
+        let mut parts = path.splitn(2, '/');
+        let tileset = parts.next()?;
+        let canonical = self.aliases.get(tileset)?;
+        match parts.next() {
+            Some(rest) => Some(format!("{}/{}", canonical, rest)),
+            None => Some(canonical.clone()),
+        }
+    }
+    fn resolve(&self, path: &str) -> String {
+        self.canonical_path(path)
+            .unwrap_or_else(|| path.to_string())
+    }
+}
+
+impl<C: Cache> Cache for AliasCache<C> {
+    fn info(&self) -> String {
+        self.cache.info()
+    }
+    fn baseurl(&self) -> String {
+        self.cache.baseurl()
+    }
+    fn read<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
+    {
+        if self.cache.read(path, &mut read)? {
+            return Ok(true);
+        }
+        match self.canonical_path(path) {
+            Some(canonical) => self.cache.read(&canonical, read),
+            None => Ok(false),
+        }
+    }
+    fn read_encoded<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read, Encoding) -> Result<(), io::Error>,
+    {
+        if self.cache.read_encoded(path, &mut read)? {
+            return Ok(true);
+        }
+        match self.canonical_path(path) {
+            Some(canonical) => self.cache.read_encoded(&canonical, read),
+            None => Ok(false),
+        }
+    }
+    fn read_meta(&self, path: &str) -> Option<TileMeta> {
+        self.cache
+            .read_meta(path)
+            .or_else(|| self.cache.read_meta(&self.canonical_path(path)?))
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
+        self.cache.write(&self.resolve(path), obj)
+    }
+    fn write_stream<F>(&self, path: &str, write: F) -> Result<(), CacheError>
+    where
+        F: FnOnce(&mut dyn Write) -> Result<(), io::Error>,
+    {
+        self.cache.write_stream(&self.resolve(path), write)
+    }
+    fn write_batch<I>(&self, tileset_name: &str, tiles: I) -> Result<(), CacheError>
+    where
+        I: IntoIterator<Item = (u8, u32, u32, Vec<u8>)>,
+    {
+        self.cache.write_batch(self.canonical(tileset_name), tiles)
+    }
+    fn preferred_encoding(&self) -> Encoding {
+        self.cache.preferred_encoding()
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.cache.exists(path)
+            || matches!(self.canonical_path(path), Some(canonical) if self.cache.exists(&canonical))
+    }
+    fn remove(&self, path: &str) -> bool {
+        self.cache.remove(&self.resolve(path))
+    }
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError> {
+        self.cache
+            .delete(self.canonical(tileset), zoom, xtile, ytile)
+    }
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError> {
+        self.cache.delete_tileset(self.canonical(tileset))
+    }
+    fn healthcheck(&self) -> Result<(), CacheError> {
+        self.cache.healthcheck()
+    }
+}
@@ -0,0 +1,41 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::aliascache::AliasCache;
+use crate::cache::cache::Cache;
+use crate::cache::recordingcache::RecordingCache;
+use std::collections::HashMap;
+
+#[test]
+fn test_aliascache() {
+    let mut aliases = HashMap::new();
+    aliases.insert("foo_v2".to_string(), "foo".to_string());
+    let cache = AliasCache::new(RecordingCache::new(), aliases);
+
+    cache.inner().write("foo/2/1/3.pbf", b"tile").unwrap();
+
+    // Reads of the alias find the tile of the canonical tileset
+    let mut s = String::new();
+    assert!(cache
+        .read("foo_v2/2/1/3.pbf", |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(s, "tile");
+    assert!(cache.exists("foo_v2/2/1/3.pbf"));
+    assert!(!cache.exists("foo_v2/2/1/4.pbf"));
+    assert!(!cache.exists("bar/2/1/3.pbf"));
+
+    // Writes go to the canonical tileset
+    cache.write("foo_v2/2/1/4.pbf", b"tile4").unwrap();
+    assert_eq!(cache.inner().get("foo", 2, 1, 4), Some(b"tile4".to_vec()));
+    assert_eq!(cache.inner().get("foo_v2", 2, 1, 4), None);
+
+    cache.delete("foo_v2", 2, 1, 3).unwrap();
+    assert!(!cache.inner().exists("foo/2/1/3.pbf"));
+    cache.delete_tileset("foo_v2").unwrap();
+    assert!(cache.inner().is_empty());
+}
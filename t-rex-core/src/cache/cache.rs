@@ -3,19 +3,356 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+use flate2::{read::GzDecoder, Crc, CrcReader};
+use std::error;
+use std::fmt;
 use std::io;
-use std::io::Read;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// Error of a cache operation
+#[derive(Debug)]
+pub enum CacheError {
+    /// I/O error, also returned by read callbacks
+    Io(io::Error),
+    /// Missing storage location, e.g. S3 bucket
+    NotFound(String),
+    /// Storage quota of the backend exceeded
+    QuotaExceeded(String),
+    /// Stored tile data is invalid
+    CorruptTile(String),
+    /// Tile coordinates outside of the tile grid
+    InvalidTile(String),
+    /// Tileset name not usable as storage location, e.g. containing a path separator
+    InvalidTileset(String),
+    /// Other error reported by the backend (SQLite, S3, Redis)
+    Backend(String),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CacheError::Io(ref e) => e.fmt(f),
+            CacheError::NotFound(ref what) => write!(f, "{} not found", what),
+            CacheError::QuotaExceeded(ref msg) => write!(f, "Quota exceeded: {}", msg),
+            CacheError::CorruptTile(ref msg) => write!(f, "Corrupt tile: {}", msg),
+            CacheError::InvalidTile(ref path) => write!(f, "Invalid tile {}", path),
+            CacheError::InvalidTileset(ref name) => write!(f, "Invalid tileset name '{}'", name),
+            CacheError::Backend(ref msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl error::Error for CacheError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            CacheError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CacheError {
+    fn from(e: io::Error) -> CacheError {
+        CacheError::Io(e)
+    }
+}
+
+/// Reserved tileset name used by `Cache::healthcheck`
+pub const HEALTHCHECK_TILESET: &str = "_t_rex_healthcheck";
 
 pub trait Cache {
     fn info(&self) -> String;
     /// Base URL of tile cache server published in metadata
     fn baseurl(&self) -> String;
-    fn read<F>(&self, path: &str, read: F) -> bool
+    /// Pass cached object to `read`. Returns `Ok(false)` on cache miss
+    /// and errors of the cache or the callback otherwise.
+    /// Backends pass a reader on their storage where possible, e.g. the cache file,
+    /// so large objects can be processed without buffering them completely.
+    /// Use `read_to_vec` if the whole object is needed in memory.
+    fn read<F>(&self, path: &str, read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>;
+    /// Cached object read into a buffer, `None` on cache miss
+    fn read_to_vec(&self, path: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        let mut data = Vec::new();
+        let hit = self.read(path, |f| f.read_to_end(&mut data).map(|_| ()))?;
+        Ok(if hit { Some(data) } else { None })
+    }
+    /// Like `read`, but passes the object in its stored encoding, so compressed objects
+    /// can be delivered without decompressing them. The default passes decoded content.
+    fn read_encoded<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read, Encoding) -> Result<(), io::Error>,
+    {
+        self.read(path, |f| read(f, Encoding::Identity))
+    }
+    /// Metadata of cached object, `None` on cache miss. The default reads the object
+    /// to compute its entity tag and returns the time of reading as modification time.
+    fn read_meta(&self, path: &str) -> Option<TileMeta> {
+        let data = self.read_to_vec(path).ok()??;
+        Some(TileMeta {
+            content_type: content_type(path).to_string(),
+            modified: SystemTime::now(),
+            etag: etag(&data),
+        })
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError>;
+    /// Write object produced by `write` into the writer passed to it.
+    /// Backends can stream the object into their storage, the default
+    /// buffers it in memory and calls `write`.
+    fn write_stream<F>(&self, path: &str, write: F) -> Result<(), CacheError>
+    where
+        F: FnOnce(&mut dyn Write) -> Result<(), io::Error>,
+    {
+        let mut obj = Vec::new();
+        write(&mut obj)?;
+        self.write(path, &obj)
+    }
+    /// Encoding in which the backend stores tiles natively. Tiles can be
+    /// encoded once in this form before writing, avoiding recompression.
+    /// Backends storing objects as written (`Identity`) get gzip compressed tiles.
+    fn preferred_encoding(&self) -> Encoding {
+        Encoding::Identity
+    }
+    /// Write multiple tiles (zoom, x, y, data) of a tileset.
+    /// Backends can override the default loop over `write`, e.g. with a single transaction.
+    fn write_batch<I>(&self, tileset_name: &str, tiles: I) -> Result<(), CacheError>
     where
-        F: FnMut(&mut dyn Read);
-    fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error>;
+        I: IntoIterator<Item = (u8, u32, u32, Vec<u8>)>,
+    {
+        for (zoom, xtile, ytile, obj) in tiles {
+            self.write(&tile_path(tileset_name, zoom, xtile, ytile), &obj)?;
+        }
+        Ok(())
+    }
     fn exists(&self, path: &str) -> bool;
+    /// Check existence of multiple tiles (zoom, x, y) of a tileset.
+    /// Backends can override the default loop over `exists` with batched lookups.
+    fn exists_many(&self, tileset: &str, coords: &[(u8, u32, u32)]) -> Vec<bool> {
+        coords
+            .iter()
+            .map(|&(zoom, xtile, ytile)| self.exists(&tile_path(tileset, zoom, xtile, ytile)))
+            .collect()
+    }
     fn remove(&self, path: &str) -> bool;
+    /// Delete cached tile. Deleting a missing tile is not an error.
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError>;
+    /// Delete all cached tiles of a tileset
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError>;
+    /// Check that the cache is writable and readable by writing, reading back
+    /// and deleting a sentinel tile of tileset `HEALTHCHECK_TILESET`.
+    fn healthcheck(&self) -> Result<(), CacheError> {
+        const SENTINEL: &[u8] = b"t-rex healthcheck";
+        let path = tile_path(HEALTHCHECK_TILESET, 0, 0, 0);
+        self.write(&path, SENTINEL)?;
+        let found = self.read_to_vec(&path);
+        let cleanup = self.delete_tileset(HEALTHCHECK_TILESET);
+        if found?.as_deref() != Some(SENTINEL) {
+            return Err(CacheError::CorruptTile(format!(
+                "written tile {} not read back from cache",
+                path
+            )));
+        }
+        cleanup
+    }
+}
+
+/// Cache which can enumerate its stored tiles
+pub trait ListableCache {
+    /// Cached tiles of tileset as (zoom, x, y)
+    fn list(&self, tileset: &str) -> Box<dyn Iterator<Item = (u8, u32, u32)>>;
+}
+
+/// Tile coordinates within the Web Mercator (XYZ) tile grid,
+/// i.e. `x` and `y` lower than 2^`zoom`
+pub fn valid_tile(zoom: u8, x: u32, y: u32) -> bool {
+    let count = 1u64 << zoom.min(32);
+    (x as u64) < count && (y as u64) < count
+}
+
+/// Construction of cache keys from tile coordinates
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum KeyStrategy {
+    /// Hierarchical path `tileset/zoom/x/y.format`
+    #[default]
+    Path,
+    /// Flat key `tileset/<hash>.format` of constant length, for key-value stores.
+    /// The hash is a bijective mix of the tile coordinates, so different tiles never collide.
+    Hashed,
+}
+
+/// Bijective mixing function (SplitMix64 finalizer)
+fn mix64(mut v: u64) -> u64 {
+    v = (v ^ (v >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    v = (v ^ (v >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    v ^ (v >> 31)
+}
+
+/// Cache key of a tile in output format `format` (file extension)
+pub fn cache_key(
+    strategy: KeyStrategy,
+    tileset: &str,
+    zoom: u8,
+    xtile: u32,
+    ytile: u32,
+    format: &str,
+) -> String {
+    match strategy {
+        KeyStrategy::Path => format!("{}/{}/{}/{}.{}", tileset, zoom, xtile, ytile, format),
+        KeyStrategy::Hashed => {
+            let hash = mix64((xtile as u64) << 32 | ytile as u64);
+            format!("{}/{:016x}{:02x}.{}", tileset, hash, zoom, format)
+        }
+    }
+}
+
+/// Cache key of cache path. Paths of objects other than tiles are used as key.
+pub fn path_key(strategy: KeyStrategy, path: &str) -> String {
+    match parse_tile_path_with_format(path) {
+        Some((tileset, zoom, xtile, ytile, format)) => {
+            cache_key(strategy, tileset, zoom, xtile, ytile, format)
+        }
+        None => path.to_string(),
+    }
+}
+
+/// Cache path of a vector tile
+pub fn tile_path(tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> String {
+    tile_path_with_format(tileset, zoom, xtile, ytile, "pbf")
+}
+
+/// Cache path of a tile in output format `format` (file extension)
+pub fn tile_path_with_format(
+    tileset: &str,
+    zoom: u8,
+    xtile: u32,
+    ytile: u32,
+    format: &str,
+) -> String {
+    cache_key(KeyStrategy::Path, tileset, zoom, xtile, ytile, format)
+}
+
+/// MIME type of cached object derived from path extension
+pub fn content_type(path: &str) -> &'static str {
+    match Path::new(path).extension().and_then(|ext| ext.to_str()) {
+        Some("mvt") | Some("pbf") => "application/vnd.mapbox-vector-tile",
+        Some("json") => "application/json",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Zero-length object or gzip stream without content, like an MVT tile without layers
+pub fn is_empty_tile(obj: &[u8]) -> bool {
+    if is_gzip(obj) {
+        let mut buf = [0; 1];
+        matches!(GzDecoder::new(obj).read(&mut buf), Ok(0))
+    } else {
+        obj.is_empty()
+    }
+}
+
+/// Object starts with the gzip magic number
+pub(crate) fn is_gzip(obj: &[u8]) -> bool {
+    obj.starts_with(&[0x1f, 0x8b])
+}
+
+/// Encoding of cached object passed to `Cache::read_encoded`
+/// and native encoding of a backend (`Cache::preferred_encoding`)
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum Encoding {
+    /// Object content as written
+    #[default]
+    Identity,
+    /// Gzip compressed object content
+    Gzip,
+    /// Brotli compressed object content
+    Brotli,
+}
+
+impl Encoding {
+    /// Value of HTTP `Content-Encoding` header
+    pub fn content_encoding(&self) -> &'static str {
+        match *self {
+            Encoding::Identity => "identity",
+            Encoding::Gzip => "gzip",
+            Encoding::Brotli => "br",
+        }
+    }
+    /// Reader decoding `r`
+    pub fn decoder<'a, R: Read + 'a>(&self, r: R) -> Box<dyn Read + 'a> {
+        match *self {
+            Encoding::Identity => Box::new(r),
+            Encoding::Gzip => Box::new(GzDecoder::new(r)),
+            Encoding::Brotli => Box::new(brotli::Decompressor::new(r, 4096)),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Encoding, String> {
+        match s {
+            "none" | "identity" => Ok(Encoding::Identity),
+            "gzip" => Ok(Encoding::Gzip),
+            "brotli" | "br" => Ok(Encoding::Brotli),
+            _ => Err(format!("Unknown compression '{}'", s)),
+        }
+    }
+}
+
+/// Metadata of cached object
+#[derive(Clone, PartialEq, Debug)]
+pub struct TileMeta {
+    pub content_type: String,
+    /// Time of writing into cache
+    pub modified: SystemTime,
+    /// Entity tag of the uncompressed content, see [`etag`]
+    pub etag: String,
+}
+
+/// Quoted entity tag for HTTP `ETag` and `If-None-Match` headers.
+/// Derived from CRC32 and length of the uncompressed content of `data`, which may be
+/// gzip compressed. So identical content always gets the same tag, compressed or not.
+pub fn etag(data: &[u8]) -> String {
+    if is_gzip(data) {
+        let mut gz = CrcReader::new(GzDecoder::new(data));
+        if io::copy(&mut gz, &mut io::sink()).is_ok() {
+            return crc_etag(gz.crc());
+        }
+    }
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc_etag(&crc)
+}
+
+/// Entity tag of content with computed CRC
+pub(crate) fn crc_etag(crc: &Crc) -> String {
+    format!("\"{:08x}-{:x}\"", crc.sum(), crc.amount())
+}
+
+/// Parse vector tile cache path into (tileset, zoom, x, y)
+pub fn parse_tile_path(path: &str) -> Option<(&str, u8, u32, u32)> {
+    match parse_tile_path_with_format(path)? {
+        (tileset, zoom, xtile, ytile, "pbf") => Some((tileset, zoom, xtile, ytile)),
+        _ => None,
+    }
+}
+
+/// Parse tile cache path of any format into (tileset, zoom, x, y, format)
+pub fn parse_tile_path_with_format(path: &str) -> Option<(&str, u8, u32, u32, &str)> {
+    let parts: Vec<&str> = path.split('/').collect();
+    if parts.len() != 4 {
+        return None;
+    }
+    let mut name = parts[3].splitn(2, '.');
+    let ytile = name.next()?.parse().ok()?;
+    let format = name.next()?;
+    let zoom = parts[1].parse().ok()?;
+    let xtile = parts[2].parse().ok()?;
+    Some((parts[0], zoom, xtile, ytile, format))
 }
 
 #[derive(Clone)]
@@ -29,14 +366,14 @@ impl Cache for Nocache {
         "http://localhost:6767".to_string()
     }
     #[allow(unused_variables)]
-    fn read<F>(&self, path: &str, read: F) -> bool
+    fn read<F>(&self, path: &str, read: F) -> Result<bool, CacheError>
     where
-        F: FnMut(&mut dyn Read),
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
     {
-        false
+        Ok(false)
     }
     #[allow(unused_variables)]
-    fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error> {
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
         Ok(())
     }
 
@@ -46,4 +383,25 @@ impl Cache for Nocache {
     fn remove(&self, _path: &str) -> bool {
         false
     }
+    fn delete(
+        &self,
+        _tileset: &str,
+        _zoom: u8,
+        _xtile: u32,
+        _ytile: u32,
+    ) -> Result<(), CacheError> {
+        Ok(())
+    }
+    fn delete_tileset(&self, _tileset: &str) -> Result<(), CacheError> {
+        Ok(())
+    }
+    fn healthcheck(&self) -> Result<(), CacheError> {
+        Ok(())
+    }
+}
+
+impl ListableCache for Nocache {
+    fn list(&self, _tileset: &str) -> Box<dyn Iterator<Item = (u8, u32, u32)>> {
+        Box::new(std::iter::empty())
+    }
 }
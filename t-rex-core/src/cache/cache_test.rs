@@ -0,0 +1,114 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::cache::{
+    cache_key, etag, path_key, tile_path_with_format, valid_tile, CacheError, KeyStrategy,
+};
+use std::collections::HashSet;
+
+#[test]
+fn test_valid_tile() {
+    assert!(valid_tile(0, 0, 0));
+    assert!(!valid_tile(0, 1, 0));
+    assert!(!valid_tile(0, 0, 1));
+    // Zoom 2 has 4x4 tiles
+    for i in 0..4 {
+        assert!(valid_tile(2, i, 3 - i));
+    }
+    assert!(!valid_tile(2, 4, 0));
+    assert!(!valid_tile(2, 0, 4));
+    assert!(valid_tile(14, 16383, 16383));
+    assert!(!valid_tile(14, 16384, 0));
+    assert!(valid_tile(31, (1 << 31) - 1, 0));
+    assert!(!valid_tile(31, 1 << 31, 0));
+    // All u32 coordinates are within the grid from zoom 32
+    assert!(valid_tile(32, u32::MAX, u32::MAX));
+    assert!(valid_tile(255, u32::MAX, 0));
+
+    assert_eq!(
+        CacheError::InvalidTile("tileset/2/4/0.pbf".to_string()).to_string(),
+        "Invalid tile tileset/2/4/0.pbf"
+    );
+}
+
+#[test]
+fn test_cache_key() {
+    assert_eq!(
+        cache_key(KeyStrategy::Path, "tileset", 3, 5, 2, "pbf"),
+        "tileset/3/5/2.pbf"
+    );
+    assert_eq!(
+        cache_key(KeyStrategy::default(), "tileset", 3, 5, 2, "mvt"),
+        tile_path_with_format("tileset", 3, 5, 2, "mvt")
+    );
+
+    // Hashed keys are stable and of constant length
+    let key = cache_key(KeyStrategy::Hashed, "tileset", 3, 5, 2, "pbf");
+    assert_eq!(key, "tileset/56eb08996e12a33003.pbf");
+    assert_eq!(
+        key,
+        cache_key(KeyStrategy::Hashed, "tileset", 3, 5, 2, "pbf")
+    );
+    assert_eq!(
+        cache_key(KeyStrategy::Hashed, "tileset", 22, u32::MAX, 0, "pbf").len(),
+        key.len()
+    );
+    assert_eq!(path_key(KeyStrategy::Hashed, "tileset/3/5/2.pbf"), key);
+    assert_eq!(
+        path_key(KeyStrategy::Hashed, "tileset.json"),
+        "tileset.json"
+    );
+
+    // No collisions
+    let mut keys = HashSet::new();
+    for zoom in 0..=8 {
+        let count = 1 << zoom;
+        for x in 0..count {
+            for y in 0..count {
+                assert!(keys.insert(cache_key(KeyStrategy::Hashed, "tileset", zoom, x, y, "pbf")));
+            }
+        }
+    }
+    // Swapped coordinates
+    assert_ne!(
+        cache_key(KeyStrategy::Hashed, "tileset", 10, 1, 2, "pbf"),
+        cache_key(KeyStrategy::Hashed, "tileset", 10, 2, 1, "pbf")
+    );
+    // Other tilesets and formats
+    assert_ne!(cache_key(KeyStrategy::Hashed, "other", 3, 5, 2, "pbf"), key);
+    assert_ne!(
+        cache_key(KeyStrategy::Hashed, "tileset", 3, 5, 2, "mvt"),
+        key
+    );
+}
+
+#[test]
+fn test_etag() {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    let tag = etag(b"0123456789");
+    assert_eq!(tag, "\"a684c7c6-a\"");
+    assert_ne!(etag(b"012345678"), tag);
+    // Gzip compressed content has the tag of the uncompressed content
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(b"0123456789").unwrap();
+    assert_eq!(etag(&gz.finish().unwrap()), tag);
+}
+
+#[test]
+fn test_read_meta_default() {
+    use crate::cache::cache::{Cache, Nocache};
+    use crate::cache::lrucache::Lrucache;
+
+    let cache = Lrucache::new(None, None);
+    cache.write("tileset/0/0/0.pbf", b"0123456789").unwrap();
+    // Entity tag computed from cached content
+    let meta = cache.read_meta("tileset/0/0/0.pbf").unwrap();
+    assert_eq!(meta.etag, etag(b"0123456789"));
+    assert_eq!(meta.content_type, "application/vnd.mapbox-vector-tile");
+    assert!(cache.read_meta("tileset/1/0/0.pbf").is_none());
+    assert!(Nocache.read_meta("tileset/0/0/0.pbf").is_none());
+}
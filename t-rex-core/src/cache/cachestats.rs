@@ -0,0 +1,143 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Cache wrapper counting reads, hits, misses and writes
+
+use crate::cache::cache::{Cache, CacheError, Encoding, TileMeta};
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+#[derive(Default)]
+struct Counters {
+    reads: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    writes: AtomicU64,
+}
+
+/// Counter values at a point in time
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct CacheStatsSnapshot {
+    pub reads: u64,
+    pub hits: u64,
+    pub misses: u64,
+    pub writes: u64,
+}
+
+/// Cache decorator collecting usage statistics. Clones share the counters.
+#[derive(Clone)]
+pub struct CacheStats<C: Cache> {
+    cache: C,
+    counters: Arc<Counters>,
+}
+
+impl<C: Cache> CacheStats<C> {
+    pub fn new(cache: C) -> CacheStats<C> {
+        CacheStats {
+            cache,
+            counters: Arc::new(Counters::default()),
+        }
+    }
+    /// Wrapped cache
+    pub fn inner(&self) -> &C {
+        &self.cache
+    }
+    pub fn snapshot(&self) -> CacheStatsSnapshot {
+        CacheStatsSnapshot {
+            reads: self.counters.reads.load(Ordering::Relaxed),
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            writes: self.counters.writes.load(Ordering::Relaxed),
+        }
+    }
+    /// Count read with result `read`
+    fn count_read(&self, read: Result<bool, CacheError>) -> Result<bool, CacheError> {
+        self.counters.reads.fetch_add(1, Ordering::Relaxed);
+        let hit = read?;
+        if hit {
+            self.counters.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.counters.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(hit)
+    }
+    /// Reset all counters to zero
+    pub fn reset(&self) {
+        self.counters.reads.store(0, Ordering::Relaxed);
+        self.counters.hits.store(0, Ordering::Relaxed);
+        self.counters.misses.store(0, Ordering::Relaxed);
+        self.counters.writes.store(0, Ordering::Relaxed);
+    }
+}
+
+impl<C: Cache> Cache for CacheStats<C> {
+    fn info(&self) -> String {
+        self.cache.info()
+    }
+    fn baseurl(&self) -> String {
+        self.cache.baseurl()
+    }
+    fn read<F>(&self, path: &str, read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
+    {
+        self.count_read(self.cache.read(path, read))
+    }
+    fn read_encoded<F>(&self, path: &str, read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read, Encoding) -> Result<(), io::Error>,
+    {
+        self.count_read(self.cache.read_encoded(path, read))
+    }
+    fn read_meta(&self, path: &str) -> Option<TileMeta> {
+        self.cache.read_meta(path)
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
+        self.counters.writes.fetch_add(1, Ordering::Relaxed);
+        self.cache.write(path, obj)
+    }
+    fn write_stream<F>(&self, path: &str, write: F) -> Result<(), CacheError>
+    where
+        F: FnOnce(&mut dyn Write) -> Result<(), io::Error>,
+    {
+        self.counters.writes.fetch_add(1, Ordering::Relaxed);
+        self.cache.write_stream(path, write)
+    }
+    fn write_batch<I>(&self, tileset_name: &str, tiles: I) -> Result<(), CacheError>
+    where
+        I: IntoIterator<Item = (u8, u32, u32, Vec<u8>)>,
+    {
+        let writes = &self.counters.writes;
+        self.cache.write_batch(
+            tileset_name,
+            tiles.into_iter().inspect(|_| {
+                writes.fetch_add(1, Ordering::Relaxed);
+            }),
+        )
+    }
+    fn preferred_encoding(&self) -> Encoding {
+        self.cache.preferred_encoding()
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.cache.exists(path)
+    }
+    fn exists_many(&self, tileset: &str, coords: &[(u8, u32, u32)]) -> Vec<bool> {
+        self.cache.exists_many(tileset, coords)
+    }
+    fn remove(&self, path: &str) -> bool {
+        self.cache.remove(path)
+    }
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError> {
+        self.cache.delete(tileset, zoom, xtile, ytile)
+    }
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError> {
+        self.cache.delete_tileset(tileset)
+    }
+    /// Not counted in statistics
+    fn healthcheck(&self) -> Result<(), CacheError> {
+        self.cache.healthcheck()
+    }
+}
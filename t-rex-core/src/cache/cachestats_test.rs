@@ -0,0 +1,49 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::cache::{Cache, Nocache};
+use crate::cache::cachestats::{CacheStats, CacheStatsSnapshot};
+use crate::cache::filecache::Filecache;
+use std::env;
+use std::fs;
+
+#[test]
+fn test_cachestats() {
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_stats");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = CacheStats::new(Filecache {
+        basepath: basepath.into(),
+        ..Default::default()
+    });
+    assert!(!cache.read("tileset/0/0/0.pbf", |_| Ok(())).unwrap());
+    cache.write("tileset/0/0/0.pbf", b"0123456789").unwrap();
+    assert!(cache.read("tileset/0/0/0.pbf", |_| Ok(())).unwrap());
+    assert!(cache.clone().read("tileset/0/0/0.pbf", |_| Ok(())).unwrap());
+    assert!(!cache.read("tileset/1/0/0.pbf", |_| Ok(())).unwrap());
+    assert_eq!(
+        cache.snapshot(),
+        CacheStatsSnapshot {
+            reads: 4,
+            hits: 2,
+            misses: 2,
+            writes: 1,
+        }
+    );
+
+    cache.reset();
+    assert_eq!(cache.snapshot(), CacheStatsSnapshot::default());
+}
+
+#[test]
+fn test_nocache_stats() {
+    let cache = CacheStats::new(Nocache);
+    cache.write("tileset/0/0/0.pbf", b"0123456789").unwrap();
+    assert!(!cache.read("tileset/0/0/0.pbf", |_| Ok(())).unwrap());
+    let stats = cache.snapshot();
+    assert_eq!((stats.hits, stats.misses, stats.writes), (0, 1, 1));
+}
@@ -3,59 +3,939 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
-use crate::cache::cache::Cache;
+use crate::cache::cache::{
+    content_type, crc_etag, etag, is_empty_tile, is_gzip, parse_tile_path_with_format, tile_path,
+    tile_path_with_format, Cache, CacheError, Encoding, ListableCache, TileMeta,
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression, Crc, CrcReader, CrcWriter};
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
 use std::fs::{self, File};
-use std::io::{self, Read, Write};
-use std::path::Path;
+use std::io::{self, BufWriter, Cursor, Read, Write};
+use std::path::{is_separator, Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-#[derive(Clone)]
+/// Counter for unique temporary file names
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of retries for creating cache directories and files
+const CREATE_RETRIES: usize = 3;
+
+/// Brotli compression level (0-11) and window size (log2)
+const BROTLI_QUALITY: u32 = 9;
+const BROTLI_LGWIN: u32 = 22;
+
+/// File suffixes of compressed objects, in lookup order
+const COMPRESSED_SUFFIXES: [(&str, Encoding); 2] =
+    [(".br", Encoding::Brotli), (".gz", Encoding::Gzip)];
+
+/// Directory layout of cached tiles
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum Layout {
+    /// `{z}/{x}/{y}.pbf`
+    #[default]
+    Simple,
+    /// `{z}/{x/1000}/{x%1000}/{y/1000}/{y%1000}.pbf` limiting the number of
+    /// entries per directory
+    FanOut,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Layout, String> {
+        match s {
+            "simple" => Ok(Layout::Simple),
+            "fanout" => Ok(Layout::FanOut),
+            _ => Err(format!("Unknown file cache layout '{}'", s)),
+        }
+    }
+}
+
+/// Row numbering of cached tiles
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum TileScheme {
+    /// Row 0 at the top, as requested by web clients
+    #[default]
+    Xyz,
+    /// Row 0 at the bottom, like in MBTiles
+    Tms,
+}
+
+impl TileScheme {
+    /// Row in this scheme of XYZ row `ytile`. Rows outside of the grid are not flipped.
+    pub fn row(&self, zoom: u8, ytile: u32) -> u32 {
+        match self {
+            TileScheme::Tms if zoom < 32 && ytile < 1 << zoom => (1 << zoom) - 1 - ytile,
+            _ => ytile,
+        }
+    }
+}
+
+impl FromStr for TileScheme {
+    type Err = String;
+    fn from_str(s: &str) -> Result<TileScheme, String> {
+        match s {
+            "xyz" => Ok(TileScheme::Xyz),
+            "tms" => Ok(TileScheme::Tms),
+            _ => Err(format!("Unknown file cache scheme '{}'", s)),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
 pub struct Filecache {
-    pub basepath: String,
+    pub basepath: PathBuf,
     pub baseurl: Option<String>,
+    pub layout: Layout,
+    /// Row numbering in file paths. Paths passed to the cache are always XYZ.
+    pub scheme: TileScheme,
+    /// Store objects compressed, gzip with `.gz` suffix or brotli with `.br` suffix.
+    /// Files with other compressions are still found when reading.
+    pub compression: Encoding,
+    /// Files older than `max_age` are treated as cache miss
+    pub max_age: Option<Duration>,
+    /// Write `.meta` sidecar files with content type and write time
+    pub meta: bool,
+    /// Don't store empty objects and tiles without layers
+    pub skip_empty: bool,
+    /// With `skip_empty`, store empty tiles as zero-byte files instead of skipping them.
+    /// Known empty tiles are then found by `exists` and read as empty object,
+    /// so they are not generated again.
+    pub empty_sentinel: bool,
+    /// Store CRC32 of written objects in `.meta` sidecar files and verify it when reading.
+    /// Corrupt files are removed and reported as cache miss.
+    pub checksum: bool,
+    /// Maximal total size of cache files in bytes.
+    /// The oldest files are removed when a write exceeds the limit.
+    pub max_bytes: Option<u64>,
+    /// Flush written files and their directory entries to stable storage
+    /// before returning from a write
+    pub sync: bool,
+    /// Total size of cache files tracked for `max_bytes`
+    pub usage: DiskUsage,
+}
+
+/// Running total of cache file sizes, shared by clones.
+/// Determined by scanning the cache directory on first use.
+#[derive(Clone, Default)]
+pub struct DiskUsage(Arc<Mutex<Option<u64>>>);
+
+/// Result of a cache directory check
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct FsckReport {
+    /// Removed empty directories
+    pub empty_dirs: Vec<PathBuf>,
+    /// Removed zero-byte files, which are not empty tile sentinels
+    pub empty_files: Vec<PathBuf>,
+    /// Files below tileset directories not matching the tile path layout (kept)
+    pub invalid_paths: Vec<PathBuf>,
+}
+
+/// Content of `.meta` sidecar file
+#[derive(Serialize, Deserialize)]
+struct MetaFile {
+    content_type: String,
+    /// Seconds since Unix epoch
+    modified: u64,
+    /// CRC32 of uncompressed content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    crc32: Option<u32>,
+    /// Entity tag of uncompressed content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// (CRC32, entity tag) of the uncompressed content of gzip compressed `data`
+fn gunzip_digest(data: &[u8]) -> Result<(u32, String), io::Error> {
+    let mut gz = CrcReader::new(GzDecoder::new(data));
+    io::copy(&mut gz, &mut io::sink())?;
+    Ok((gz.crc().sum(), crc_etag(gz.crc())))
+}
+
+impl Filecache {
+    /// File path of a tile in output format `format` (without compression suffix)
+    pub fn path_for_tile(
+        &self,
+        tileset: &str,
+        zoom: u8,
+        xtile: u32,
+        ytile: u32,
+        format: &str,
+    ) -> PathBuf {
+        self.fullpath(&tile_path_with_format(tileset, zoom, xtile, ytile, format))
+    }
+    /// Path relative to basepath according to layout.
+    /// Components which could escape basepath, e.g. in tileset names, are encoded.
+    fn relpath(&self, path: &str) -> String {
+        let (tileset, zoom, xtile, ytile, format) = match parse_tile_path_with_format(path) {
+            Some(tile) => tile,
+            None => return encode_path_components(path),
+        };
+        let tileset = encode_path_components(tileset);
+        let tileset = tileset.as_str();
+        let ytile = self.scheme.row(zoom, ytile);
+        match self.layout {
+            Layout::Simple => tile_path_with_format(tileset, zoom, xtile, ytile, format),
+            Layout::FanOut => format!(
+                "{}/{}/{}/{}/{}/{}.{}",
+                tileset,
+                zoom,
+                xtile / 1000,
+                xtile % 1000,
+                ytile / 1000,
+                ytile % 1000,
+                format
+            ),
+        }
+    }
+    fn fullpath(&self, path: &str) -> PathBuf {
+        let relpath = self.relpath(path);
+        let mut fullpath = self.basepath.clone();
+        for part in relpath.split('/').filter(|part| !part.is_empty()) {
+            fullpath.push(part);
+        }
+        fullpath
+    }
+    fn fullpath_encoded(&self, path: &str, encoding: Encoding) -> PathBuf {
+        with_suffix(self.fullpath(path), suffix(encoding))
+    }
+    fn fullpath_meta(&self, path: &str) -> PathBuf {
+        with_suffix(self.fullpath(path), ".meta")
+    }
+    /// Entity tag of uncompressed file content
+    fn content_etag(&self, fullpath: &Path, encoding: Encoding) -> Option<String> {
+        let mut data = Vec::new();
+        let result = File::open(fullpath).and_then(|f| encoding.decoder(f).read_to_end(&mut data));
+        match result {
+            Ok(_) => Some(etag(&data)),
+            Err(e) => {
+                warn!("Filecache.read_meta {}: {}", fullpath.display(), e);
+                None
+            }
+        }
+    }
+    /// Checksum stored in sidecar file
+    fn stored_checksum(&self, path: &str) -> Option<u32> {
+        let json = fs::read(self.fullpath_meta(path)).ok()?;
+        serde_json::from_slice::<MetaFile>(&json).ok()?.crc32
+    }
+    /// Read content of cache file, verifying its checksum.
+    /// Returns `Ok(None)` if the file is missing or corrupt.
+    fn read_verified(
+        &self,
+        path: &str,
+        fullpath: &Path,
+        encoding: Encoding,
+        expected: u32,
+    ) -> Result<Option<Vec<u8>>, io::Error> {
+        let mut data = Vec::new();
+        let result = match File::open(fullpath) {
+            Ok(f) => encoding.decoder(f).read_to_end(&mut data),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        match result {
+            Ok(_) if crc32(&data) == expected => Ok(Some(data)),
+            Err(e) if encoding == Encoding::Identity => Err(e),
+            // Checksum mismatch or invalid compressed stream
+            _ => {
+                warn!(
+                    "Filecache.read {}: corrupt file removed",
+                    fullpath.display()
+                );
+                self.remove(path);
+                Ok(None)
+            }
+        }
+    }
+    /// Existing and not expired cache file (path, compression)
+    fn lookup(&self, path: &str) -> Option<(PathBuf, Encoding)> {
+        for &(_, encoding) in &COMPRESSED_SUFFIXES {
+            let fullpath = self.fullpath_encoded(path, encoding);
+            if self.is_valid(&fullpath) {
+                return Some((fullpath, encoding));
+            }
+        }
+        let fullpath = self.fullpath(path);
+        if self.is_valid(&fullpath) {
+            return Some((fullpath, Encoding::Identity));
+        }
+        None
+    }
+    fn is_valid(&self, fullpath: &Path) -> bool {
+        let meta = match fs::metadata(fullpath) {
+            Ok(meta) => meta,
+            Err(_) => return false,
+        };
+        match (self.max_age, meta.modified().map(|t| t.elapsed())) {
+            (Some(max_age), Ok(Ok(age))) => age <= max_age,
+            _ => true,
+        }
+    }
+    /// Files of object `path` in all encodings, including its sidecar file
+    fn object_files(&self, path: &str) -> Vec<PathBuf> {
+        COMPRESSED_SUFFIXES
+            .iter()
+            .map(|&(_, encoding)| self.fullpath_encoded(path, encoding))
+            .chain(vec![self.fullpath(path), self.fullpath_meta(path)])
+            .collect()
+    }
+    /// Size of the files of object `path` counted for `max_bytes` (0 without limit)
+    fn object_usage(&self, path: &str) -> u64 {
+        if self.max_bytes.is_none() {
+            return 0;
+        }
+        self.object_files(path)
+            .iter()
+            .filter_map(|f| fs::metadata(f).ok())
+            .map(|m| m.len())
+            .sum()
+    }
+    /// Add the size change of object `path` from `old_usage` to the running total and
+    /// remove the oldest objects if `max_bytes` is exceeded. Object `path` is kept.
+    fn limit_usage(&self, path: &str, old_usage: u64) {
+        let max_bytes = match self.max_bytes {
+            Some(max_bytes) => max_bytes,
+            None => return,
+        };
+        let mut usage = self.usage.0.lock().unwrap();
+        let total = match *usage {
+            Some(total) => (total + self.object_usage(path)).saturating_sub(old_usage),
+            None => cache_files(&self.basepath).iter().map(|f| f.2).sum(),
+        };
+        *usage = Some(if total > max_bytes {
+            self.evict(max_bytes, &self.fullpath(path))
+        } else {
+            total
+        });
+    }
+    /// Remove oldest objects except `keep` together with their files in other encodings
+    /// and sidecar files, until total size is within `max_bytes`. Returns the new total size.
+    fn evict(&self, max_bytes: u64, keep: &Path) -> u64 {
+        // Latest modification time and files with size of each object
+        let mut objects: BTreeMap<PathBuf, (SystemTime, Vec<(PathBuf, u64)>)> = BTreeMap::new();
+        let mut total = 0;
+        for (modified, fpath, size) in cache_files(&self.basepath) {
+            let object = objects
+                .entry(object_path(&fpath))
+                .or_insert((UNIX_EPOCH, Vec::new()));
+            object.0 = object.0.max(modified);
+            object.1.push((fpath, size));
+            total += size;
+        }
+        let mut objects = objects
+            .into_iter()
+            .map(|(opath, (modified, files))| (modified, opath, files))
+            .collect::<Vec<_>>();
+        objects.sort();
+        for (_, opath, files) in objects {
+            if total <= max_bytes {
+                break;
+            }
+            if opath == keep {
+                continue;
+            }
+            debug!("Filecache.evict {}", opath.display());
+            for (fpath, size) in files {
+                if fs::remove_file(&fpath).is_ok() {
+                    total -= size;
+                }
+            }
+        }
+        total
+    }
+    /// Remove files of object stored with other encodings than `encoding`, which `lookup`
+    /// could return instead of the written file. Without sidecar files, a sidecar
+    /// file written before is removed as well.
+    fn remove_other_encodings(&self, path: &str, encoding: Encoding) -> Result<(), io::Error> {
+        let encodings = COMPRESSED_SUFFIXES
+            .iter()
+            .map(|&(_, encoding)| encoding)
+            .chain(Some(Encoding::Identity));
+        for other in encodings.filter(|&other| other != encoding) {
+            ignore_not_found(fs::remove_file(self.fullpath_encoded(path, other)))?;
+        }
+        if !(self.meta || self.checksum) {
+            ignore_not_found(fs::remove_file(self.fullpath_meta(path)))?;
+        }
+        Ok(())
+    }
+    /// Write `.meta` sidecar file of object with checksum `crc` and entity tag `etag`
+    fn write_meta(&self, path: &str, crc: u32, etag: String) -> Result<(), io::Error> {
+        let modified = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let json = serde_json::to_vec(&MetaFile {
+            content_type: content_type(path).to_string(),
+            modified,
+            crc32: if self.checksum { Some(crc) } else { None },
+            etag: Some(etag),
+        })
+        .map_err(io::Error::from)?;
+        write_atomic(&self.fullpath_meta(path), self.sync, |f| f.write_all(&json))
+    }
 }
 
 impl Cache for Filecache {
     fn info(&self) -> String {
-        format!("Tile cache directory: {}", self.basepath)
+        format!("Tile cache directory: {}", self.basepath.display())
     }
     fn baseurl(&self) -> String {
         self.baseurl
             .clone()
             .unwrap_or("http://localhost:6767".to_string())
     }
-    fn read<F>(&self, path: &str, mut read: F) -> bool
+    fn read<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
     where
-        F: FnMut(&mut dyn Read),
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
     {
-        let fullpath = format!("{}/{}", self.basepath, path);
-        debug!("Filecache.read {}", fullpath);
+        self.read_encoded(path, |f, encoding| read(&mut *encoding.decoder(f)))
+    }
+    /// Files with `.gz` or `.br` suffix are passed compressed.
+    /// Files with verified checksum are passed decompressed.
+    fn read_encoded<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read, Encoding) -> Result<(), io::Error>,
+    {
+        let (fullpath, encoding) = match self.lookup(path) {
+            Some(found) => found,
+            None => return Ok(false),
+        };
+        debug!("Filecache.read {}", fullpath.display());
+        if self.checksum {
+            if let Some(expected) = self.stored_checksum(path) {
+                return match self.read_verified(path, &fullpath, encoding, expected)? {
+                    Some(data) => {
+                        read(&mut Cursor::new(data), Encoding::Identity)?;
+                        Ok(true)
+                    }
+                    None => Ok(false),
+                };
+            }
+        }
         match File::open(&fullpath) {
             Ok(mut f) => {
-                read(&mut f);
-                true
+                read(&mut f, encoding)?;
+                Ok(true)
+            }
+            // Removed in the meantime
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+    /// Metadata of cached object. Without sidecar file, the file
+    /// modification time and a content type derived from the path are returned.
+    /// The entity tag is computed from the file content if it is not stored in the sidecar file.
+    fn read_meta(&self, path: &str) -> Option<TileMeta> {
+        let (fullpath, encoding) = self.lookup(path)?;
+        if let Ok(json) = fs::read(self.fullpath_meta(path)) {
+            match serde_json::from_slice::<MetaFile>(&json) {
+                Ok(MetaFile {
+                    content_type,
+                    modified,
+                    etag: Some(etag),
+                    ..
+                }) => {
+                    return Some(TileMeta {
+                        content_type,
+                        modified: UNIX_EPOCH + Duration::from_secs(modified),
+                        etag,
+                    })
+                }
+                // Written before entity tags were stored
+                Ok(meta) => {
+                    return Some(TileMeta {
+                        content_type: meta.content_type,
+                        modified: UNIX_EPOCH + Duration::from_secs(meta.modified),
+                        etag: self.content_etag(&fullpath, encoding)?,
+                    })
+                }
+                Err(e) => warn!("Filecache.read_meta {}: {}", path, e),
+            }
+        }
+        let modified = fs::metadata(&fullpath).and_then(|m| m.modified()).ok()?;
+        Some(TileMeta {
+            content_type: content_type(path).to_string(),
+            modified,
+            etag: self.content_etag(&fullpath, encoding)?,
+        })
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
+        let empty = self.skip_empty && is_empty_tile(obj);
+        if empty && !self.empty_sentinel {
+            debug!("Filecache.write {} - Skipping empty tile", path);
+            // A tile cached before is outdated
+            self.remove(path);
+            return Ok(());
+        }
+        // Empty tile sentinels are uncompressed zero-byte files
+        let (obj, encoding) = if empty {
+            (&[][..], Encoding::Identity)
+        } else {
+            (obj, self.compression)
+        };
+        let old_usage = self.object_usage(path);
+        // Gzip compressed objects are stored as is instead of compressing them twice
+        let precompressed = encoding == Encoding::Gzip && is_gzip(obj);
+        let fullpath = self.fullpath_encoded(path, encoding);
+        debug!("Filecache.write {}", fullpath.display());
+        create_parent_dirs(&fullpath)?;
+        let mut attempts = 0;
+        let compression = if precompressed {
+            Encoding::Identity
+        } else {
+            encoding
+        };
+        while let Err(e) = write_atomic(&fullpath, self.sync, |f| {
+            write_encoded(f, compression, |out| out.write_all(obj))
+        }) {
+            // Directory removed in the meantime, e.g. by a concurrent delete
+            if e.kind() != io::ErrorKind::NotFound || attempts >= CREATE_RETRIES {
+                return Err(e.into());
+            }
+            attempts += 1;
+            create_parent_dirs(&fullpath)?;
+        }
+        self.remove_other_encodings(path, encoding)?;
+        if self.meta || self.checksum {
+            if precompressed {
+                let (crc, etag) = gunzip_digest(obj)?;
+                self.write_meta(path, crc, etag)?;
+            } else {
+                self.write_meta(path, crc32(obj), etag(obj))?;
             }
-            Err(_e) => false,
         }
+        self.limit_usage(path, old_usage);
+        Ok(())
     }
-    fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error> {
-        let fullpath = format!("{}/{}", self.basepath, path);
-        debug!("Filecache.write {}", fullpath);
-        let p = Path::new(&fullpath);
-        fs::create_dir_all(p.parent().unwrap())?;
-        let mut f = File::create(&fullpath)?;
-        f.write_all(obj)
+    /// Stream object into the cache file. With `skip_empty`, the object is buffered
+    /// to detect empty tiles. The cache directory is not created again if it is
+    /// removed concurrently, since `write` can't be repeated.
+    fn write_stream<F>(&self, path: &str, write: F) -> Result<(), CacheError>
+    where
+        F: FnOnce(&mut dyn Write) -> Result<(), io::Error>,
+    {
+        if self.skip_empty {
+            let mut obj = Vec::new();
+            write(&mut obj)?;
+            return self.write(path, &obj);
+        }
+        let old_usage = self.object_usage(path);
+        let fullpath = self.fullpath_encoded(path, self.compression);
+        debug!("Filecache.write_stream {}", fullpath.display());
+        create_parent_dirs(&fullpath)?;
+        // (CRC32, entity tag) of uncompressed content
+        let mut digest = (0, String::new());
+        write_atomic(&fullpath, self.sync, |f| {
+            write_encoded(f, self.compression, |out| {
+                let mut out = CrcWriter::new(out);
+                write(&mut out)?;
+                digest = (out.crc().sum(), crc_etag(out.crc()));
+                Ok(())
+            })
+        })?;
+        self.remove_other_encodings(path, self.compression)?;
+        if self.meta || self.checksum {
+            self.write_meta(path, digest.0, digest.1)?;
+        }
+        self.limit_usage(path, old_usage);
+        Ok(())
     }
 
+    fn preferred_encoding(&self) -> Encoding {
+        self.compression
+    }
     fn exists(&self, path: &str) -> bool {
-        let fullpath = format!("{}/{}", self.basepath, path);
-        Path::new(&fullpath).exists()
+        self.lookup(path).is_some()
     }
 
     fn remove(&self, path: &str) -> bool {
-        let fullpath = format!("{}/{}", self.basepath, path);
-        match fs::remove_file(fullpath) {
-            Ok(_) => true,
-            Err(_) => false,
+        let old_usage = self.object_usage(path);
+        let _ = fs::remove_file(self.fullpath_meta(path));
+        let mut removed = fs::remove_file(self.fullpath(path)).is_ok();
+        for &(_, encoding) in &COMPRESSED_SUFFIXES {
+            removed |= fs::remove_file(self.fullpath_encoded(path, encoding)).is_ok();
+        }
+        self.limit_usage(path, old_usage);
+        removed
+    }
+
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError> {
+        let path = tile_path(tileset, zoom, xtile, ytile);
+        debug!("Filecache.delete {}", path);
+        let old_usage = self.object_usage(&path);
+        ignore_not_found(fs::remove_file(self.fullpath_meta(&path)))?;
+        for &(_, encoding) in &COMPRESSED_SUFFIXES {
+            ignore_not_found(fs::remove_file(self.fullpath_encoded(&path, encoding)))?;
+        }
+        ignore_not_found(fs::remove_file(self.fullpath(&path)))?;
+        self.limit_usage(&path, old_usage);
+        Ok(())
+    }
+
+    /// Tileset names not being a single path component, like `..` or `tileset/0`, are rejected
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError> {
+        if matches!(tileset, "" | "." | "..") || tileset.contains(is_separator) {
+            return Err(CacheError::InvalidTileset(tileset.to_string()));
+        }
+        let fullpath = self.fullpath(tileset);
+        debug!("Filecache.delete_tileset {}", fullpath.display());
+        ignore_not_found(fs::remove_dir_all(fullpath))?;
+        // Total is determined again on next write
+        *self.usage.0.lock().unwrap() = None;
+        Ok(())
+    }
+}
+
+impl ListableCache for Filecache {
+    fn list(&self, tileset: &str) -> Box<dyn Iterator<Item = (u8, u32, u32)>> {
+        let depth = match self.layout {
+            Layout::Simple => 2,
+            Layout::FanOut => 4,
+        };
+        let mut tiles = BTreeSet::new();
+        for (zoom, zoompath) in dir_entries(&self.fullpath(tileset)) {
+            let zoom = match zoom.parse::<u8>() {
+                Ok(zoom) => zoom,
+                Err(_) => continue,
+            };
+            let mut files = Vec::new();
+            walk_files(&zoompath, depth, &mut Vec::new(), &mut files);
+            for (parts, fpath) in files {
+                if !self.is_valid(&fpath) {
+                    continue;
+                }
+                if let Some((xtile, ytile)) = self.parse_tile_parts(&parts) {
+                    tiles.insert((zoom, xtile, self.scheme.row(zoom, ytile)));
+                }
+            }
+        }
+        Box::new(tiles.into_iter())
+    }
+}
+
+impl Filecache {
+    /// Tile coordinates from path components below zoom directory
+    fn parse_tile_parts(&self, parts: &[String]) -> Option<(u32, u32)> {
+        match self.parse_tile_parts_with_format(parts)? {
+            (xtile, ytile, "pbf") => Some((xtile, ytile)),
+            _ => None,
+        }
+    }
+    /// Tile coordinates and format (file extension) from path components below zoom directory
+    fn parse_tile_parts_with_format<'p>(&self, parts: &'p [String]) -> Option<(u32, u32, &'p str)> {
+        let last = parts.last()?;
+        let last = COMPRESSED_SUFFIXES
+            .iter()
+            .find_map(|(suffix, _)| last.strip_suffix(suffix))
+            .unwrap_or(last);
+        let (ytile, format) = last.split_once('.')?;
+        let mut nums = Vec::with_capacity(parts.len());
+        for part in &parts[..parts.len() - 1] {
+            nums.push(part.parse::<u32>().ok()?);
+        }
+        nums.push(ytile.parse::<u32>().ok()?);
+        let (xtile, ytile) = match (self.layout, &nums[..]) {
+            (Layout::Simple, &[xtile, ytile]) => (xtile, ytile),
+            (Layout::FanOut, &[x1, x2, y1, y2]) if x2 < 1000 && y2 < 1000 => (
+                x1.checked_mul(1000)?.checked_add(x2)?,
+                y1.checked_mul(1000)?.checked_add(y2)?,
+            ),
+            _ => return None,
+        };
+        Some((xtile, ytile, format))
+    }
+    /// Check cache directory: remove empty directories and zero-byte files which
+    /// are not empty tile sentinels, and report files not matching the tile path layout.
+    /// Files of writes in progress are ignored.
+    pub fn fsck(&self) -> Result<FsckReport, io::Error> {
+        let mut report = FsckReport::default();
+        // Missing or unreadable cache directory
+        fs::read_dir(&self.basepath)?;
+        self.fsck_dir(&self.basepath, &mut Vec::new(), &mut report)?;
+        info!(
+            "Filecache.fsck {}: {} empty directories and {} empty files removed, {} invalid paths",
+            self.basepath.display(),
+            report.empty_dirs.len(),
+            report.empty_files.len(),
+            report.invalid_paths.len()
+        );
+        Ok(report)
+    }
+    fn fsck_dir(
+        &self,
+        dir: &Path,
+        parts: &mut Vec<String>,
+        report: &mut FsckReport,
+    ) -> Result<(), io::Error> {
+        for (name, path) in dir_entries(dir) {
+            let meta = match fs::symlink_metadata(&path) {
+                Ok(meta) => meta,
+                Err(_) => continue,
+            };
+            parts.push(name);
+            if meta.is_dir() {
+                self.fsck_dir(&path, parts, report)?;
+                // Fails if a file was written in the meantime
+                if dir_entries(&path).is_empty() && fs::remove_dir(&path).is_ok() {
+                    debug!("Filecache.fsck {}: empty directory removed", path.display());
+                    report.empty_dirs.push(path);
+                }
+            } else if !parts[parts.len() - 1].ends_with(".tmp") {
+                self.fsck_file(path, meta.len(), parts, report)?;
+            }
+            parts.pop();
+        }
+        Ok(())
+    }
+    /// Check file with path components `parts` below the cache directory
+    fn fsck_file(
+        &self,
+        path: PathBuf,
+        size: u64,
+        parts: &[String],
+        report: &mut FsckReport,
+    ) -> Result<(), io::Error> {
+        // Files in cache directory, e.g. tileset metadata
+        if parts.len() == 1 {
+            return Ok(());
+        }
+        let name = &parts[parts.len() - 1];
+        let sidecar = name.ends_with(".meta");
+        let mut tile_parts = parts[2..].to_vec();
+        if let Some(last) = tile_parts.last_mut() {
+            *last = last.trim_end_matches(".meta").to_string();
+        }
+        let valid = parts[1].parse::<u8>().is_ok()
+            && self.parse_tile_parts_with_format(&tile_parts).is_some();
+        let compressed = COMPRESSED_SUFFIXES
+            .iter()
+            .any(|(suffix, _)| name.ends_with(suffix));
+        let sentinel = self.empty_sentinel && valid && !sidecar && !compressed;
+        if size == 0 && !sentinel {
+            ignore_not_found(fs::remove_file(&path))?;
+            debug!("Filecache.fsck {}: empty file removed", path.display());
+            report.empty_files.push(path);
+        } else if !valid {
+            warn!("Filecache.fsck {}: invalid tile path", path.display());
+            report.invalid_paths.push(path);
+        }
+        Ok(())
+    }
+}
+
+/// Collect files exactly `depth` levels below `dir` with their path components
+fn walk_files(
+    dir: &Path,
+    depth: usize,
+    parts: &mut Vec<String>,
+    files: &mut Vec<(Vec<String>, PathBuf)>,
+) {
+    for (name, path) in dir_entries(dir) {
+        parts.push(name);
+        if depth == 1 {
+            files.push((parts.clone(), path));
+        } else {
+            walk_files(&path, depth - 1, parts, files);
+        }
+        parts.pop();
+    }
+}
+
+/// Encode `.` and `..` path components and backslashes (path separator on Windows)
+fn encode_path_components(path: &str) -> String {
+    path.split('/')
+        .map(|part| match part {
+            "." => "%2E".to_string(),
+            ".." => "%2E%2E".to_string(),
+            _ => part.replace('\\', "%5C"),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// File suffix of objects stored with `encoding`
+fn suffix(encoding: Encoding) -> &'static str {
+    match encoding {
+        Encoding::Identity => "",
+        Encoding::Gzip => ".gz",
+        Encoding::Brotli => ".br",
+    }
+}
+
+/// Write into `f` with compression `encoding`
+fn write_encoded<F>(f: &mut File, encoding: Encoding, write: F) -> Result<(), io::Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), io::Error>,
+{
+    let mut out = BufWriter::new(f);
+    match encoding {
+        Encoding::Identity => write(&mut out)?,
+        Encoding::Gzip => {
+            let mut gz = GzEncoder::new(&mut out, Compression::default());
+            write(&mut gz)?;
+            gz.finish()?;
+        }
+        Encoding::Brotli => {
+            let mut br =
+                brotli::CompressorWriter::new(&mut out, 4096, BROTLI_QUALITY, BROTLI_LGWIN);
+            write(&mut br)?;
+            // `into_inner` doesn't report errors. After flushing, the
+            // stream trailer is only written into the buffer of `out`.
+            br.flush()?;
+            br.into_inner();
+        }
+    }
+    out.flush()
+}
+
+/// Path with `suffix` appended to the file name
+fn with_suffix(path: PathBuf, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    path.into()
+}
+
+/// Path of the object stored in file `fpath`, without encoding or sidecar suffix
+fn object_path(fpath: &Path) -> PathBuf {
+    let name = match fpath.to_str() {
+        Some(name) => name,
+        None => return fpath.to_path_buf(),
+    };
+    COMPRESSED_SUFFIXES
+        .iter()
+        .map(|&(suffix, _)| suffix)
+        .chain(Some(".meta"))
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .map(PathBuf::from)
+        .unwrap_or_else(|| fpath.to_path_buf())
+}
+
+/// All files below `dir` as (modification time, path, size),
+/// without temporary files of writes in progress
+fn cache_files(dir: &Path) -> Vec<(SystemTime, PathBuf, u64)> {
+    let mut files = Vec::new();
+    for (name, path) in dir_entries(dir) {
+        let meta = match fs::metadata(&path) {
+            Ok(meta) => meta,
+            Err(_) => continue,
+        };
+        if meta.is_dir() {
+            files.append(&mut cache_files(&path));
+        } else if !name.ends_with(".tmp") {
+            let modified = meta.modified().unwrap_or(UNIX_EPOCH);
+            files.push((modified, path, meta.len()));
+        }
+    }
+    files
+}
+
+/// Create parent directories of `fullpath`. Directories created
+/// by concurrent writers in the meantime are not an error.
+fn create_parent_dirs(fullpath: &Path) -> Result<(), io::Error> {
+    let dir = match fullpath.parent() {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+    let mut attempts = 0;
+    loop {
+        match fs::create_dir_all(dir) {
+            Ok(_) => return Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists && dir.is_dir() => return Ok(()),
+            Err(ref e)
+                if attempts < CREATE_RETRIES
+                    && matches!(
+                        e.kind(),
+                        io::ErrorKind::AlreadyExists | io::ErrorKind::NotFound
+                    ) =>
+            {
+                attempts += 1
+            }
+            Err(e) => return Err(e),
         }
     }
 }
+
+/// Write into temporary file in the same directory and rename it
+/// into place, so readers never see partially written files.
+/// With `sync`, the file and the renamed directory entry are flushed to disk.
+fn write_atomic<F>(fullpath: &Path, sync: bool, write: F) -> Result<(), io::Error>
+where
+    F: FnOnce(&mut File) -> Result<(), io::Error>,
+{
+    let tmppath = with_suffix(
+        fullpath.to_path_buf(),
+        &format!(
+            ".{}-{}.tmp",
+            process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ),
+    );
+    let result = File::create(&tmppath)
+        .and_then(|mut f| {
+            write(&mut f)?;
+            if sync {
+                f.sync_all()?;
+            }
+            Ok(())
+        })
+        .and_then(|_| fs::rename(&tmppath, fullpath))
+        .and_then(|_| {
+            if sync {
+                sync_parent_dir(fullpath)
+            } else {
+                Ok(())
+            }
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmppath);
+    }
+    result
+}
+
+/// Flush directory entries of the directory containing `path` to disk
+fn sync_parent_dir(path: &Path) -> Result<(), io::Error> {
+    // Directories can't be opened as files on Windows
+    #[cfg(unix)]
+    {
+        if let Some(dir) = path.parent() {
+            File::open(dir)?.sync_all()?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Directory entries as (file name, full path).
+/// Unreadable entries and entries with non-UTF8 names are skipped.
+fn dir_entries(dir: &Path) -> Vec<(String, PathBuf)> {
+    match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                Some((name, entry.path()))
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn ignore_not_found(result: Result<(), io::Error>) -> Result<(), io::Error> {
+    match result {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
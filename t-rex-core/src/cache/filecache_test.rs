@@ -3,7 +3,7 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
-use crate::cache::cache::Cache;
+use crate::cache::cache::{Cache, CacheError, Encoding, Nocache};
 use crate::cache::filecache::Filecache;
 use std::fs;
 use std::path::Path;
@@ -18,27 +18,1489 @@ fn test_dircache() {
     let _ = fs::remove_dir_all(&basepath);
 
     let cache = Filecache {
-        basepath: basepath,
+        basepath: basepath.into(),
         baseurl: Some("http://localhost:6767".to_string()),
+        ..Default::default()
     };
     let path = "tileset/0/1/2.pbf";
-    let fullpath = format!("{}/{}", cache.basepath, path);
+    let fullpath = format!("{}/{}", cache.basepath.display(), path);
     let obj = "0123456789";
 
     // Cache miss
-    assert_eq!(cache.read(path, |_| {}), false);
+    assert_eq!(cache.read(path, |_| Ok(())).unwrap(), false);
 
     // Write into cache
     let _ = cache.write(path, obj.as_bytes());
     assert!(Path::new(&fullpath).exists());
 
     // Cache hit
-    assert_eq!(cache.read(path, |_| {}), true);
+    assert_eq!(cache.read(path, |_| Ok(())).unwrap(), true);
 
     // Read from cache
     let mut s = String::new();
-    cache.read(path, |f| {
-        let _ = f.read_to_string(&mut s);
-    });
+    cache
+        .read(path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap();
     assert_eq!(&s, "0123456789");
 }
+
+#[test]
+fn test_dircache_read_to_vec() {
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_read_to_vec");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        ..Default::default()
+    };
+    let path = "tileset/0/0/0.pbf";
+    assert_eq!(cache.read_to_vec(path).unwrap(), None);
+    cache.write(path, b"0123456789").unwrap();
+    assert_eq!(
+        cache.read_to_vec(path).unwrap(),
+        Some(b"0123456789".to_vec())
+    );
+
+    // Compressed files are decoded
+    let gzip = Filecache {
+        compression: Encoding::Gzip,
+        ..cache.clone()
+    };
+    gzip.write("tileset/1/0/0.pbf", b"compressed").unwrap();
+    assert_eq!(
+        cache.read_to_vec("tileset/1/0/0.pbf").unwrap(),
+        Some(b"compressed".to_vec())
+    );
+
+    // Read errors are returned
+    let fullpath = format!("{}/tileset/2/0/0.pbf", cache.basepath.display());
+    fs::create_dir_all(&fullpath).unwrap();
+    assert!(cache.read_to_vec("tileset/2/0/0.pbf").is_err());
+}
+
+#[test]
+fn test_dircache_gzip() {
+    use std::env;
+    use std::io::Read;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_gzip");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        compression: Encoding::Gzip,
+        ..Default::default()
+    };
+    let path = "tileset/0/1/2.pbf";
+    let fullpath = format!("{}/{}", cache.basepath.display(), path);
+    let obj = "0123456789".repeat(100);
+
+    cache.write(path, obj.as_bytes()).unwrap();
+    assert!(!Path::new(&fullpath).exists());
+    assert!(cache.exists(path));
+
+    // Compressed file on disk
+    let mut data = Vec::new();
+    let mut f = fs::File::open(format!("{}.gz", fullpath)).unwrap();
+    f.read_to_end(&mut data).unwrap();
+    assert_eq!(&data[0..2], &[0x1f, 0x8b]);
+    assert!(data.len() < obj.len());
+
+    // Read callback gets uncompressed content
+    let mut s = String::new();
+    assert!(cache
+        .read(path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(s, obj);
+
+    // Uncompressed files written before are still found
+    let plain = Filecache {
+        compression: Encoding::Identity,
+        ..cache.clone()
+    };
+    plain.write("tileset/0/0/0.pbf", b"plain").unwrap();
+    assert!(cache.exists("tileset/0/0/0.pbf"));
+    let mut s = String::new();
+    assert!(cache
+        .read("tileset/0/0/0.pbf", |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(&s, "plain");
+
+    assert!(cache.remove(path));
+    assert!(!cache.exists(path));
+}
+
+#[test]
+fn test_dircache_delete() {
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_delete");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        ..Default::default()
+    };
+    let obj = "0123456789";
+
+    cache.write("tileset/0/1/2.pbf", obj.as_bytes()).unwrap();
+    cache.write("tileset/1/0/0.pbf", obj.as_bytes()).unwrap();
+    assert!(cache.exists("tileset/0/1/2.pbf"));
+
+    cache.delete("tileset", 0, 1, 2).unwrap();
+    assert!(!cache.exists("tileset/0/1/2.pbf"));
+    assert!(cache.exists("tileset/1/0/0.pbf"));
+
+    // Deleting missing tiles succeeds
+    assert!(cache.delete("tileset", 0, 1, 2).is_ok());
+
+    cache.delete_tileset("tileset").unwrap();
+    assert!(!cache.exists("tileset/1/0/0.pbf"));
+    assert!(cache.delete_tileset("tileset").is_ok());
+
+    // Names outside of the tileset directories are rejected
+    cache.write("tileset/0/1/2.pbf", obj.as_bytes()).unwrap();
+    for name in &["", "/", ".", "..", "../tileset", "tileset/0", "./"] {
+        match cache.delete_tileset(name) {
+            Err(CacheError::InvalidTileset(ref invalid)) => assert_eq!(invalid, name),
+            result => panic!("Unexpected result {:?} for '{}'", result, name),
+        }
+    }
+    assert!(cache.exists("tileset/0/1/2.pbf"));
+
+    // Names containing dots are valid
+    cache.write("v1..2/0/1/2.pbf", obj.as_bytes()).unwrap();
+    cache.delete_tileset("v1..2").unwrap();
+    assert!(!cache.exists("v1..2/0/1/2.pbf"));
+    assert!(cache.exists("tileset/0/1/2.pbf"));
+}
+
+#[test]
+fn test_dircache_high_zoom() {
+    use crate::cache::cache::tile_path;
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_z18");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        ..Default::default()
+    };
+    // Coordinates beyond u16 range
+    let path = tile_path("tileset", 18, 137_000, 91_234);
+    assert_eq!(path, "tileset/18/137000/91234.pbf");
+
+    cache.write(&path, b"z18").unwrap();
+    assert!(Path::new(&format!(
+        "{}/tileset/18/137000/91234.pbf",
+        cache.basepath.display()
+    ))
+    .exists());
+    let mut s = String::new();
+    assert!(cache
+        .read(&path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(&s, "z18");
+    // No truncation to u16 (137000 % 65536 = 5928)
+    assert!(!cache.exists("tileset/18/5928/25698.pbf"));
+
+    cache.delete("tileset", 18, 137_000, 91_234).unwrap();
+    assert!(!cache.exists(&path));
+}
+
+#[test]
+fn test_dircache_atomic_write() {
+    use std::env;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_atomic");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        ..Default::default()
+    };
+    let path = "tileset/0/0/0.pbf";
+    let size = 1_000_000;
+
+    let done = Arc::new(AtomicBool::new(false));
+    let reader = {
+        let cache = cache.clone();
+        let done = done.clone();
+        thread::spawn(move || {
+            while !done.load(Ordering::SeqCst) {
+                let mut data = Vec::new();
+                if cache
+                    .read(path, |f| {
+                        f.read_to_end(&mut data)?;
+                        Ok(())
+                    })
+                    .unwrap()
+                {
+                    // Never a partially written file
+                    assert_eq!(data.len(), size);
+                }
+            }
+        })
+    };
+    for i in 0..20u8 {
+        cache.write(path, &vec![i; size]).unwrap();
+    }
+    done.store(true, Ordering::SeqCst);
+    reader.join().unwrap();
+
+    // No temporary files left behind
+    let entries: Vec<_> = fs::read_dir(format!("{}/tileset/0/0", cache.basepath.display()))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(entries, vec!["0.pbf"]);
+}
+
+#[test]
+fn test_dircache_max_age() {
+    use std::env;
+    use std::thread;
+    use std::time::Duration;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_max_age");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        max_age: Some(Duration::from_millis(200)),
+        ..Default::default()
+    };
+    let path = "tileset/0/0/0.pbf";
+    cache.write(path, b"0123456789").unwrap();
+    assert!(cache.exists(path));
+
+    thread::sleep(Duration::from_millis(400));
+    assert!(!cache.exists(path));
+    assert!(!cache.read(path, |_| Ok(())).unwrap());
+    // Stale file is kept for overwriting
+    assert!(Path::new(&format!("{}/{}", cache.basepath.display(), path)).exists());
+
+    cache.write(path, b"0123456789").unwrap();
+    assert!(cache.exists(path));
+}
+
+#[test]
+fn test_dircache_list() {
+    use crate::cache::cache::ListableCache;
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_list");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        ..Default::default()
+    };
+    assert_eq!(cache.list("tileset").count(), 0);
+
+    cache.write("tileset/0/0/0.pbf", b"0").unwrap();
+    cache.write("tileset/3/5/2.pbf", b"3").unwrap();
+    cache.write("tileset/14/8500/5600.pbf", b"14").unwrap();
+    // Malformed paths and other files are skipped
+    cache.write("tileset/metadata.json", b"{}").unwrap();
+    cache.write("tileset/x/0/0.pbf", b"").unwrap();
+    cache.write("tileset/1/0/a.pbf", b"").unwrap();
+    cache.write("tileset/1/1/1.json", b"").unwrap();
+    cache.write("other/1/1/1.pbf", b"").unwrap();
+
+    let tiles: Vec<_> = cache.list("tileset").collect();
+    assert_eq!(tiles, vec![(0, 0, 0), (3, 5, 2), (14, 8500, 5600)]);
+}
+
+#[test]
+fn test_dircache_read_error() {
+    use std::env;
+    use std::io;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_read_error");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        compression: Encoding::Gzip,
+        ..Default::default()
+    };
+    let path = "tileset/0/0/0.pbf";
+    cache.write(path, b"0123456789").unwrap();
+
+    // Callback error is distinguishable from cache miss
+    let result = cache.read(path, |_| {
+        Err(io::Error::new(io::ErrorKind::InvalidData, "decode failed"))
+    });
+    assert!(matches!(result, Err(CacheError::Io(ref e)) if e.kind() == io::ErrorKind::InvalidData));
+    assert!(!cache.read("tileset/1/0/0.pbf", |_| Ok(())).unwrap());
+
+    // Decompression errors are reported
+    fs::write(
+        format!("{}/{}.gz", cache.basepath.display(), path),
+        b"no gzip",
+    )
+    .unwrap();
+    let mut data = Vec::new();
+    let result = cache.read(path, |f| {
+        f.read_to_end(&mut data)?;
+        Ok(())
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_dircache_layout() {
+    use crate::cache::cache::ListableCache;
+    use crate::cache::filecache::Layout;
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_layout");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let simple = Filecache {
+        basepath: basepath.clone().into(),
+        ..Default::default()
+    };
+    assert_eq!(
+        simple.path_for_tile("tileset", 18, 137_000, 91_234, "pbf"),
+        dir.join("tileset/18/137000/91234.pbf")
+    );
+
+    let fanout = Filecache {
+        basepath: basepath.clone().into(),
+        layout: Layout::FanOut,
+        ..Default::default()
+    };
+    assert_eq!(
+        fanout.path_for_tile("tileset", 18, 137_000, 91_234, "pbf"),
+        dir.join("tileset/18/137/0/91/234.pbf")
+    );
+    // Other files are not affected
+    fanout.write("tileset/metadata.json", b"{}").unwrap();
+    assert!(Path::new(&format!("{}/tileset/metadata.json", basepath)).exists());
+
+    let path = "tileset/18/137000/91234.pbf";
+    fanout.write(path, b"fanout").unwrap();
+    assert!(fanout
+        .path_for_tile("tileset", 18, 137_000, 91_234, "pbf")
+        .exists());
+    assert!(fanout.exists(path));
+    assert!(!simple.exists(path));
+    let mut s = String::new();
+    assert!(fanout
+        .read(path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(&s, "fanout");
+
+    fanout.write("tileset/2/1/3.pbf", b"fanout").unwrap();
+    let tiles: Vec<_> = fanout.list("tileset").collect();
+    assert_eq!(tiles, vec![(2, 1, 3), (18, 137_000, 91_234)]);
+
+    fanout.delete("tileset", 18, 137_000, 91_234).unwrap();
+    assert!(!fanout.exists(path));
+}
+
+#[test]
+fn test_dircache_meta() {
+    use std::env;
+    use std::time::{Duration, SystemTime};
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_meta");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        meta: true,
+        ..Default::default()
+    };
+    let before = SystemTime::now() - Duration::from_secs(1);
+    cache.write("tileset/0/0/0.pbf", b"0123456789").unwrap();
+    cache.write("tileset.json", b"{}").unwrap();
+    assert!(Path::new(&format!(
+        "{}/tileset/0/0/0.pbf.meta",
+        cache.basepath.display()
+    ))
+    .exists());
+
+    let meta = cache.read_meta("tileset/0/0/0.pbf").unwrap();
+    assert_eq!(meta.content_type, "application/vnd.mapbox-vector-tile");
+    assert!(meta.modified >= before && meta.modified <= SystemTime::now());
+    let meta = cache.read_meta("tileset.json").unwrap();
+    assert_eq!(meta.content_type, "application/json");
+    assert!(cache.read_meta("tileset/1/0/0.pbf").is_none());
+
+    // Sidecar files are not listed as tiles
+    use crate::cache::cache::ListableCache;
+    assert_eq!(cache.list("tileset").collect::<Vec<_>>(), vec![(0, 0, 0)]);
+
+    cache.delete("tileset", 0, 0, 0).unwrap();
+    assert!(!Path::new(&format!(
+        "{}/tileset/0/0/0.pbf.meta",
+        cache.basepath.display()
+    ))
+    .exists());
+}
+
+#[test]
+fn test_dircache_legacy_meta() {
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_legacy_meta");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    // Tiles written without metadata
+    let cache = Filecache {
+        basepath: basepath.into(),
+        ..Default::default()
+    };
+    cache.write("tileset/0/0/0.pbf", b"0123456789").unwrap();
+    let fullpath = format!("{}/tileset/0/0/0.pbf", cache.basepath.display());
+    assert!(!Path::new(&format!("{}.meta", fullpath)).exists());
+
+    let meta = cache.read_meta("tileset/0/0/0.pbf").unwrap();
+    assert_eq!(meta.content_type, "application/vnd.mapbox-vector-tile");
+    let mtime = fs::metadata(&fullpath).unwrap().modified().unwrap();
+    assert_eq!(meta.modified, mtime);
+}
+
+#[test]
+fn test_dircache_etag() {
+    use crate::cache::cache::etag;
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_etag");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.clone().into(),
+        meta: true,
+        ..Default::default()
+    };
+    cache.write("tileset/0/0/0.pbf", b"0123456789").unwrap();
+    let tag = cache.read_meta("tileset/0/0/0.pbf").unwrap().etag;
+    assert_eq!(tag, etag(b"0123456789"));
+    assert!(tag.starts_with('"') && tag.ends_with('"'));
+    // Stable when rewriting the same content
+    cache.write("tileset/0/0/0.pbf", b"0123456789").unwrap();
+    assert_eq!(cache.read_meta("tileset/0/0/0.pbf").unwrap().etag, tag);
+    // Identical content of other tiles has the same tag
+    cache.write("tileset/1/0/0.pbf", b"0123456789").unwrap();
+    assert_eq!(cache.read_meta("tileset/1/0/0.pbf").unwrap().etag, tag);
+    cache.write("tileset/1/1/0.pbf", b"012345678").unwrap();
+    assert_ne!(cache.read_meta("tileset/1/1/0.pbf").unwrap().etag, tag);
+
+    // Computed from uncompressed content without sidecar file
+    let cache = Filecache {
+        basepath: basepath.into(),
+        compression: Encoding::Gzip,
+        ..Default::default()
+    };
+    cache.write("tileset/2/0/0.pbf", b"0123456789").unwrap();
+    assert_eq!(cache.read_meta("tileset/2/0/0.pbf").unwrap().etag, tag);
+}
+
+#[test]
+fn test_dircache_write_stream() {
+    use crate::cache::cache::etag;
+    use crate::cache::lrucache::Lrucache;
+    use std::env;
+    use std::io::{self, Write};
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_write_stream");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let payload: Vec<u8> = (0..10000).map(|i| (i % 251) as u8).collect();
+    let stream = |f: &mut dyn Write| {
+        for chunk in payload.chunks(999) {
+            f.write_all(chunk)?;
+        }
+        Ok(())
+    };
+    fn read_back<C: Cache>(cache: &C, path: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        assert!(cache
+            .read(path, |f| f.read_to_end(&mut data).map(|_| ()))
+            .unwrap());
+        data
+    }
+
+    for &(compression, path, suffix) in &[
+        (Encoding::Identity, "plain/0/0/0.pbf", ""),
+        (Encoding::Gzip, "gzip/0/0/0.pbf", ".gz"),
+        (Encoding::Brotli, "brotli/0/0/0.pbf", ".br"),
+    ] {
+        let cache = Filecache {
+            basepath: basepath.clone().into(),
+            compression,
+            meta: true,
+            checksum: true,
+            ..Default::default()
+        };
+        cache.write_stream(path, stream).unwrap();
+        let data = read_back(&cache, path);
+        assert_eq!(data, payload);
+        assert_eq!(cache.read_meta(path).unwrap().etag, etag(&payload));
+        assert!(Path::new(&format!("{}/{}{}", basepath, path, suffix)).exists());
+    }
+
+    // Errors of the writer are returned and leave no file
+    let cache = Filecache {
+        basepath: basepath.clone().into(),
+        ..Default::default()
+    };
+    let err = cache
+        .write_stream("error/0/0/0.pbf", |f| {
+            f.write_all(b"partial")?;
+            Err(io::Error::new(io::ErrorKind::InvalidData, "failed"))
+        })
+        .unwrap_err();
+    assert_eq!(err.to_string(), "failed");
+    assert!(!cache.exists("error/0/0/0.pbf"));
+    assert_eq!(
+        fs::read_dir(format!("{}/error/0/0", basepath))
+            .unwrap()
+            .count(),
+        0
+    );
+
+    // Buffered by backends without streaming support
+    let cache = Lrucache::new(Some(2), None);
+    cache.write_stream("tileset/0/0/0.pbf", stream).unwrap();
+    let data = read_back(&cache, "tileset/0/0/0.pbf");
+    assert_eq!(data, payload);
+}
+
+#[test]
+fn test_dircache_fsck() {
+    use crate::cache::cache::ListableCache;
+    use std::env;
+    use std::path::PathBuf;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_fsck");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.clone().into(),
+        meta: true,
+        ..Default::default()
+    };
+    cache.write("tileset/0/0/0.pbf", b"0123456789").unwrap();
+    cache.write("tileset.json", b"{}").unwrap();
+    let fullpath = |path: &str| -> PathBuf { format!("{}/{}", basepath, path).into() };
+    fs::create_dir_all(fullpath("tileset/5/3")).unwrap();
+    fs::create_dir_all(fullpath("other/2/1")).unwrap();
+    fs::write(fullpath("other/2/1/1.pbf"), b"").unwrap();
+    fs::write(fullpath("tileset/0/0/1.pbf.gz"), b"").unwrap();
+    fs::write(fullpath("tileset/0/bogus.txt"), b"bogus").unwrap();
+    // Write in progress
+    fs::write(fullpath("tileset/0/0/2.pbf.123-0.tmp"), b"").unwrap();
+
+    let report = cache.fsck().unwrap();
+    let mut empty_dirs = report.empty_dirs.clone();
+    empty_dirs.sort();
+    assert_eq!(
+        empty_dirs,
+        vec![
+            fullpath("other"),
+            fullpath("other/2"),
+            fullpath("other/2/1"),
+            fullpath("tileset/5"),
+            fullpath("tileset/5/3"),
+        ]
+    );
+    let mut empty_files = report.empty_files.clone();
+    empty_files.sort();
+    assert_eq!(
+        empty_files,
+        vec![
+            fullpath("other/2/1/1.pbf"),
+            fullpath("tileset/0/0/1.pbf.gz")
+        ]
+    );
+    assert_eq!(report.invalid_paths, vec![fullpath("tileset/0/bogus.txt")]);
+    assert!(!fullpath("tileset/5").exists());
+    assert!(!fullpath("other").exists());
+    assert!(fullpath("tileset/0/bogus.txt").exists());
+    assert!(fullpath("tileset/0/0/2.pbf.123-0.tmp").exists());
+    assert_eq!(cache.list("tileset").collect::<Vec<_>>(), vec![(0, 0, 0)]);
+    assert!(cache.read_meta("tileset/0/0/0.pbf").is_some());
+    assert!(cache.exists("tileset.json"));
+
+    // Nothing left to clean
+    let report = cache.fsck().unwrap();
+    assert!(report.empty_dirs.is_empty() && report.empty_files.is_empty());
+
+    // Empty tile sentinels are kept
+    let cache = Filecache {
+        basepath: basepath.clone().into(),
+        skip_empty: true,
+        empty_sentinel: true,
+        ..Default::default()
+    };
+    cache.write("tileset/1/0/0.pbf", b"").unwrap();
+    assert!(cache.fsck().unwrap().empty_files.is_empty());
+    assert!(cache.exists("tileset/1/0/0.pbf"));
+
+    let _ = fs::remove_dir_all(&basepath);
+    assert!(cache.fsck().is_err());
+}
+
+#[test]
+fn test_dircache_skip_empty() {
+    use flate2::{write::GzEncoder, Compression};
+    use std::env;
+    use std::io::Write;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_skip_empty");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        skip_empty: true,
+        ..Default::default()
+    };
+    cache.write("tileset/0/0/0.pbf", b"").unwrap();
+    assert!(!cache.exists("tileset/0/0/0.pbf"));
+
+    // Gzip compressed tile without layers
+    let tilegz = GzEncoder::new(Vec::new(), Compression::default())
+        .finish()
+        .unwrap();
+    cache.write("tileset/0/0/0.pbf", &tilegz).unwrap();
+    assert!(!cache.exists("tileset/0/0/0.pbf"));
+
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(b"0123456789").unwrap();
+    cache
+        .write("tileset/0/0/0.pbf", &gz.finish().unwrap())
+        .unwrap();
+    assert!(cache.exists("tileset/0/0/0.pbf"));
+
+    // Skipped empty tile removes the tile written before
+    cache.write("tileset/0/0/0.pbf", b"").unwrap();
+    assert!(!cache.exists("tileset/0/0/0.pbf"));
+
+    // Empty tiles are stored by default
+    let cache = Filecache {
+        skip_empty: false,
+        ..cache
+    };
+    cache.write("tileset/1/0/0.pbf", b"").unwrap();
+    assert!(cache.exists("tileset/1/0/0.pbf"));
+}
+
+#[test]
+fn test_dircache_empty_sentinel() {
+    use flate2::{write::GzEncoder, Compression};
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_empty_sentinel");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        compression: Encoding::Gzip,
+        skip_empty: true,
+        empty_sentinel: true,
+        ..Default::default()
+    };
+    let path = "tileset/0/0/0.pbf";
+    let tilegz = GzEncoder::new(Vec::new(), Compression::default())
+        .finish()
+        .unwrap();
+    cache.write(path, &tilegz).unwrap();
+    assert!(cache.exists(path));
+    // Stored uncompressed as zero-byte file
+    let sentinel = cache.path_for_tile("tileset", 0, 0, 0, "pbf");
+    assert_eq!(fs::metadata(&sentinel).unwrap().len(), 0);
+
+    let mut data = vec![1];
+    assert!(cache
+        .read(path, |f| f.read_to_end(&mut data).map(|_| ()))
+        .unwrap());
+    assert_eq!(data, vec![1]);
+
+    // Sentinel replaces a compressed tile written before
+    let path = "tileset/1/0/0.pbf";
+    cache.write(path, b"0123456789").unwrap();
+    cache.write(path, &tilegz).unwrap();
+    let compressed = format!(
+        "{}.gz",
+        cache.path_for_tile("tileset", 1, 0, 0, "pbf").display()
+    );
+    assert!(!Path::new(&compressed).exists());
+    assert_eq!(cache.read_to_vec(path).unwrap(), Some(Vec::new()));
+}
+
+#[test]
+fn test_dircache_sync() {
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_sync");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        meta: true,
+        sync: true,
+        ..Default::default()
+    };
+    cache.write("tileset/0/0/0.pbf", b"0123456789").unwrap();
+    cache
+        .write_stream("tileset/1/0/0.pbf", |out| out.write_all(b"streamed"))
+        .unwrap();
+    let gzip = Filecache {
+        compression: Encoding::Gzip,
+        ..cache.clone()
+    };
+    gzip.write("tileset/1/1/0.pbf", b"compressed").unwrap();
+
+    let fullpath = |path: &str| format!("{}/{}", cache.basepath.display(), path);
+    assert_eq!(
+        fs::read(fullpath("tileset/0/0/0.pbf")).unwrap(),
+        b"0123456789"
+    );
+    assert_eq!(
+        fs::read(fullpath("tileset/1/0/0.pbf")).unwrap(),
+        b"streamed"
+    );
+    assert!(Path::new(&fullpath("tileset/0/0/0.pbf.meta")).exists());
+    assert!(Path::new(&fullpath("tileset/1/1/0.pbf.gz")).exists());
+    // No temporary files left
+    assert_eq!(fs::read_dir(fullpath("tileset/0/0")).unwrap().count(), 2);
+}
+
+#[test]
+fn test_dircache_checksum() {
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_checksum");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        checksum: true,
+        ..Default::default()
+    };
+    let path = "tileset/0/0/0.pbf";
+    let fullpath = format!("{}/{}", cache.basepath.display(), path);
+    cache.write(path, b"0123456789").unwrap();
+    let mut s = String::new();
+    assert!(cache
+        .read(path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(s, "0123456789");
+
+    // Corrupt file on disk
+    fs::write(&fullpath, b"0123X56789").unwrap();
+    assert!(!cache.read(path, |_| Ok(())).unwrap());
+    // Corrupt file is removed
+    assert!(!Path::new(&fullpath).exists());
+
+    // Corrupt gzip stream
+    let cache = Filecache {
+        compression: Encoding::Gzip,
+        ..cache
+    };
+    cache.write(path, b"0123456789").unwrap();
+    fs::write(format!("{}.gz", fullpath), b"\x1f\x8bgarbage").unwrap();
+    assert!(!cache.read(path, |_| Ok(())).unwrap());
+    assert!(!cache.exists(path));
+
+    // Files without checksum are read unverified
+    let unverified = Filecache {
+        checksum: false,
+        compression: Encoding::Identity,
+        ..cache.clone()
+    };
+    unverified.write(path, b"0123456789").unwrap();
+    fs::write(&fullpath, b"0123X56789").unwrap();
+    assert!(cache.read(path, |_| Ok(())).unwrap());
+}
+
+#[test]
+fn test_dircache_formats() {
+    use crate::cache::cache::tile_path_with_format;
+    use crate::cache::filecache::Layout;
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_formats");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    for layout in &[Layout::Simple, Layout::FanOut] {
+        let cache = Filecache {
+            basepath: basepath.clone().into(),
+            layout: *layout,
+            ..Default::default()
+        };
+        assert_ne!(
+            cache.path_for_tile("tileset", 2, 1, 3, "pbf"),
+            cache.path_for_tile("tileset", 2, 1, 3, "json")
+        );
+        let pbf = tile_path_with_format("tileset", 2, 1, 3, "pbf");
+        let json = tile_path_with_format("tileset", 2, 1, 3, "json");
+        cache.write(&pbf, b"pbf").unwrap();
+        cache.write(&json, b"json").unwrap();
+        for (path, expected) in &[(&pbf, "pbf"), (&json, "json")] {
+            let mut s = String::new();
+            assert!(cache
+                .read(path, |f| {
+                    f.read_to_string(&mut s)?;
+                    Ok(())
+                })
+                .unwrap());
+            assert_eq!(&s, expected);
+        }
+        assert!(cache.path_for_tile("tileset", 2, 1, 3, "json").exists());
+    }
+}
+
+#[test]
+fn test_dircache_read_encoded() {
+    use crate::cache::cache::Encoding;
+    use flate2::read::GzDecoder;
+    use std::env;
+    use std::io::Read;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_read_encoded");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let plain = Filecache {
+        basepath: basepath.into(),
+        ..Default::default()
+    };
+    let gzip = Filecache {
+        compression: Encoding::Gzip,
+        ..plain.clone()
+    };
+    plain.write("tileset/0/0/0.pbf", b"identity").unwrap();
+    gzip.write("tileset/0/0/1.pbf", b"compressed").unwrap();
+
+    let read_encoded = |path| {
+        let mut result = None;
+        let hit = plain
+            .read_encoded(path, |f, encoding| {
+                let mut data = Vec::new();
+                f.read_to_end(&mut data)?;
+                result = Some((encoding, data));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(hit, result.is_some());
+        result
+    };
+
+    assert_eq!(
+        read_encoded("tileset/0/0/0.pbf"),
+        Some((Encoding::Identity, b"identity".to_vec()))
+    );
+
+    // Stored gzip stream is passed as is
+    let (encoding, data) = read_encoded("tileset/0/0/1.pbf").unwrap();
+    assert_eq!(encoding, Encoding::Gzip);
+    assert_eq!(&data[0..2], &[0x1f, 0x8b]);
+    let mut s = String::new();
+    GzDecoder::new(&data[..]).read_to_string(&mut s).unwrap();
+    assert_eq!(s, "compressed");
+
+    assert_eq!(read_encoded("tileset/0/0/2.pbf"), None);
+}
+
+#[test]
+fn test_dircache_scheme() {
+    use crate::cache::cache::ListableCache;
+    use crate::cache::filecache::TileScheme;
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_scheme");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let xyz = Filecache {
+        basepath: basepath.clone().into(),
+        ..Default::default()
+    };
+    let tms = Filecache {
+        scheme: TileScheme::Tms,
+        ..xyz.clone()
+    };
+    // Row 0 of 4 rows at zoom level 2 is row 3 in TMS
+    assert_eq!(
+        xyz.path_for_tile("tileset", 2, 1, 0, "pbf"),
+        dir.join("tileset/2/1/0.pbf")
+    );
+    assert_eq!(
+        tms.path_for_tile("tileset", 2, 1, 0, "pbf"),
+        dir.join("tileset/2/1/3.pbf")
+    );
+
+    let path = "tileset/2/1/0.pbf";
+    tms.write(path, b"tile").unwrap();
+    assert!(Path::new(&dir.join("tileset/2/1/3.pbf")).exists());
+    assert!(tms.exists(path));
+    assert!(!xyz.exists(path));
+    assert!(xyz.exists("tileset/2/1/3.pbf"));
+    assert!(tms.read(path, |_| Ok(())).unwrap());
+    assert_eq!(tms.list("tileset").collect::<Vec<_>>(), vec![(2, 1, 0)]);
+
+    tms.delete("tileset", 2, 1, 0).unwrap();
+    assert!(!tms.exists(path));
+}
+
+#[test]
+fn test_dircache_concurrent_write() {
+    use std::env;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_concurrent_write");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        meta: true,
+        ..Default::default()
+    };
+    let threads = 16;
+    let barrier = Arc::new(Barrier::new(threads));
+    let handles: Vec<_> = (0..threads as u32)
+        .map(|i| {
+            let cache = cache.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                barrier.wait();
+                // Tiles sharing zoom and column directories
+                (0..8)
+                    .map(|y| {
+                        cache.write(&format!("tileset/5/{}/{}.pbf", i % 4, i * 8 + y), b"tile")
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    for handle in handles {
+        for result in handle.join().unwrap() {
+            assert!(result.is_ok(), "{:?}", result);
+        }
+    }
+    for i in 0..threads as u32 {
+        for y in 0..8 {
+            assert!(cache.exists(&format!("tileset/5/{}/{}.pbf", i % 4, i * 8 + y)));
+        }
+    }
+}
+
+#[test]
+fn test_dircache_max_bytes() {
+    use std::env;
+    use std::thread;
+    use std::time::Duration;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_max_bytes");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let tile = [0u8; 100];
+    let cache = Filecache {
+        basepath: basepath.clone().into(),
+        max_bytes: Some(350),
+        ..Default::default()
+    };
+    let disk_usage = || -> u64 {
+        (0..6)
+            .filter_map(|y| fs::metadata(cache.path_for_tile("tileset", 3, 1, y, "pbf")).ok())
+            .map(|m| m.len())
+            .sum()
+    };
+    for y in 0..6 {
+        cache
+            .write(&format!("tileset/3/1/{}.pbf", y), &tile)
+            .unwrap();
+        assert!(disk_usage() <= 350, "{} bytes", disk_usage());
+        // Distinct modification times
+        thread::sleep(Duration::from_millis(10));
+    }
+    // Oldest tiles removed
+    for y in 0..3 {
+        assert!(!cache.exists(&format!("tileset/3/1/{}.pbf", y)));
+    }
+    for y in 3..6 {
+        assert!(cache.exists(&format!("tileset/3/1/{}.pbf", y)));
+    }
+
+    // Total of existing cache files is determined on first write
+    let cache = Filecache {
+        basepath: basepath.clone().into(),
+        max_bytes: Some(250),
+        ..Default::default()
+    };
+    cache.write("tileset/3/1/6.pbf", &tile).unwrap();
+    assert!(!cache.exists("tileset/3/1/3.pbf"));
+    assert!(!cache.exists("tileset/3/1/4.pbf"));
+    assert!(cache.exists("tileset/3/1/5.pbf"));
+    assert!(cache.exists("tileset/3/1/6.pbf"));
+
+    // Overwritten tiles are not counted twice
+    for _ in 0..3 {
+        cache.write("tileset/3/1/6.pbf", &tile).unwrap();
+    }
+    assert!(cache.exists("tileset/3/1/5.pbf"));
+    assert!(cache.exists("tileset/3/1/6.pbf"));
+    cache.delete("tileset", 3, 1, 6).unwrap();
+    cache.write("tileset/3/1/7.pbf", &tile).unwrap();
+    assert!(cache.exists("tileset/3/1/5.pbf"));
+
+    // Tiles are evicted together with their sidecar files
+    let _ = fs::remove_dir_all(&basepath);
+    let cache = Filecache {
+        basepath: basepath.clone().into(),
+        max_bytes: Some(350),
+        meta: true,
+        ..Default::default()
+    };
+    for y in 0..4 {
+        cache
+            .write(&format!("tileset/3/1/{}.pbf", y), &tile)
+            .unwrap();
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(cache.exists("tileset/3/1/3.pbf"));
+    for y in 0..4 {
+        let fullpath = cache.path_for_tile("tileset", 3, 1, y, "pbf");
+        assert_eq!(
+            Path::new(&format!("{}.meta", fullpath.display())).exists(),
+            cache.exists(&format!("tileset/3/1/{}.pbf", y))
+        );
+    }
+}
+
+#[test]
+fn test_dircache_tileset_name() {
+    use std::env;
+    use std::path::Component;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_tileset_name");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.clone().into(),
+        ..Default::default()
+    };
+    let inside_basepath = |path: &Path| {
+        let relpath = path.strip_prefix(&basepath).unwrap();
+        relpath
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+    };
+    for tileset in &["../../etc", "..", "a/../../b", "./..", "..\\..\\etc"] {
+        let path = cache.path_for_tile(tileset, 0, 0, 0, "pbf");
+        assert!(inside_basepath(&path), "{}", path.display());
+
+        let tilepath = format!("{}/0/0/0.pbf", tileset);
+        cache.write(&tilepath, b"tile").unwrap();
+        assert!(path.exists(), "{}", path.display());
+        assert!(cache.exists(&tilepath));
+        cache.delete(tileset, 0, 0, 0).unwrap();
+        assert!(!path.exists());
+
+        cache
+            .write(&format!("{}/metadata.json", tileset), b"{}")
+            .unwrap();
+        // Only single path components are deleted
+        let single_component = cfg!(unix) && *tileset == "..\\..\\etc";
+        assert_eq!(cache.delete_tileset(tileset).is_ok(), single_component);
+        assert_eq!(
+            cache.exists(&format!("{}/metadata.json", tileset)),
+            !single_component
+        );
+    }
+    assert_eq!(
+        cache.path_for_tile("../../etc", 0, 0, 0, "pbf"),
+        dir.join("%2E%2E/%2E%2E/etc/0/0/0.pbf")
+    );
+    // Nothing written next to basepath
+    assert!(!dir.with_file_name("etc").exists());
+    assert!(dir.exists());
+}
+
+#[test]
+fn test_dircache_basepath() {
+    use std::env;
+    use std::path::PathBuf;
+
+    let dir = env::temp_dir().join("t_rex_test_basepath");
+    let _ = fs::remove_dir_all(&dir);
+    let expected = dir.join("tileset").join("2").join("1").join("3.pbf");
+
+    let with_slash = Filecache {
+        basepath: PathBuf::from(format!("{}/", dir.display())),
+        ..Default::default()
+    };
+    let without_slash = Filecache {
+        basepath: dir.clone(),
+        ..Default::default()
+    };
+    assert_eq!(
+        with_slash.path_for_tile("tileset", 2, 1, 3, "pbf"),
+        expected
+    );
+    assert_eq!(
+        without_slash.path_for_tile("tileset", 2, 1, 3, "pbf"),
+        expected
+    );
+
+    with_slash.write("tileset/2/1/3.pbf", b"tile").unwrap();
+    assert!(expected.exists());
+    assert!(without_slash.exists("tileset/2/1/3.pbf"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_dircache_non_utf8_basepath() {
+    use std::env;
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let dir = env::temp_dir().join(OsStr::from_bytes(b"t_rex_test_\xff"));
+    let _ = fs::remove_dir_all(&dir);
+    let cache = Filecache {
+        basepath: dir.clone(),
+        ..Default::default()
+    };
+    cache.write("tileset/2/1/3.pbf", b"tile").unwrap();
+    assert!(dir.join("tileset/2/1/3.pbf").exists());
+    assert!(cache.exists("tileset/2/1/3.pbf"));
+    let mut s = String::new();
+    assert!(cache
+        .read("tileset/2/1/3.pbf", |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(s, "tile");
+    cache.delete_tileset("tileset").unwrap();
+    assert!(!cache.exists("tileset/2/1/3.pbf"));
+}
+
+#[test]
+fn test_dircache_healthcheck() {
+    use crate::cache::cache::{Nocache, HEALTHCHECK_TILESET};
+    use std::env;
+
+    let dir = env::temp_dir().join("t_rex_test_healthcheck");
+    let _ = fs::remove_dir_all(&dir);
+
+    let cache = Filecache {
+        basepath: dir.clone(),
+        compression: Encoding::Gzip,
+        ..Default::default()
+    };
+    cache.healthcheck().unwrap();
+    // Sentinel tile is removed
+    assert!(!dir.join(HEALTHCHECK_TILESET).exists());
+
+    // Basepath below a regular file can't be created
+    fs::write(dir.join("file"), b"").unwrap();
+    let invalid = Filecache {
+        basepath: dir.join("file").join("cache"),
+        ..Default::default()
+    };
+    assert!(invalid.healthcheck().is_err());
+
+    assert!(Nocache.healthcheck().is_ok());
+}
+
+#[test]
+fn test_dircache_write_batch() {
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_batch");
+    let _ = fs::remove_dir_all(&dir);
+
+    let cache = Filecache {
+        basepath: dir.clone(),
+        ..Default::default()
+    };
+    cache
+        .write_batch(
+            "tileset",
+            vec![
+                (0, 0, 0, b"tile0".to_vec()),
+                (1, 1, 0, b"tile1".to_vec()),
+                (2, 3, 3, b"tile2".to_vec()),
+            ],
+        )
+        .unwrap();
+    for (path, obj) in &[
+        ("tileset/0/0/0.pbf", "tile0"),
+        ("tileset/1/1/0.pbf", "tile1"),
+        ("tileset/2/3/3.pbf", "tile2"),
+    ] {
+        let mut s = String::new();
+        assert!(cache
+            .read(path, |f| f.read_to_string(&mut s).map(|_| ()))
+            .unwrap());
+        assert_eq!(&s, obj);
+    }
+}
+
+#[test]
+fn test_dircache_error() {
+    use std::env;
+
+    let dir = env::temp_dir().join("t_rex_test_error");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    // Parent directory can't be created below a regular file
+    fs::write(dir.join("file"), b"").unwrap();
+    let cache = Filecache {
+        basepath: dir.join("file"),
+        ..Default::default()
+    };
+    let err = cache.write("tileset/0/0/0.pbf", b"0123456789").unwrap_err();
+    assert!(matches!(err, CacheError::Io(_)), "{:?}", err);
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+#[test]
+fn test_dircache_brotli() {
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_brotli");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        compression: Encoding::Brotli,
+        ..Default::default()
+    };
+    let path = "tileset/0/1/2.pbf";
+    let fullpath = format!("{}/{}", cache.basepath.display(), path);
+    let obj = "0123456789".repeat(100);
+
+    cache.write(path, obj.as_bytes()).unwrap();
+    assert!(!Path::new(&fullpath).exists());
+    assert!(!Path::new(&format!("{}.gz", fullpath)).exists());
+    assert!(cache.exists(path));
+    assert_eq!(cache.preferred_encoding(), Encoding::Brotli);
+
+    // Compressed file on disk
+    let data = fs::read(format!("{}.br", fullpath)).unwrap();
+    assert!(data.len() < obj.len());
+
+    // Read callback gets uncompressed content
+    let mut s = String::new();
+    assert!(cache
+        .read(path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(s, obj);
+
+    // Stored brotli stream is passed as is
+    let mut encoded = None;
+    assert!(cache
+        .read_encoded(path, |f, encoding| {
+            let mut data = Vec::new();
+            f.read_to_end(&mut data)?;
+            encoded = Some((encoding, data));
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(encoded, Some((Encoding::Brotli, data)));
+
+    // Gzip files written before are still found
+    let gzip = Filecache {
+        compression: Encoding::Gzip,
+        ..cache.clone()
+    };
+    gzip.write("tileset/0/0/0.pbf", b"gzip").unwrap();
+    let mut s = String::new();
+    assert!(cache
+        .read("tileset/0/0/0.pbf", |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(&s, "gzip");
+
+    assert!(cache.remove(path));
+    assert!(!cache.exists(path));
+}
+
+#[test]
+fn test_encoding_from_str() {
+    assert_eq!("none".parse::<Encoding>(), Ok(Encoding::Identity));
+    assert_eq!("gzip".parse::<Encoding>(), Ok(Encoding::Gzip));
+    assert_eq!("brotli".parse::<Encoding>(), Ok(Encoding::Brotli));
+    assert_eq!("br".parse::<Encoding>(), Ok(Encoding::Brotli));
+    assert!("zstd".parse::<Encoding>().is_err());
+    assert_eq!(Encoding::Brotli.content_encoding(), "br");
+}
+
+#[test]
+fn test_preferred_encoding() {
+    let cache = Filecache {
+        basepath: "/tmp/t_rex_test_encoding".into(),
+        ..Default::default()
+    };
+    assert_eq!(cache.preferred_encoding(), Encoding::Identity);
+    let cache = Filecache {
+        compression: Encoding::Gzip,
+        ..cache
+    };
+    assert_eq!(cache.preferred_encoding(), Encoding::Gzip);
+    assert_eq!(Nocache.preferred_encoding(), Encoding::Identity);
+}
+
+#[test]
+fn test_dircache_gzip_precompressed() {
+    use crate::cache::cache::etag;
+    use flate2::{write::GzEncoder, Compression};
+    use std::env;
+    use std::io::Write;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_gzip_precompressed");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        compression: Encoding::Gzip,
+        meta: true,
+        ..Default::default()
+    };
+    let path = "tileset/0/0/0.pbf";
+    let obj = "0123456789".repeat(100);
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(obj.as_bytes()).unwrap();
+    let tilegz = gz.finish().unwrap();
+
+    // Tile encoded in the preferred encoding is stored as is
+    cache.write(path, &tilegz).unwrap();
+    let stored = fs::read(format!("{}/{}.gz", cache.basepath.display(), path)).unwrap();
+    assert_eq!(stored, tilegz);
+    assert_eq!(
+        cache.read_to_vec(path).unwrap(),
+        Some(obj.clone().into_bytes())
+    );
+    assert_eq!(cache.read_meta(path).unwrap().etag, etag(obj.as_bytes()));
+}
+
+#[test]
+fn test_dircache_replace_encoding() {
+    use std::env;
+
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_replace_encoding");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        compression: Encoding::Brotli,
+        meta: true,
+        ..Default::default()
+    };
+    let path = "tileset/0/0/0.pbf";
+    let fullpath = format!("{}/{}", cache.basepath.display(), path);
+    cache.write(path, b"brotli").unwrap();
+    assert!(Path::new(&format!("{}.br", fullpath)).exists());
+
+    // Tile written with another compression replaces the brotli file
+    let gzip = Filecache {
+        compression: Encoding::Gzip,
+        ..cache.clone()
+    };
+    gzip.write(path, b"gzip").unwrap();
+    assert!(!Path::new(&format!("{}.br", fullpath)).exists());
+    assert_eq!(cache.read_to_vec(path).unwrap(), Some(b"gzip".to_vec()));
+
+    // Sidecar file is removed when writing without metadata
+    let plain = Filecache {
+        compression: Encoding::Identity,
+        meta: false,
+        ..cache.clone()
+    };
+    plain.write(path, b"plain").unwrap();
+    assert!(!Path::new(&format!("{}.gz", fullpath)).exists());
+    assert!(!Path::new(&format!("{}.meta", fullpath)).exists());
+    assert_eq!(cache.read_to_vec(path).unwrap(), Some(b"plain".to_vec()));
+}
@@ -0,0 +1,170 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! In-memory tile cache with least-recently-used eviction
+
+use crate::cache::cache::{tile_path, Cache, CacheError};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Cursor, Read};
+use std::sync::{Arc, Mutex};
+
+struct Entry {
+    data: Arc<Vec<u8>>,
+    tick: u64,
+}
+
+#[derive(Default)]
+struct LruState {
+    entries: HashMap<String, Entry>,
+    /// Access order (access tick -> path)
+    order: BTreeMap<u64, String>,
+    tick: u64,
+    bytes: usize,
+}
+
+impl LruState {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+    fn remove(&mut self, path: &str) -> bool {
+        match self.entries.remove(path) {
+            Some(entry) => {
+                self.order.remove(&entry.tick);
+                self.bytes -= entry.data.len();
+                true
+            }
+            None => false,
+        }
+    }
+    fn remove_prefix(&mut self, prefix: &str) {
+        let paths: Vec<String> = self
+            .entries
+            .keys()
+            .filter(|path| path.starts_with(prefix))
+            .cloned()
+            .collect();
+        for path in paths {
+            self.remove(&path);
+        }
+    }
+    fn evict_oldest(&mut self) {
+        let oldest = self.order.keys().next().cloned();
+        if let Some(tick) = oldest {
+            let path = self.order.remove(&tick).unwrap();
+            if let Some(entry) = self.entries.remove(&path) {
+                self.bytes -= entry.data.len();
+            }
+            debug!("Lrucache.evict {}", path);
+        }
+    }
+}
+
+/// Size-bounded in-memory cache. Clones share the same storage.
+#[derive(Clone)]
+pub struct Lrucache {
+    /// Maximal number of cached entries
+    max_entries: Option<usize>,
+    /// Maximal size of cached data in bytes
+    max_bytes: Option<usize>,
+    state: Arc<Mutex<LruState>>,
+}
+
+impl Lrucache {
+    pub fn new(max_entries: Option<usize>, max_bytes: Option<usize>) -> Lrucache {
+        Lrucache {
+            max_entries,
+            max_bytes,
+            state: Arc::new(Mutex::new(LruState::default())),
+        }
+    }
+    /// Number of cached entries
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Size of cached data in bytes
+    pub fn size(&self) -> usize {
+        self.state.lock().unwrap().bytes
+    }
+    fn over_limit(&self, state: &LruState) -> bool {
+        matches!(self.max_entries, Some(max) if state.entries.len() > max)
+            || matches!(self.max_bytes, Some(max) if state.bytes > max)
+    }
+}
+
+impl Cache for Lrucache {
+    fn info(&self) -> String {
+        format!("In-memory LRU cache ({} entries)", self.len())
+    }
+    fn baseurl(&self) -> String {
+        "http://localhost:6767".to_string()
+    }
+    fn read<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
+    {
+        let data = {
+            let mut state = self.state.lock().unwrap();
+            let tick = state.next_tick();
+            let (data, old_tick) = match state.entries.get_mut(path) {
+                Some(entry) => {
+                    let old_tick = entry.tick;
+                    entry.tick = tick;
+                    (entry.data.clone(), old_tick)
+                }
+                None => return Ok(false),
+            };
+            state.order.remove(&old_tick);
+            state.order.insert(tick, path.to_string());
+            data
+        };
+        debug!("Lrucache.read {}", path);
+        read(&mut Cursor::new(&data[..]))?;
+        Ok(true)
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
+        debug!("Lrucache.write {}", path);
+        let mut state = self.state.lock().unwrap();
+        state.remove(path);
+        if matches!(self.max_bytes, Some(max) if obj.len() > max) {
+            // Entry would evict everything else
+            return Ok(());
+        }
+        let tick = state.next_tick();
+        state.entries.insert(
+            path.to_string(),
+            Entry {
+                data: Arc::new(obj.to_vec()),
+                tick,
+            },
+        );
+        state.order.insert(tick, path.to_string());
+        state.bytes += obj.len();
+        while self.over_limit(&state) {
+            state.evict_oldest();
+        }
+        Ok(())
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.state.lock().unwrap().entries.contains_key(path)
+    }
+    fn remove(&self, path: &str) -> bool {
+        self.state.lock().unwrap().remove(path)
+    }
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError> {
+        self.remove(&tile_path(tileset, zoom, xtile, ytile));
+        Ok(())
+    }
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError> {
+        self.state
+            .lock()
+            .unwrap()
+            .remove_prefix(&format!("{}/", tileset));
+        Ok(())
+    }
+}
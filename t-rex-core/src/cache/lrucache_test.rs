@@ -0,0 +1,120 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::cache::Cache;
+use crate::cache::lrucache::Lrucache;
+
+#[test]
+fn test_lrucache() {
+    let cache = Lrucache::new(Some(2), None);
+    let obj = "0123456789";
+
+    // Cache miss
+    assert!(!cache.read("tileset/0/0/0.pbf", |_| Ok(())).unwrap());
+
+    cache.write("tileset/0/0/0.pbf", obj.as_bytes()).unwrap();
+    cache.write("tileset/1/0/0.pbf", obj.as_bytes()).unwrap();
+
+    // Read from cache
+    let mut s = String::new();
+    assert!(cache
+        .read("tileset/0/0/0.pbf", |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(&s, obj);
+
+    // Least recently used tile 1/0/0 is evicted
+    cache.write("tileset/1/0/1.pbf", obj.as_bytes()).unwrap();
+    assert_eq!(cache.len(), 2);
+    assert!(cache.exists("tileset/0/0/0.pbf"));
+    assert!(!cache.exists("tileset/1/0/0.pbf"));
+    assert!(cache.exists("tileset/1/0/1.pbf"));
+
+    assert!(cache.remove("tileset/0/0/0.pbf"));
+    assert!(!cache.remove("tileset/0/0/0.pbf"));
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn test_lrucache_max_bytes() {
+    let cache = Lrucache::new(None, Some(25));
+    cache.write("tileset/0/0/0.pbf", &[0; 10]).unwrap();
+    cache.write("tileset/1/0/0.pbf", &[1; 10]).unwrap();
+    cache.write("tileset/1/0/1.pbf", &[2; 10]).unwrap();
+    assert_eq!(cache.size(), 20);
+    assert!(!cache.exists("tileset/0/0/0.pbf"));
+
+    // Overwriting an entry replaces its size
+    cache.write("tileset/1/0/1.pbf", &[3; 5]).unwrap();
+    assert_eq!(cache.size(), 15);
+
+    // Entries larger than the cache are not stored
+    cache.write("tileset/2/0/0.pbf", &[4; 30]).unwrap();
+    assert!(!cache.exists("tileset/2/0/0.pbf"));
+    assert_eq!(cache.len(), 2);
+}
+
+#[test]
+fn test_lrucache_concurrent_reads() {
+    use std::thread;
+
+    let cache = Lrucache::new(Some(10), None);
+    cache.write("tileset/0/0/0.pbf", b"0123456789").unwrap();
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let cache = cache.clone();
+            thread::spawn(move || {
+                let mut data = Vec::new();
+                for _ in 0..100 {
+                    data.clear();
+                    assert!(cache
+                        .read("tileset/0/0/0.pbf", |f| {
+                            f.read_to_end(&mut data)?;
+                            Ok(())
+                        })
+                        .unwrap());
+                }
+                data
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), b"0123456789");
+    }
+}
+
+#[test]
+fn test_lrucache_delete() {
+    let cache = Lrucache::new(None, None);
+    cache.write("tileset/0/0/0.pbf", b"0").unwrap();
+    cache.write("tileset/1/0/0.pbf", b"1").unwrap();
+    cache.write("tileset2/0/0/0.pbf", b"2").unwrap();
+
+    cache.delete("tileset", 0, 0, 0).unwrap();
+    assert!(!cache.exists("tileset/0/0/0.pbf"));
+    assert!(cache.delete("tileset", 0, 0, 0).is_ok());
+
+    cache.delete_tileset("tileset").unwrap();
+    assert!(!cache.exists("tileset/1/0/0.pbf"));
+    assert!(cache.exists("tileset2/0/0/0.pbf"));
+    assert_eq!(cache.size(), 1);
+}
+
+#[test]
+fn test_lrucache_exists_many() {
+    let cache = Lrucache::new(None, None);
+    cache.write("tileset/0/0/0.pbf", b"0").unwrap();
+    cache.write("tileset/2/1/3.pbf", b"2").unwrap();
+    cache.write("other/1/0/0.pbf", b"1").unwrap();
+
+    let coords = [(0, 0, 0), (1, 0, 0), (2, 1, 3), (2, 3, 1)];
+    assert_eq!(
+        cache.exists_many("tileset", &coords),
+        vec![true, false, true, false]
+    );
+    assert!(cache.exists_many("tileset", &[]).is_empty());
+}
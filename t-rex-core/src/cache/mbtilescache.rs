@@ -0,0 +1,279 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Tile cache storing each tileset in an MBTiles file
+
+use crate::cache::cache::{parse_tile_path, tile_path, valid_tile, Cache, CacheError, Encoding};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS metadata (name TEXT, value TEXT);
+    CREATE UNIQUE INDEX IF NOT EXISTS name ON metadata (name);
+    CREATE TABLE IF NOT EXISTS tiles (zoom_level INTEGER, tile_column INTEGER, tile_row INTEGER, tile_data BLOB);
+    CREATE UNIQUE INDEX IF NOT EXISTS tile_index ON tiles (zoom_level, tile_column, tile_row);
+";
+
+/// Cache with one MBTiles file `{basepath}/{tileset}.mbtiles` per tileset.
+/// Tile paths are expected in XYZ scheme and stored with TMS row numbers.
+#[derive(Clone)]
+pub struct Mbtilescache {
+    pub basepath: String,
+    pub baseurl: Option<String>,
+    connections: Arc<Mutex<HashMap<String, Connection>>>,
+}
+
+/// Tile address parsed from cache path `{tileset}/{z}/{x}/{y}.pbf`
+struct TileAddr<'a> {
+    tileset: &'a str,
+    zoom: u8,
+    column: u32,
+    row: u32,
+}
+
+impl<'a> TileAddr<'a> {
+    fn from_path(path: &'a str) -> Option<TileAddr<'a>> {
+        let (tileset, zoom, column, y) = parse_tile_path(path)?;
+        if zoom > 31 || !valid_tile(zoom, column, y) {
+            return None;
+        }
+        Some(TileAddr {
+            tileset,
+            zoom,
+            column,
+            // TMS row numbering
+            row: (1 << zoom) - 1 - y,
+        })
+    }
+}
+
+fn sql_err(e: rusqlite::Error) -> CacheError {
+    CacheError::Backend(e.to_string())
+}
+
+impl Mbtilescache {
+    pub fn new(basepath: &str, baseurl: Option<String>) -> Mbtilescache {
+        Mbtilescache {
+            basepath: basepath.to_string(),
+            baseurl,
+            connections: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+    /// Path of MBTiles file for tileset
+    pub fn mbtiles_path(&self, tileset: &str) -> String {
+        format!("{}/{}.mbtiles", self.basepath, tileset)
+    }
+    /// Run `f` with connection to tileset MBTiles file.
+    /// With `create` false, a missing file returns `Ok(None)`.
+    fn with_connection<T, F>(
+        &self,
+        tileset: &str,
+        create: bool,
+        f: F,
+    ) -> Result<Option<T>, CacheError>
+    where
+        F: FnOnce(&Connection) -> rusqlite::Result<T>,
+    {
+        let mut connections = self.connections.lock().unwrap();
+        if !connections.contains_key(tileset) {
+            let fname = self.mbtiles_path(tileset);
+            if !create && !Path::new(&fname).exists() {
+                return Ok(None);
+            }
+            fs::create_dir_all(&self.basepath)?;
+            debug!("Mbtilescache.open {}", fname);
+            let conn = Connection::open(&fname).map_err(sql_err)?;
+            conn.execute_batch(SCHEMA).map_err(sql_err)?;
+            for (name, value) in &[("name", tileset), ("format", "pbf")] {
+                conn.execute(
+                    "INSERT OR IGNORE INTO metadata (name, value) VALUES (?1, ?2)",
+                    params![name, value],
+                )
+                .map_err(sql_err)?;
+            }
+            connections.insert(tileset.to_string(), conn);
+        }
+        f(&connections[tileset]).map(Some).map_err(sql_err)
+    }
+    /// Store entries of `{tileset}/metadata.json` in metadata table
+    fn write_metadata(&self, tileset: &str, obj: &[u8]) -> Result<(), CacheError> {
+        let json: serde_json::Value = serde_json::from_slice(obj).map_err(io::Error::from)?;
+        let entries = match json.as_object() {
+            Some(entries) => entries,
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "metadata is not a JSON object",
+                )
+                .into())
+            }
+        };
+        self.with_connection(tileset, true, |conn| {
+            for (name, value) in entries {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    _ => value.to_string(),
+                };
+                // MBTiles expects comma separated values without brackets
+                let value = match name.as_str() {
+                    "bounds" | "center" => value.trim_matches(|c| c == '[' || c == ']').to_string(),
+                    _ => value,
+                };
+                conn.execute(
+                    "INSERT OR REPLACE INTO metadata (name, value) VALUES (?1, ?2)",
+                    params![name, value],
+                )?;
+            }
+            Ok(())
+        })
+        .map(|_| ())
+    }
+    /// Delete tile, returning the number of deleted rows
+    fn delete_tile(&self, tile: &TileAddr) -> Result<usize, CacheError> {
+        self.with_connection(tile.tileset, false, |conn| {
+            conn.execute(
+                "DELETE FROM tiles WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                params![tile.zoom, tile.column, tile.row],
+            )
+        })
+        .map(|deleted| deleted.unwrap_or(0))
+    }
+}
+
+impl Cache for Mbtilescache {
+    fn info(&self) -> String {
+        format!("Tile cache MBTiles directory: {}", self.basepath)
+    }
+    fn baseurl(&self) -> String {
+        self.baseurl
+            .clone()
+            .unwrap_or("http://localhost:6767".to_string())
+    }
+    fn read<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
+    {
+        let tile = match TileAddr::from_path(path) {
+            Some(tile) => tile,
+            None => return Ok(false),
+        };
+        debug!("Mbtilescache.read {}", path);
+        let data = self.with_connection(tile.tileset, false, |conn| {
+            conn.query_row(
+                "SELECT tile_data FROM tiles WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                params![tile.zoom, tile.column, tile.row],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()
+        });
+        match data? {
+            Some(Some(data)) => {
+                read(&mut Cursor::new(data))?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
+        if let Some(tile) = TileAddr::from_path(path) {
+            debug!("Mbtilescache.write {}", path);
+            self.with_connection(tile.tileset, true, |conn| {
+                conn.execute(
+                    "INSERT OR REPLACE INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+                    params![tile.zoom, tile.column, tile.row, obj],
+                )
+            })
+            .map(|_| ())
+        } else if parse_tile_path(path).is_some() {
+            Err(CacheError::InvalidTile(path.to_string()))
+        } else if path.ends_with("/metadata.json") {
+            let tileset = path.trim_end_matches("/metadata.json");
+            self.write_metadata(tileset, obj)
+        } else {
+            debug!("Mbtilescache.write {} ignored", path);
+            Ok(())
+        }
+    }
+    /// Write all tiles in a single transaction
+    fn write_batch<I>(&self, tileset_name: &str, tiles: I) -> Result<(), CacheError>
+    where
+        I: IntoIterator<Item = (u8, u32, u32, Vec<u8>)>,
+    {
+        let mut rows = Vec::new();
+        for (zoom, xtile, ytile, obj) in tiles {
+            let path = tile_path(tileset_name, zoom, xtile, ytile);
+            match TileAddr::from_path(&path) {
+                Some(tile) => rows.push((tile.zoom, tile.column, tile.row, obj)),
+                None => return Err(CacheError::InvalidTile(path)),
+            }
+        }
+        if rows.is_empty() {
+            return Ok(());
+        }
+        debug!(
+            "Mbtilescache.write_batch {} tiles of {}",
+            rows.len(),
+            tileset_name
+        );
+        self.with_connection(tileset_name, true, |conn| {
+            let tx = conn.unchecked_transaction()?;
+            {
+                let mut stmt = tx.prepare(
+                    "INSERT OR REPLACE INTO tiles (zoom_level, tile_column, tile_row, tile_data) VALUES (?1, ?2, ?3, ?4)",
+                )?;
+                for (zoom, column, row, obj) in &rows {
+                    stmt.execute(params![zoom, column, row, obj])?;
+                }
+            }
+            tx.commit()
+        })
+        .map(|_| ())
+    }
+    /// MBTiles spec: vector tiles are stored gzip compressed
+    fn preferred_encoding(&self) -> Encoding {
+        Encoding::Gzip
+    }
+    fn exists(&self, path: &str) -> bool {
+        let tile = match TileAddr::from_path(path) {
+            Some(tile) => tile,
+            None => return false,
+        };
+        let found = self.with_connection(tile.tileset, false, |conn| {
+            conn.query_row(
+                "SELECT 1 FROM tiles WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                params![tile.zoom, tile.column, tile.row],
+                |_| Ok(()),
+            )
+            .optional()
+        });
+        matches!(found, Ok(Some(Some(_))))
+    }
+    fn remove(&self, path: &str) -> bool {
+        let tile = match TileAddr::from_path(path) {
+            Some(tile) => tile,
+            None => return false,
+        };
+        matches!(self.delete_tile(&tile), Ok(n) if n > 0)
+    }
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError> {
+        let path = tile_path(tileset, zoom, xtile, ytile);
+        match TileAddr::from_path(&path) {
+            Some(tile) => self.delete_tile(&tile).map(|_| ()),
+            None => Err(CacheError::InvalidTile(path)),
+        }
+    }
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError> {
+        // Close connection before removing the file
+        self.connections.lock().unwrap().remove(tileset);
+        match fs::remove_file(self.mbtiles_path(tileset)) {
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => Ok(result?),
+        }
+    }
+}
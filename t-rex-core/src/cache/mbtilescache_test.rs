@@ -0,0 +1,116 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::cache::{tile_path, Cache, CacheError};
+use crate::cache::mbtilescache::Mbtilescache;
+use rusqlite::{params, Connection};
+use std::env;
+use std::fs;
+
+#[test]
+fn test_mbtilescache() {
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_mbtiles");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Mbtilescache::new(&basepath, None);
+    let path = "tileset/2/1/0.pbf";
+    let obj = "0123456789";
+
+    // Cache miss
+    assert!(!cache.read(path, |_| Ok(())).unwrap());
+    assert!(!cache.exists(path));
+
+    // Write into cache
+    cache.write(path, obj.as_bytes()).unwrap();
+    assert!(cache.exists(path));
+
+    // Read from cache
+    let mut s = String::new();
+    assert!(cache
+        .read(path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(&s, obj);
+
+    // Metadata
+    cache
+        .write(
+            "tileset/metadata.json",
+            br#"{"bounds":"[-180.0,-90.0,180.0,90.0]","minzoom":0,"maxzoom":14}"#,
+        )
+        .unwrap();
+    // Ignored non-tile paths
+    cache.write("tileset.json", b"{}").unwrap();
+
+    let conn = Connection::open(cache.mbtiles_path("tileset")).unwrap();
+    let row: u32 = conn
+        .query_row(
+            "SELECT tile_row FROM tiles WHERE zoom_level = 2 AND tile_column = 1",
+            params![],
+            |row| row.get(0),
+        )
+        .unwrap();
+    // TMS row of XYZ y=0 at zoom 2
+    assert_eq!(row, 3);
+    let metadata = |name: &str| -> String {
+        conn.query_row(
+            "SELECT value FROM metadata WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )
+        .unwrap()
+    };
+    assert_eq!(metadata("name"), "tileset");
+    assert_eq!(metadata("format"), "pbf");
+    assert_eq!(metadata("bounds"), "-180.0,-90.0,180.0,90.0");
+    assert_eq!(metadata("maxzoom"), "14");
+
+    // Delete
+    assert!(cache.remove(path));
+    assert!(!cache.exists(path));
+    cache.delete("tileset", 2, 1, 0).unwrap();
+    cache.delete_tileset("tileset").unwrap();
+    assert!(!dir.join("tileset.mbtiles").exists());
+}
+
+#[test]
+fn test_mbtilescache_write_batch() {
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_mbtiles_batch");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Mbtilescache::new(&basepath, None);
+    let tiles = vec![
+        (0, 0, 0, b"tile0".to_vec()),
+        (1, 1, 0, b"tile1".to_vec()),
+        (2, 3, 3, b"tile2".to_vec()),
+    ];
+    cache.write_batch("tileset", tiles.clone()).unwrap();
+    for (zoom, xtile, ytile, obj) in tiles {
+        let mut data = Vec::new();
+        let path = tile_path("tileset", zoom, xtile, ytile);
+        assert!(cache
+            .read(&path, |f| f.read_to_end(&mut data).map(|_| ()))
+            .unwrap());
+        assert_eq!(data, obj);
+    }
+
+    // Invalid tiles are rejected before writing
+    assert!(cache
+        .write_batch("tileset", vec![(3, 0, 0, vec![1]), (1, 0, 2, vec![2])])
+        .is_err());
+    assert!(!cache.exists("tileset/3/0/0.pbf"));
+    assert!(matches!(
+        cache.write("tileset/1/2/0.pbf", b"x"),
+        Err(CacheError::InvalidTile(_))
+    ));
+
+    cache.delete_tileset("tileset").unwrap();
+}
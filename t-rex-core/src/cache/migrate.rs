@@ -0,0 +1,60 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Copy cached tiles between cache backends
+
+use crate::cache::cache::{tile_path, Cache, CacheError, ListableCache};
+
+/// Number of tiles written with a single `write_batch` call
+const BATCH_SIZE: usize = 1000;
+
+/// Tile counts of a migration
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct MigrateStats {
+    /// Tiles written into the destination cache
+    pub copied: usize,
+    /// Tiles already in the destination cache
+    pub skipped: usize,
+    /// Listed tiles which could not be read from the source cache anymore
+    pub missing: usize,
+}
+
+/// Copy all tiles of `tileset` from `src` into `dst` without regenerating them.
+///
+/// Tiles already in `dst` are skipped, so an interrupted migration can be resumed
+/// by running it again. Tiles are written in batches with [`Cache::write_batch`].
+pub fn migrate<S, D>(src: &S, dst: &D, tileset: &str) -> Result<MigrateStats, CacheError>
+where
+    S: Cache + ListableCache,
+    D: Cache,
+{
+    let mut stats = MigrateStats::default();
+    let mut coords = src.list(tileset).peekable();
+    while coords.peek().is_some() {
+        let chunk: Vec<(u8, u32, u32)> = coords.by_ref().take(BATCH_SIZE).collect();
+        let exists = dst.exists_many(tileset, &chunk);
+        let mut batch = Vec::with_capacity(chunk.len());
+        for (&(zoom, xtile, ytile), exists) in chunk.iter().zip(exists) {
+            if exists {
+                stats.skipped += 1;
+                continue;
+            }
+            let path = tile_path(tileset, zoom, xtile, ytile);
+            if let Some(data) = src.read_to_vec(&path)? {
+                batch.push((zoom, xtile, ytile, data));
+            } else {
+                warn!("Tile {} removed from source cache during migration", path);
+                stats.missing += 1;
+            }
+        }
+        stats.copied += batch.len();
+        dst.write_batch(tileset, batch)?;
+        debug!(
+            "Migrated {} tiles of {} ({} skipped)",
+            stats.copied, tileset, stats.skipped
+        );
+    }
+    Ok(stats)
+}
@@ -0,0 +1,67 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::cache::{tile_path, Cache, Encoding};
+use crate::cache::filecache::Filecache;
+use crate::cache::mbtilescache::Mbtilescache;
+use crate::cache::migrate::{migrate, MigrateStats};
+use std::env;
+use std::fs;
+
+#[test]
+fn test_migrate() {
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_migrate");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let src = Filecache {
+        basepath: dir.join("files"),
+        compression: Encoding::Gzip,
+        ..Default::default()
+    };
+    let dst = Mbtilescache::new(&format!("{}/mbtiles", basepath), None);
+    let tiles = [(0, 0, 0), (3, 5, 2), (14, 8500, 5600)];
+    for &(z, x, y) in &tiles {
+        let obj = format!("{}/{}/{}", z, x, y);
+        src.write(&tile_path("tileset", z, x, y), obj.as_bytes())
+            .unwrap();
+    }
+    src.write(&tile_path("other", 1, 1, 1), b"other").unwrap();
+
+    let stats = migrate(&src, &dst, "tileset").unwrap();
+    assert_eq!(
+        stats,
+        MigrateStats {
+            copied: 3,
+            skipped: 0,
+            missing: 0
+        }
+    );
+    // Tiles are copied decompressed
+    for &(z, x, y) in &tiles {
+        let mut s = String::new();
+        assert!(dst
+            .read(&tile_path("tileset", z, x, y), |f| f
+                .read_to_string(&mut s)
+                .map(|_| ()))
+            .unwrap());
+        assert_eq!(s, format!("{}/{}/{}", z, x, y));
+    }
+    assert!(!dst.exists(&tile_path("other", 1, 1, 1)));
+
+    // Resume skips already copied tiles
+    src.write(&tile_path("tileset", 2, 1, 1), b"new").unwrap();
+    let stats = migrate(&src, &dst, "tileset").unwrap();
+    assert_eq!(
+        stats,
+        MigrateStats {
+            copied: 1,
+            skipped: 3,
+            missing: 0
+        }
+    );
+    assert!(dst.exists(&tile_path("tileset", 2, 1, 1)));
+}
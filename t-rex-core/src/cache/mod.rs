@@ -3,28 +3,89 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+pub mod aliascache;
 pub mod cache;
+pub mod cachestats;
 pub mod filecache;
+pub mod lrucache;
+#[cfg(feature = "with-mbtiles")]
+pub mod mbtilescache;
+pub mod migrate;
+pub mod overzoom;
+pub mod recordingcache;
+#[cfg(feature = "with-redis")]
+pub mod rediscache;
 pub mod s3cache;
+pub mod seed;
+pub mod singleflight;
+pub mod tieredcache;
 
+#[cfg(test)]
+mod aliascache_test;
+#[cfg(test)]
+mod cache_test;
+#[cfg(test)]
+mod cachestats_test;
 #[cfg(test)]
 mod filecache_test;
 #[cfg(test)]
+mod lrucache_test;
+#[cfg(all(test, feature = "with-mbtiles"))]
+mod mbtilescache_test;
+#[cfg(all(test, feature = "with-mbtiles"))]
+mod migrate_test;
+#[cfg(test)]
+mod overzoom_test;
+#[cfg(test)]
+mod recordingcache_test;
+#[cfg(all(test, feature = "with-redis"))]
+mod rediscache_test;
+#[cfg(test)]
 mod s3cache_test;
+#[cfg(test)]
+mod seed_test;
+#[cfg(test)]
+mod singleflight_test;
+#[cfg(test)]
+mod tieredcache_test;
 
+pub use self::aliascache::AliasCache;
 pub use self::cache::Cache;
+pub use self::cache::CacheError;
+pub use self::cache::Encoding;
+pub use self::cache::KeyStrategy;
+pub use self::cache::ListableCache;
 pub use self::cache::Nocache;
-pub use self::filecache::Filecache;
+pub use self::cache::TileMeta;
+pub use self::cache::HEALTHCHECK_TILESET;
+pub use self::cache::{cache_key, etag, path_key, tile_path, tile_path_with_format, valid_tile};
+pub use self::cachestats::{CacheStats, CacheStatsSnapshot};
+pub use self::filecache::{DiskUsage, Filecache, FsckReport, Layout, TileScheme};
+pub use self::lrucache::Lrucache;
+#[cfg(feature = "with-mbtiles")]
+pub use self::mbtilescache::Mbtilescache;
+pub use self::migrate::{migrate, MigrateStats};
+pub use self::overzoom::{find_cached_ancestor, CachedAncestor};
+pub use self::recordingcache::RecordingCache;
+#[cfg(feature = "with-redis")]
+pub use self::rediscache::Rediscache;
 pub use self::s3cache::S3Cache;
+pub use self::seed::{seed, seed_missing};
+pub use self::singleflight::SingleFlightCache;
+pub use self::tieredcache::TieredCache;
+use crate::core::config::CacheMbtilesCfg;
 use crate::core::ApplicationCfg;
 use crate::core::Config;
 use std::io;
-use std::io::Read;
+use std::io::{Read, Write};
+use std::time::Duration;
 
 #[derive(Clone)]
 pub enum Tilecache {
     Nocache(Nocache),
     Filecache(Filecache),
+    #[cfg(feature = "with-mbtiles")]
+    Mbtilescache(Mbtilescache),
     S3Cache(S3Cache),
 }
 
@@ -33,6 +94,8 @@ impl Cache for Tilecache {
         match self {
             &Tilecache::Nocache(ref cache) => cache.info(),
             &Tilecache::Filecache(ref cache) => cache.info(),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtilescache(ref cache) => cache.info(),
             &Tilecache::S3Cache(ref cache) => cache.info(),
         }
     }
@@ -40,41 +103,152 @@ impl Cache for Tilecache {
         match self {
             &Tilecache::Nocache(ref cache) => cache.baseurl(),
             &Tilecache::Filecache(ref cache) => cache.baseurl(),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtilescache(ref cache) => cache.baseurl(),
             &Tilecache::S3Cache(ref cache) => cache.baseurl(),
         }
     }
-    fn read<F>(&self, path: &str, read: F) -> bool
+    fn read<F>(&self, path: &str, read: F) -> Result<bool, CacheError>
     where
-        F: FnMut(&mut dyn Read),
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
     {
         match self {
             &Tilecache::Nocache(ref cache) => cache.read(path, read),
             &Tilecache::Filecache(ref cache) => cache.read(path, read),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtilescache(ref cache) => cache.read(path, read),
             &Tilecache::S3Cache(ref cache) => cache.read(path, read),
         }
     }
-    fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error> {
+    fn read_encoded<F>(&self, path: &str, read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read, Encoding) -> Result<(), io::Error>,
+    {
+        match self {
+            &Tilecache::Nocache(ref cache) => cache.read_encoded(path, read),
+            &Tilecache::Filecache(ref cache) => cache.read_encoded(path, read),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtilescache(ref cache) => cache.read_encoded(path, read),
+            &Tilecache::S3Cache(ref cache) => cache.read_encoded(path, read),
+        }
+    }
+    fn read_meta(&self, path: &str) -> Option<TileMeta> {
+        match *self {
+            Tilecache::Nocache(ref cache) => cache.read_meta(path),
+            Tilecache::Filecache(ref cache) => cache.read_meta(path),
+            #[cfg(feature = "with-mbtiles")]
+            Tilecache::Mbtilescache(ref cache) => cache.read_meta(path),
+            Tilecache::S3Cache(ref cache) => cache.read_meta(path),
+        }
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
         match self {
             &Tilecache::Nocache(ref cache) => cache.write(path, obj),
             &Tilecache::Filecache(ref cache) => cache.write(path, obj),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtilescache(ref cache) => cache.write(path, obj),
             &Tilecache::S3Cache(ref cache) => cache.write(path, obj),
         }
     }
+    fn write_stream<F>(&self, path: &str, write: F) -> Result<(), CacheError>
+    where
+        F: FnOnce(&mut dyn Write) -> Result<(), io::Error>,
+    {
+        match *self {
+            Tilecache::Nocache(ref cache) => cache.write_stream(path, write),
+            Tilecache::Filecache(ref cache) => cache.write_stream(path, write),
+            #[cfg(feature = "with-mbtiles")]
+            Tilecache::Mbtilescache(ref cache) => cache.write_stream(path, write),
+            Tilecache::S3Cache(ref cache) => cache.write_stream(path, write),
+        }
+    }
+    fn write_batch<I>(&self, tileset_name: &str, tiles: I) -> Result<(), CacheError>
+    where
+        I: IntoIterator<Item = (u8, u32, u32, Vec<u8>)>,
+    {
+        match *self {
+            Tilecache::Nocache(ref cache) => cache.write_batch(tileset_name, tiles),
+            Tilecache::Filecache(ref cache) => cache.write_batch(tileset_name, tiles),
+            #[cfg(feature = "with-mbtiles")]
+            Tilecache::Mbtilescache(ref cache) => cache.write_batch(tileset_name, tiles),
+            Tilecache::S3Cache(ref cache) => cache.write_batch(tileset_name, tiles),
+        }
+    }
+    fn preferred_encoding(&self) -> Encoding {
+        match *self {
+            Tilecache::Nocache(ref cache) => cache.preferred_encoding(),
+            Tilecache::Filecache(ref cache) => cache.preferred_encoding(),
+            #[cfg(feature = "with-mbtiles")]
+            Tilecache::Mbtilescache(ref cache) => cache.preferred_encoding(),
+            Tilecache::S3Cache(ref cache) => cache.preferred_encoding(),
+        }
+    }
     fn exists(&self, path: &str) -> bool {
         match self {
             &Tilecache::Nocache(ref cache) => cache.exists(path),
             &Tilecache::Filecache(ref cache) => cache.exists(path),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtilescache(ref cache) => cache.exists(path),
             &Tilecache::S3Cache(ref cache) => cache.exists(path),
         }
     }
+    fn exists_many(&self, tileset: &str, coords: &[(u8, u32, u32)]) -> Vec<bool> {
+        match self {
+            &Tilecache::Nocache(ref cache) => cache.exists_many(tileset, coords),
+            &Tilecache::Filecache(ref cache) => cache.exists_many(tileset, coords),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtilescache(ref cache) => cache.exists_many(tileset, coords),
+            &Tilecache::S3Cache(ref cache) => cache.exists_many(tileset, coords),
+        }
+    }
 
     fn remove(&self, path: &str) -> bool {
         match self {
             &Tilecache::Nocache(ref cache) => cache.remove(path),
             &Tilecache::Filecache(ref cache) => cache.remove(path),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtilescache(ref cache) => cache.remove(path),
             &Tilecache::S3Cache(ref cache) => cache.remove(path),
         }
     }
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError> {
+        match self {
+            &Tilecache::Nocache(ref cache) => cache.delete(tileset, zoom, xtile, ytile),
+            &Tilecache::Filecache(ref cache) => cache.delete(tileset, zoom, xtile, ytile),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtilescache(ref cache) => cache.delete(tileset, zoom, xtile, ytile),
+            &Tilecache::S3Cache(ref cache) => cache.delete(tileset, zoom, xtile, ytile),
+        }
+    }
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError> {
+        match self {
+            &Tilecache::Nocache(ref cache) => cache.delete_tileset(tileset),
+            &Tilecache::Filecache(ref cache) => cache.delete_tileset(tileset),
+            #[cfg(feature = "with-mbtiles")]
+            &Tilecache::Mbtilescache(ref cache) => cache.delete_tileset(tileset),
+            &Tilecache::S3Cache(ref cache) => cache.delete_tileset(tileset),
+        }
+    }
+    fn healthcheck(&self) -> Result<(), CacheError> {
+        match *self {
+            Tilecache::Nocache(ref cache) => cache.healthcheck(),
+            Tilecache::Filecache(ref cache) => cache.healthcheck(),
+            #[cfg(feature = "with-mbtiles")]
+            Tilecache::Mbtilescache(ref cache) => cache.healthcheck(),
+            Tilecache::S3Cache(ref cache) => cache.healthcheck(),
+        }
+    }
+}
+
+#[cfg(feature = "with-mbtiles")]
+fn mbtilescache_from_config(cfg: &CacheMbtilesCfg) -> Result<Tilecache, String> {
+    let mc = Mbtilescache::new(&cfg.base, cfg.baseurl.clone());
+    Ok(Tilecache::Mbtilescache(mc))
+}
+
+#[cfg(not(feature = "with-mbtiles"))]
+fn mbtilescache_from_config(_cfg: &CacheMbtilesCfg) -> Result<Tilecache, String> {
+    Err("MBTiles cache requires t-rex built with feature 'with-mbtiles'".to_string())
 }
 
 impl<'a> Config<'a, ApplicationCfg> for Tilecache {
@@ -87,11 +261,42 @@ impl<'a> Config<'a, ApplicationCfg> for Tilecache {
                 .as_ref()
                 .map(|cache| {
                     if let Some(file_cache_cfg) = cache.file.as_ref() {
+                        let layout = match file_cache_cfg.layout.as_ref() {
+                            Some(layout) => layout.parse()?,
+                            None => Layout::default(),
+                        };
+                        let scheme = match file_cache_cfg.scheme.as_ref() {
+                            Some(scheme) => scheme.parse()?,
+                            None => TileScheme::default(),
+                        };
+                        let compression = match file_cache_cfg.compression.as_ref() {
+                            Some(compression) => compression.parse()?,
+                            None if file_cache_cfg.gzip.unwrap_or(false) => Encoding::Gzip,
+                            None => Encoding::Identity,
+                        };
                         let fc = Filecache {
-                            basepath: file_cache_cfg.base.clone(),
+                            basepath: file_cache_cfg.base.clone().into(),
                             baseurl: file_cache_cfg.baseurl.clone(),
+                            layout,
+                            scheme,
+                            compression,
+                            max_age: file_cache_cfg.max_age.map(Duration::from_secs),
+                            meta: file_cache_cfg.meta.unwrap_or(false),
+                            skip_empty: file_cache_cfg.skip_empty.unwrap_or(false),
+                            empty_sentinel: file_cache_cfg.empty_sentinel.unwrap_or(false),
+                            checksum: file_cache_cfg.checksum.unwrap_or(false),
+                            max_bytes: file_cache_cfg.max_bytes,
+                            sync: file_cache_cfg.sync.unwrap_or(false),
+                            usage: Default::default(),
                         };
-                        Tilecache::Filecache(fc)
+                        if file_cache_cfg.fsck.unwrap_or(false) {
+                            if let Err(e) = fc.fsck() {
+                                warn!("Cache directory check failed: {}", e);
+                            }
+                        }
+                        Ok(Tilecache::Filecache(fc))
+                    } else if let Some(mbtiles_cache_cfg) = cache.mbtiles.as_ref() {
+                        mbtilescache_from_config(mbtiles_cache_cfg)
                     } else if let Some(s3_cache_cfg) = cache.s3.as_ref() {
                         let s3c = S3Cache::new(
                             &s3_cache_cfg.endpoint.clone(),
@@ -103,12 +308,12 @@ impl<'a> Config<'a, ApplicationCfg> for Tilecache {
                             s3_cache_cfg.key_prefix.clone(),
                             s3_cache_cfg.gzip_header_enabled.clone(),
                         );
-                        Tilecache::S3Cache(s3c)
+                        Ok(Tilecache::S3Cache(s3c))
                     } else {
-                        Tilecache::Nocache(Nocache)
+                        Ok(Tilecache::Nocache(Nocache))
                     }
                 })
-                .ok_or("No Application Config found".to_string())
+                .unwrap_or_else(|| Err("No Application Config found".to_string()))
         }
     }
     fn gen_config() -> String {
@@ -116,6 +321,27 @@ impl<'a> Config<'a, ApplicationCfg> for Tilecache {
 #[cache.file]
 #base = "/tmp/mvtcache"
 #baseurl = "http://example.com/tiles"
+# Directory layout: "simple" ({z}/{x}/{y}.pbf) or "fanout"
+#layout = "simple"
+# Tile row numbering: "xyz" or "tms" (y flipped)
+#scheme = "xyz"
+#gzip = false
+# Compression of stored tiles: "none", "gzip" or "brotli" (overrides gzip)
+#compression = "gzip"
+# Maximal age of cached tiles in seconds
+#max_age = 86400
+# Maximal total size of cached files in bytes. Oldest files are removed first.
+#max_bytes = 10000000000
+# Don't store empty tiles
+#skip_empty = false
+# Mark skipped empty tiles with zero-byte files, so they are not generated again
+#empty_sentinel = false
+# Verify checksums of cached tiles
+#checksum = false
+# Flush written tiles to disk before returning (slower writes)
+#sync = false
+# Remove empty directories and files in cache directory on startup
+#fsck = false
 "#;
         toml.to_string()
     }
@@ -0,0 +1,47 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Lookup of cached ancestor tiles for overzooming
+
+use crate::cache::cache::{tile_path, Cache, CacheError};
+
+/// Cached tile found for a requested tile
+#[derive(Clone, PartialEq, Debug)]
+pub struct CachedAncestor {
+    pub zoom: u8,
+    pub xtile: u32,
+    pub ytile: u32,
+    /// Number of zoom levels between requested tile and ancestor (0: requested tile)
+    pub zoom_delta: u8,
+    pub data: Vec<u8>,
+}
+
+/// Find the requested tile or its nearest cached ancestor, walking up
+/// at most `max_delta` zoom levels. Tile coordinates are in XYZ scheme,
+/// as used by `tile_path`.
+pub fn find_cached_ancestor<C: Cache>(
+    cache: &C,
+    tileset: &str,
+    zoom: u8,
+    xtile: u32,
+    ytile: u32,
+    max_delta: u8,
+) -> Result<Option<CachedAncestor>, CacheError> {
+    for zoom_delta in 0..=max_delta.min(zoom) {
+        let (ax, ay) = (xtile >> zoom_delta, ytile >> zoom_delta);
+        let azoom = zoom - zoom_delta;
+        let path = tile_path(tileset, azoom, ax, ay);
+        if let Some(data) = cache.read_to_vec(&path)? {
+            return Ok(Some(CachedAncestor {
+                zoom: azoom,
+                xtile: ax,
+                ytile: ay,
+                zoom_delta,
+                data,
+            }));
+        }
+    }
+    Ok(None)
+}
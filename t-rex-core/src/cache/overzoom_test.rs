@@ -0,0 +1,49 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::cache::{tile_path, Cache};
+use crate::cache::overzoom::{find_cached_ancestor, CachedAncestor};
+use crate::cache::recordingcache::RecordingCache;
+
+#[test]
+fn test_find_cached_ancestor() {
+    let cache = RecordingCache::new();
+    cache
+        .write(&tile_path("tileset", 10, 536, 358), b"z10")
+        .unwrap();
+
+    // Child at zoom 12: x = 536 * 4 + 3, y = 358 * 4 + 1
+    let ancestor = find_cached_ancestor(&cache, "tileset", 12, 2147, 1433, 4).unwrap();
+    assert_eq!(
+        ancestor,
+        Some(CachedAncestor {
+            zoom: 10,
+            xtile: 536,
+            ytile: 358,
+            zoom_delta: 2,
+            data: b"z10".to_vec(),
+        })
+    );
+
+    // Requested tile itself
+    let found = find_cached_ancestor(&cache, "tileset", 10, 536, 358, 4).unwrap();
+    assert_eq!(found.map(|t| t.zoom_delta), Some(0));
+
+    // Ancestor beyond max_delta
+    assert_eq!(
+        find_cached_ancestor(&cache, "tileset", 12, 2147, 1433, 1).unwrap(),
+        None
+    );
+    // Tile of another branch
+    assert_eq!(
+        find_cached_ancestor(&cache, "tileset", 12, 2148, 1433, 4).unwrap(),
+        None
+    );
+    // Walk stops at zoom 0
+    assert_eq!(
+        find_cached_ancestor(&cache, "tileset", 2, 1, 1, 10).unwrap(),
+        None
+    );
+}
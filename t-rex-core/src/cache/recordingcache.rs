@@ -0,0 +1,111 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! In-memory cache recording written tiles for inspection
+
+use crate::cache::cache::{parse_tile_path, Cache, CacheError};
+use std::collections::BTreeMap;
+use std::io::{self, Cursor, Read};
+use std::sync::{Arc, Mutex};
+
+/// Tile address (tileset, zoom, x, y)
+pub type TileKey = (String, u8, u32, u32);
+
+/// Cache keeping all written tiles in memory, e.g. for tests and dry runs.
+/// Non-tile objects like metadata are ignored. Clones share the same storage.
+#[derive(Clone, Default)]
+pub struct RecordingCache {
+    entries: Arc<Mutex<BTreeMap<TileKey, Vec<u8>>>>,
+}
+
+fn tile_key(path: &str) -> Option<TileKey> {
+    parse_tile_path(path)
+        .map(|(tileset, zoom, xtile, ytile)| (tileset.to_string(), zoom, xtile, ytile))
+}
+
+impl RecordingCache {
+    pub fn new() -> RecordingCache {
+        RecordingCache::default()
+    }
+    /// Copy of all recorded tiles
+    pub fn entries(&self) -> BTreeMap<TileKey, Vec<u8>> {
+        self.entries.lock().unwrap().clone()
+    }
+    /// Recorded tile data
+    pub fn get(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Option<Vec<u8>> {
+        self.entries
+            .lock()
+            .unwrap()
+            .get(&(tileset.to_string(), zoom, xtile, ytile))
+            .cloned()
+    }
+    /// Number of recorded tiles
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Cache for RecordingCache {
+    fn info(&self) -> String {
+        format!("Recording cache ({} tiles)", self.len())
+    }
+    fn baseurl(&self) -> String {
+        "http://localhost:6767".to_string()
+    }
+    fn read<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
+    {
+        let key = match tile_key(path) {
+            Some(key) => key,
+            None => return Ok(false),
+        };
+        let data = match self.entries.lock().unwrap().get(&key).cloned() {
+            Some(data) => data,
+            None => return Ok(false),
+        };
+        read(&mut Cursor::new(data))?;
+        Ok(true)
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
+        match tile_key(path) {
+            Some(key) => {
+                debug!("RecordingCache.write {}", path);
+                self.entries.lock().unwrap().insert(key, obj.to_vec());
+            }
+            None => debug!("RecordingCache.write {} ignored", path),
+        }
+        Ok(())
+    }
+    fn exists(&self, path: &str) -> bool {
+        match tile_key(path) {
+            Some(key) => self.entries.lock().unwrap().contains_key(&key),
+            None => false,
+        }
+    }
+    fn remove(&self, path: &str) -> bool {
+        match tile_key(path) {
+            Some(key) => self.entries.lock().unwrap().remove(&key).is_some(),
+            None => false,
+        }
+    }
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError> {
+        self.entries
+            .lock()
+            .unwrap()
+            .remove(&(tileset.to_string(), zoom, xtile, ytile));
+        Ok(())
+    }
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError> {
+        self.entries
+            .lock()
+            .unwrap()
+            .retain(|(ts, _, _, _), _| ts != tileset);
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::cache::Cache;
+use crate::cache::recordingcache::RecordingCache;
+
+#[test]
+fn test_recordingcache() {
+    let cache = RecordingCache::new();
+    assert!(cache.is_empty());
+
+    cache.write("tileset/0/0/0.pbf", b"tile0").unwrap();
+    cache.write("tileset/2/1/3.pbf", b"tile2").unwrap();
+    // Non-tile objects are not recorded
+    cache.write("tileset/metadata.json", b"{}").unwrap();
+
+    let entries = cache.entries();
+    assert_eq!(
+        entries.into_iter().collect::<Vec<_>>(),
+        vec![
+            (("tileset".to_string(), 0, 0, 0), b"tile0".to_vec()),
+            (("tileset".to_string(), 2, 1, 3), b"tile2".to_vec()),
+        ]
+    );
+    assert_eq!(cache.get("tileset", 2, 1, 3), Some(b"tile2".to_vec()));
+    assert_eq!(cache.get("tileset", 2, 1, 4), None);
+
+    // Recorded tiles are served back
+    let mut s = String::new();
+    assert!(cache
+        .read("tileset/2/1/3.pbf", |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(s, "tile2");
+    assert!(!cache.read("tileset/metadata.json", |_| Ok(())).unwrap());
+
+    // Clones share recorded tiles
+    let clone = cache.clone();
+    clone.delete("tileset", 0, 0, 0).unwrap();
+    assert!(!cache.exists("tileset/0/0/0.pbf"));
+    assert_eq!(cache.len(), 1);
+
+    cache.delete_tileset("tileset").unwrap();
+    assert!(cache.is_empty());
+}
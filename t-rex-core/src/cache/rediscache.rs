@@ -0,0 +1,170 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Tile cache in a Redis key-value store
+
+use crate::cache::cache::{parse_tile_path, path_key, tile_path, Cache, CacheError, KeyStrategy};
+use std::io::{self, Cursor, Read};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[derive(Clone)]
+pub struct Rediscache {
+    pub baseurl: Option<String>,
+    /// Expiry time of written keys
+    pub ttl: Option<Duration>,
+    key_strategy: KeyStrategy,
+    client: redis::Client,
+    connection: Arc<Mutex<Option<redis::Connection>>>,
+}
+
+fn redis_err(e: redis::RedisError) -> CacheError {
+    match e.code() {
+        // Memory limit reached with `maxmemory-policy noeviction`
+        Some("OOM") => CacheError::QuotaExceeded(e.to_string()),
+        _ => CacheError::Backend(e.to_string()),
+    }
+}
+
+impl Rediscache {
+    /// Create cache for Redis server at `url` (e.g. `redis://127.0.0.1/`).
+    /// The connection is established on first use.
+    pub fn new(
+        url: &str,
+        ttl: Option<Duration>,
+        baseurl: Option<String>,
+    ) -> Result<Rediscache, CacheError> {
+        let client = redis::Client::open(url).map_err(redis_err)?;
+        Ok(Rediscache {
+            baseurl,
+            ttl,
+            key_strategy: KeyStrategy::Path,
+            client,
+            connection: Arc::new(Mutex::new(None)),
+        })
+    }
+    /// Cache with given construction of tile keys
+    pub fn with_key_strategy(mut self, key_strategy: KeyStrategy) -> Rediscache {
+        self.key_strategy = key_strategy;
+        self
+    }
+    /// Redis key for cache path
+    pub fn key(&self, path: &str) -> String {
+        match (self.key_strategy, parse_tile_path(path)) {
+            (KeyStrategy::Path, Some((tileset, zoom, xtile, ytile))) => {
+                format!("trex:{}:{}:{}:{}", tileset, zoom, xtile, ytile)
+            }
+            _ => format!("trex:{}", path_key(self.key_strategy, path)),
+        }
+    }
+    /// `SCAN` patterns matching all keys of `tileset`
+    pub fn tileset_patterns(&self, tileset: &str) -> Vec<String> {
+        let tileset = escape_pattern(tileset);
+        match self.key_strategy {
+            // Objects other than pbf tiles are stored with their path as key
+            KeyStrategy::Path => vec![format!("trex:{}:*", tileset), format!("trex:{}/*", tileset)],
+            KeyStrategy::Hashed => vec![format!("trex:{}/*", tileset)],
+        }
+    }
+    /// Run command on shared connection, reconnecting after failures
+    fn query<T: redis::FromRedisValue>(&self, cmd: &redis::Cmd) -> redis::RedisResult<T> {
+        let mut connection = self.connection.lock().unwrap();
+        if connection.is_none() {
+            *connection = Some(self.client.get_connection()?);
+        }
+        let result = cmd.query(connection.as_mut().unwrap());
+        if result.is_err() {
+            *connection = None;
+        }
+        result
+    }
+}
+
+impl Cache for Rediscache {
+    fn info(&self) -> String {
+        format!(
+            "Tile cache Redis: {}",
+            self.client.get_connection_info().addr
+        )
+    }
+    fn baseurl(&self) -> String {
+        self.baseurl
+            .clone()
+            .unwrap_or("http://localhost:6767".to_string())
+    }
+    fn read<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
+    {
+        let key = self.key(path);
+        debug!("Rediscache.read {}", key);
+        match self.query::<Option<Vec<u8>>>(redis::cmd("GET").arg(&key)) {
+            Ok(Some(data)) => {
+                read(&mut Cursor::new(data))?;
+                Ok(true)
+            }
+            Ok(None) => Ok(false),
+            Err(e) => {
+                // Treat unavailable server as cache miss
+                warn!("Rediscache.read {}: {}", key, e);
+                Ok(false)
+            }
+        }
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
+        let key = self.key(path);
+        debug!("Rediscache.write {}", key);
+        let mut cmd = redis::cmd("SET");
+        cmd.arg(&key).arg(obj);
+        if let Some(ttl) = self.ttl {
+            cmd.arg("PX").arg((ttl.as_millis() as u64).max(1));
+        }
+        self.query::<()>(&cmd).map_err(redis_err)
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.query::<bool>(redis::cmd("EXISTS").arg(self.key(path)))
+            .unwrap_or(false)
+    }
+    fn remove(&self, path: &str) -> bool {
+        let deleted = self.query::<u64>(redis::cmd("DEL").arg(self.key(path)));
+        matches!(deleted, Ok(n) if n > 0)
+    }
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError> {
+        let key = self.key(&tile_path(tileset, zoom, xtile, ytile));
+        self.query::<()>(redis::cmd("DEL").arg(key))
+            .map_err(redis_err)
+    }
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError> {
+        for pattern in self.tileset_patterns(tileset) {
+            let mut cursor = 0u64;
+            loop {
+                let (next, keys): (u64, Vec<String>) = self
+                    .query(redis::cmd("SCAN").arg(cursor).arg("MATCH").arg(&pattern))
+                    .map_err(redis_err)?;
+                if !keys.is_empty() {
+                    self.query::<()>(redis::cmd("DEL").arg(keys))
+                        .map_err(redis_err)?;
+                }
+                if next == 0 {
+                    break;
+                }
+                cursor = next;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Escape glob characters of `SCAN` patterns
+fn escape_pattern(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
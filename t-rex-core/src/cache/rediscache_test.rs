@@ -0,0 +1,93 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::cache::{cache_key, Cache, KeyStrategy};
+use crate::cache::rediscache::Rediscache;
+use std::env;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_rediscache_keys() {
+    let cache = Rediscache::new("redis://127.0.0.1/", None, None).unwrap();
+    assert_eq!(cache.key("tileset/0/1/2.pbf"), "trex:tileset:0:1:2");
+    assert_eq!(cache.key("tileset.json"), "trex:tileset.json");
+    assert_eq!(
+        cache.tileset_patterns("tileset"),
+        vec!["trex:tileset:*", "trex:tileset/*"]
+    );
+    // Glob characters in tileset names are escaped
+    assert_eq!(
+        cache.tileset_patterns("t*?[s]\\"),
+        vec!["trex:t\\*\\?\\[s\\]\\\\:*", "trex:t\\*\\?\\[s\\]\\\\/*"]
+    );
+
+    let cache = cache.with_key_strategy(KeyStrategy::Hashed);
+    assert_eq!(
+        cache.key("tileset/0/1/2.pbf"),
+        format!(
+            "trex:{}",
+            cache_key(KeyStrategy::Hashed, "tileset", 0, 1, 2, "pbf")
+        )
+    );
+    assert_eq!(cache.key("tileset.json"), "trex:tileset.json");
+    assert_eq!(cache.tileset_patterns("tileset"), vec!["trex:tileset/*"]);
+}
+
+#[test]
+fn test_rediscache_unavailable() {
+    // Nothing listens on port 1
+    let cache = Rediscache::new("redis://127.0.0.1:1/", None, None).unwrap();
+    assert!(!cache.read("tileset/0/0/0.pbf", |_| Ok(())).unwrap());
+    assert!(!cache.exists("tileset/0/0/0.pbf"));
+    assert!(cache.write("tileset/0/0/0.pbf", b"0123456789").is_err());
+}
+
+#[test]
+#[ignore]
+fn test_rediscache() {
+    let url = match env::var("REDIS_URL") {
+        Ok(url) => url,
+        Err(_) => return,
+    };
+
+    let cache = Rediscache::new(&url, None, None).unwrap();
+    let path = "t_rex_test/0/1/2.pbf";
+    let _ = cache.delete_tileset("t_rex_test");
+
+    // Cache miss
+    assert!(!cache.read(path, |_| Ok(())).unwrap());
+
+    // Write into cache
+    cache.write(path, b"0123456789").unwrap();
+    assert!(cache.exists(path));
+
+    // Read from cache
+    let mut s = String::new();
+    assert!(cache
+        .read(path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(&s, "0123456789");
+
+    // Other objects of the tileset are deleted as well
+    let metadata = "t_rex_test/metadata.json";
+    cache.write(metadata, b"{}").unwrap();
+    // Glob characters don't match other tilesets
+    cache.delete_tileset("t_rex_*").unwrap();
+    assert!(cache.exists(path));
+    cache.delete_tileset("t_rex_test").unwrap();
+    assert!(!cache.exists(path));
+    assert!(!cache.exists(metadata));
+
+    // Expiring keys
+    let cache = Rediscache::new(&url, Some(Duration::from_millis(200)), None).unwrap();
+    cache.write(path, b"0123456789").unwrap();
+    assert!(cache.exists(path));
+    thread::sleep(Duration::from_millis(400));
+    assert!(!cache.exists(path));
+}
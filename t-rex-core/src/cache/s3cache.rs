@@ -3,12 +3,16 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
-use crate::cache::cache::Cache;
+use crate::cache::cache::{
+    content_type, path_key, tile_path, Cache, CacheError, Encoding, KeyStrategy,
+};
 use rusoto_core::{Client, HttpClient, Region};
 use rusoto_credential::StaticProvider;
 use rusoto_s3::{
-    DeleteObjectRequest, GetObjectRequest, HeadObjectRequest, PutObjectRequest, S3Client, S3,
+    DeleteObjectRequest, GetObjectRequest, HeadObjectRequest, ListObjectsV2Request,
+    PutObjectRequest, S3Client, S3,
 };
+use std::fmt;
 use std::io::{self, Read};
 use std::path::Path;
 
@@ -19,6 +23,7 @@ pub struct S3Cache {
     endpoint: String,
     bucket_name: String,
     key_prefix: Option<String>,
+    key_strategy: KeyStrategy,
     gzip_header_enabled: Option<bool>,
 }
 
@@ -50,10 +55,17 @@ impl S3Cache {
             endpoint: endpoint.to_string(),
             bucket_name: bucket_name.to_string(),
             key_prefix: key_prefix,
+            key_strategy: KeyStrategy::Path,
             gzip_header_enabled: gzip_header_enabled,
         }
     }
 
+    /// Cache with given construction of tile keys
+    pub fn with_key_strategy(mut self, key_strategy: KeyStrategy) -> S3Cache {
+        self.key_strategy = key_strategy;
+        self
+    }
+
     fn key_prefix(&self) -> String {
         self.key_prefix.clone().unwrap_or("".to_string())
     }
@@ -62,9 +74,35 @@ impl S3Cache {
         self.gzip_header_enabled.clone().unwrap_or(true)
     }
 
-    fn full_path(&self, path: &str) -> String {
+    fn delete_key(&self, key: &str) -> Result<(), CacheError> {
+        let request = DeleteObjectRequest {
+            bucket: self.bucket_name.to_owned(),
+            key: key.to_owned(),
+            ..Default::default()
+        };
+        match self.client.delete_object(request).sync() {
+            Ok(_) => Ok(()),
+            Err(err) => Err(self.s3_err(err)),
+        }
+    }
+
+    /// Cache error of a failed S3 request
+    fn s3_err<E: fmt::Display>(&self, err: E) -> CacheError {
+        let msg = err.to_string();
+        if msg.contains("NoSuchBucket") {
+            CacheError::NotFound(format!("Bucket {}", self.bucket_name))
+        } else if msg.contains("QuotaExceeded") {
+            CacheError::QuotaExceeded(msg)
+        } else {
+            CacheError::Backend(msg)
+        }
+    }
+
+    /// Object key for cache path
+    pub(crate) fn full_path(&self, path: &str) -> String {
         let key_prefix = &self.key_prefix();
-        match Path::new(key_prefix).join(path).to_str() {
+        let key = path_key(self.key_strategy, path);
+        match Path::new(key_prefix).join(key).to_str() {
             None => String::new(),
             Some(result) => result.to_string(),
         }
@@ -82,13 +120,13 @@ impl Cache for S3Cache {
             .unwrap_or("http://localhost:6767".to_string())
     }
 
-    fn read<F>(&self, path: &str, mut read: F) -> bool
+    fn read<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
     where
-        F: FnMut(&mut dyn Read),
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
     {
         let key = self.full_path(path);
         if key.is_empty() {
-            return false;
+            return Ok(false);
         }
         let request = GetObjectRequest {
             bucket: self.bucket_name.to_owned(),
@@ -100,28 +138,22 @@ impl Cache for S3Cache {
         match response {
             Ok(mut result) => {
                 let body = result.body.take().expect("The object has no body");
-                read(&mut body.into_blocking_read());
-                true
+                read(&mut body.into_blocking_read())?;
+                Ok(true)
             }
-            Err(_) => false,
+            // Missing object or unreachable server
+            Err(_) => Ok(false),
         }
     }
 
-    fn write(&self, path: &str, obj: &[u8]) -> Result<(), io::Error> {
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
         let key = self.full_path(path);
         if key.is_empty() {
-            return Err(io::Error::new(
-                io::ErrorKind::Other,
-                "failed to join key_prefix with path",
+            return Err(CacheError::Backend(
+                "failed to join key_prefix with path".to_string(),
             ));
         }
-        let extension = Path::new(path).extension().unwrap().to_str().unwrap();
-        let content_type = match extension {
-            "mvt" => Some("application/vnd.mapbox-vector-tile".to_string()),
-            "pbf" => Some("application/vnd.mapbox-vector-tile".to_string()),
-            "json" => Some("application/json".to_string()),
-            _ => Some("application/octet-stream".to_string()),
-        };
+        let content_type = Some(content_type(path).to_string());
         let mut content_encoding: Option<String> = None;
         if self.gzip_header_enabled()
             && content_type == Some("application/vnd.mapbox-vector-tile".to_string())
@@ -139,10 +171,18 @@ impl Cache for S3Cache {
         let response = self.client.put_object(request).sync();
         match response {
             Ok(_) => Ok(()),
-            Err(err) => Err(io::Error::new(io::ErrorKind::Other, err.to_string())),
+            Err(err) => Err(self.s3_err(err)),
         }
     }
 
+    /// Tiles are served with gzip `Content-Encoding` if enabled
+    fn preferred_encoding(&self) -> Encoding {
+        if self.gzip_header_enabled() {
+            Encoding::Gzip
+        } else {
+            Encoding::Identity
+        }
+    }
     fn exists(&self, path: &str) -> bool {
         let key = self.full_path(path);
         if key.is_empty() {
@@ -176,4 +216,38 @@ impl Cache for S3Cache {
             Err(_) => false,
         }
     }
+
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError> {
+        // S3 reports success when deleting a missing key
+        let key = self.full_path(&tile_path(tileset, zoom, xtile, ytile));
+        self.delete_key(&key)
+    }
+
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError> {
+        let prefix = self.full_path(&format!("{}/", tileset));
+        let mut continuation_token = None;
+        loop {
+            let request = ListObjectsV2Request {
+                bucket: self.bucket_name.to_owned(),
+                prefix: Some(prefix.clone()),
+                continuation_token: continuation_token.take(),
+                ..Default::default()
+            };
+            let response = self
+                .client
+                .list_objects_v2(request)
+                .sync()
+                .map_err(|err| self.s3_err(err))?;
+            for object in response.contents.unwrap_or_default() {
+                if let Some(key) = object.key {
+                    self.delete_key(&key)?;
+                }
+            }
+            if response.is_truncated != Some(true) {
+                break;
+            }
+            continuation_token = response.next_continuation_token;
+        }
+        Ok(())
+    }
 }
@@ -2,12 +2,58 @@
 // Copyright (c) Pirmin Kalberer. All rights reserved.
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
-use crate::cache::cache::Cache;
+use crate::cache::cache::{cache_key, Cache, KeyStrategy};
 use crate::cache::s3cache::S3Cache;
 use curl::easy::Easy;
 use std::env;
 use std::str;
 
+#[test]
+fn test_s3cache_keys() {
+    let cache = S3Cache::new(
+        "http://localhost:9000",
+        "trex",
+        "miniostorage",
+        "miniostorage",
+        "my-region",
+        None,
+        None,
+        None,
+    );
+    assert_eq!(cache.info(), "Tile cache s3: http://localhost:9000/trex");
+    assert_eq!(cache.baseurl(), "http://localhost:6767");
+    assert_eq!(cache.full_path("tileset/0/1/2.pbf"), "tileset/0/1/2.pbf");
+
+    let cache_prefix = S3Cache::new(
+        "http://localhost:9000",
+        "trex",
+        "miniostorage",
+        "miniostorage",
+        "my-region",
+        Some("http://example.com/tiles".to_string()),
+        Some("my-prefix".to_string()),
+        None,
+    );
+    assert_eq!(cache_prefix.baseurl(), "http://example.com/tiles");
+    assert_eq!(
+        cache_prefix.full_path("tileset/0/1/2.pbf"),
+        "my-prefix/tileset/0/1/2.pbf"
+    );
+
+    let cache_hashed = cache_prefix.with_key_strategy(KeyStrategy::Hashed);
+    assert_eq!(
+        cache_hashed.full_path("tileset/0/1/2.pbf"),
+        format!(
+            "my-prefix/{}",
+            cache_key(KeyStrategy::Hashed, "tileset", 0, 1, 2, "pbf")
+        )
+    );
+    assert_eq!(
+        cache_hashed.full_path("tileset.json"),
+        "my-prefix/tileset.json"
+    );
+}
+
 #[test]
 #[ignore]
 fn test_s3cache() {
@@ -29,7 +75,7 @@ fn test_s3cache() {
     let obj = "01234567910";
 
     // Cache miss
-    assert_eq!(cache.read(path, |_| {}), false);
+    assert_eq!(cache.read(path, |_| Ok(())).unwrap(), false);
 
     // Write into cache
     let e = cache.write(path, obj.as_bytes());
@@ -45,13 +91,16 @@ fn test_s3cache() {
     assert!(cache.exists(&path));
 
     // Cache hit
-    assert_eq!(cache.read(path, |_| {}), true);
+    assert_eq!(cache.read(path, |_| Ok(())).unwrap(), true);
 
     // Read from cache
     let mut s = String::new();
-    cache.read(path, |f| {
-        let _ = f.read_to_string(&mut s);
-    });
+    cache
+        .read(path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap();
     assert_eq!(&s, obj);
 
     // check if Content-Encoding header set by default
@@ -86,7 +135,7 @@ fn test_s3cache() {
     );
 
     // Cache miss
-    assert_eq!(cache_prefix.read(path, |_| {}), false);
+    assert_eq!(cache_prefix.read(path, |_| Ok(())).unwrap(), false);
 
     // Write into cache
     let e = cache_prefix.write(path, obj.as_bytes());
@@ -102,13 +151,16 @@ fn test_s3cache() {
     assert!(cache_prefix.exists(&path));
 
     // Cache hit
-    assert_eq!(cache_prefix.read(path, |_| {}), true);
+    assert_eq!(cache_prefix.read(path, |_| Ok(())).unwrap(), true);
 
     // Read from cache
     let mut s = String::new();
-    cache_prefix.read(path, |f| {
-        let _ = f.read_to_string(&mut s);
-    });
+    cache_prefix
+        .read(path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap();
     assert_eq!(&s, obj);
 
     // check if Content-Encoding header not set
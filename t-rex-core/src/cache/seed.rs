@@ -0,0 +1,118 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Parallel cache seeding
+
+use crate::cache::cache::{tile_path, Cache};
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use tile_grid::ExtentInt;
+
+/// Tile coordinates (zoom, x, y) within `limits` for all zoom levels in `zooms`
+fn tile_coords(
+    zooms: RangeInclusive<u8>,
+    limits: &[ExtentInt],
+) -> impl Iterator<Item = (u8, u32, u32)> + '_ {
+    zooms
+        .filter(move |zoom| (*zoom as usize) < limits.len())
+        .flat_map(move |zoom| {
+            let limit = &limits[zoom as usize];
+            (limit.minx..limit.maxx)
+                .flat_map(move |x| (limit.miny..limit.maxy).map(move |y| (zoom, x, y)))
+        })
+}
+
+/// Generate tiles of tileset within `limits` (tile extent per zoom level, max exclusive)
+/// and write them into `cache`.
+///
+/// Tiles are generated by `threads` workers, each pulling the next tile
+/// from a shared queue. `generate` returns `None` for empty tiles, which are not written.
+/// `progress` is called with the number of processed and total tiles after each tile.
+/// Returns the number of written tiles.
+///
+/// All tiles are regenerated, overwriting existing ones. Use [`seed_missing`]
+/// for skipping tiles already in the cache.
+pub fn seed<C, G, P>(
+    cache: &C,
+    tileset: &str,
+    zooms: RangeInclusive<u8>,
+    limits: &[ExtentInt],
+    threads: usize,
+    generate: G,
+    progress: P,
+) -> usize
+where
+    C: Cache + Sync,
+    G: Fn(u8, u32, u32) -> Option<Vec<u8>> + Sync,
+    P: Fn(u64, u64) + Sync,
+{
+    let total = tile_coords(zooms.clone(), limits).count() as u64;
+    let queue = Mutex::new(tile_coords(zooms, limits));
+    let done = AtomicU64::new(0);
+    let written = AtomicUsize::new(0);
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            s.spawn(|| loop {
+                // Release lock before generating the tile
+                let next = queue.lock().unwrap().next();
+                let (zoom, xtile, ytile) = match next {
+                    Some(coords) => coords,
+                    None => break,
+                };
+                if let Some(tile) = generate(zoom, xtile, ytile) {
+                    // Filecache handles concurrent directory creation
+                    // and writes via unique temporary files
+                    let path = tile_path(tileset, zoom, xtile, ytile);
+                    match cache.write(&path, &tile) {
+                        Ok(_) => {
+                            written.fetch_add(1, Ordering::Relaxed);
+                        }
+                        Err(ioerr) => error!("Error writing {}: {}", path, ioerr),
+                    }
+                }
+                progress(done.fetch_add(1, Ordering::Relaxed) + 1, total);
+            });
+        }
+    });
+    written.into_inner()
+}
+
+/// Like [`seed`], but tiles already in the cache are skipped without generating them.
+/// With a cache expiring tiles (e.g. `max_age` of [`Filecache`](crate::cache::Filecache)),
+/// only outdated and missing tiles are refreshed.
+/// Skipped tiles are reported as processed, but not counted as written.
+/// Use [`seed`] to force regenerating all tiles.
+pub fn seed_missing<C, G, P>(
+    cache: &C,
+    tileset: &str,
+    zooms: RangeInclusive<u8>,
+    limits: &[ExtentInt],
+    threads: usize,
+    generate: G,
+    progress: P,
+) -> usize
+where
+    C: Cache + Sync,
+    G: Fn(u8, u32, u32) -> Option<Vec<u8>> + Sync,
+    P: Fn(u64, u64) + Sync,
+{
+    seed(
+        cache,
+        tileset,
+        zooms,
+        limits,
+        threads,
+        |zoom, xtile, ytile| {
+            if cache.exists(&tile_path(tileset, zoom, xtile, ytile)) {
+                None
+            } else {
+                generate(zoom, xtile, ytile)
+            }
+        },
+        progress,
+    )
+}
@@ -0,0 +1,178 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::cache::Cache;
+use crate::cache::filecache::Filecache;
+use crate::cache::seed::{seed, seed_missing};
+use std::env;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tile_grid::ExtentInt;
+
+#[test]
+fn test_seed() {
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_seed");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        ..Default::default()
+    };
+    let world = ExtentInt {
+        minx: 0,
+        miny: 0,
+        maxx: 1,
+        maxy: 1,
+    };
+    // 2x2 tiles at zoom level 2
+    let limits = vec![
+        world.clone(),
+        world,
+        ExtentInt {
+            minx: 1,
+            miny: 2,
+            maxx: 3,
+            maxy: 4,
+        },
+    ];
+    let progress = AtomicU64::new(0);
+    let written = seed(
+        &cache,
+        "tileset",
+        2..=2,
+        &limits,
+        4,
+        |zoom, xtile, ytile| Some(format!("{}/{}/{}", zoom, xtile, ytile).into_bytes()),
+        |done, total| {
+            assert_eq!(total, 4);
+            progress.fetch_max(done, Ordering::Relaxed);
+        },
+    );
+    assert_eq!(written, 4);
+    assert_eq!(progress.load(Ordering::Relaxed), 4);
+    for (x, y) in &[(1, 2), (2, 2), (1, 3), (2, 3)] {
+        let path = format!("tileset/2/{}/{}.pbf", x, y);
+        let mut s = String::new();
+        assert!(cache
+            .read(&path, |f| {
+                f.read_to_string(&mut s)?;
+                Ok(())
+            })
+            .unwrap());
+        assert_eq!(s, format!("2/{}/{}", x, y));
+    }
+    assert!(!cache.exists("tileset/2/0/2.pbf"));
+
+    // Empty tiles are not written
+    let written = seed(
+        &cache,
+        "tileset",
+        0..=1,
+        &limits,
+        2,
+        |_, _, _| None,
+        |_, _| {},
+    );
+    assert_eq!(written, 0);
+    assert!(!cache.exists("tileset/0/0/0.pbf"));
+}
+
+#[test]
+fn test_seed_missing() {
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_seed_missing");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        ..Default::default()
+    };
+    // 2x2 tiles at zoom level 1
+    let limits = vec![
+        ExtentInt {
+            minx: 0,
+            miny: 0,
+            maxx: 1,
+            maxy: 1,
+        },
+        ExtentInt {
+            minx: 0,
+            miny: 0,
+            maxx: 2,
+            maxy: 2,
+        },
+    ];
+    cache.write("tileset/1/0/1.pbf", b"cached").unwrap();
+    let existing = cache.path_for_tile("tileset", 1, 0, 1, "pbf");
+    let mtime = fs::metadata(&existing).unwrap().modified().unwrap();
+
+    let generated = AtomicU64::new(0);
+    let written = seed_missing(
+        &cache,
+        "tileset",
+        1..=1,
+        &limits,
+        2,
+        |zoom, xtile, ytile| {
+            generated.fetch_add(1, Ordering::Relaxed);
+            Some(format!("{}/{}/{}", zoom, xtile, ytile).into_bytes())
+        },
+        |_, total| assert_eq!(total, 4),
+    );
+    assert_eq!(written, 3);
+    assert_eq!(generated.load(Ordering::Relaxed), 3);
+    assert_eq!(fs::metadata(&existing).unwrap().modified().unwrap(), mtime);
+    assert_eq!(fs::read_to_string(&existing).unwrap(), "cached");
+    for (x, y) in &[(0, 0), (1, 0), (1, 1)] {
+        assert!(cache.exists(&format!("tileset/1/{}/{}.pbf", x, y)));
+    }
+
+    // Forced seeding regenerates all tiles
+    let written = seed(
+        &cache,
+        "tileset",
+        1..=1,
+        &limits,
+        2,
+        |zoom, xtile, ytile| Some(format!("{}/{}/{}", zoom, xtile, ytile).into_bytes()),
+        |_, _| {},
+    );
+    assert_eq!(written, 4);
+    assert_eq!(fs::read_to_string(&existing).unwrap(), "1/0/1");
+}
+
+#[test]
+fn test_seed_missing_empty_sentinel() {
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_seed_sentinel");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = Filecache {
+        basepath: basepath.into(),
+        skip_empty: true,
+        empty_sentinel: true,
+        ..Default::default()
+    };
+    let limits = vec![ExtentInt {
+        minx: 0,
+        miny: 0,
+        maxx: 1,
+        maxy: 1,
+    }];
+    let generated = AtomicU64::new(0);
+    let generate = |_, _, _| {
+        generated.fetch_add(1, Ordering::Relaxed);
+        Some(Vec::new())
+    };
+    seed_missing(&cache, "tileset", 0..=0, &limits, 1, generate, |_, _| {});
+    assert!(cache.exists("tileset/0/0/0.pbf"));
+    // Known empty tile is not generated again
+    seed_missing(&cache, "tileset", 0..=0, &limits, 1, generate, |_, _| {});
+    assert_eq!(generated.load(Ordering::Relaxed), 1);
+}
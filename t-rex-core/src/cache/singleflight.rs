@@ -0,0 +1,196 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Cache wrapper generating missing tiles only once for concurrent requests
+
+use crate::cache::cache::{tile_path, Cache, CacheError, Encoding, TileMeta};
+use std::collections::HashMap;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Condvar, Mutex};
+
+#[derive(Default)]
+struct FlightState {
+    finished: bool,
+    /// Result of the generation, `None` if it failed
+    result: Option<Option<Vec<u8>>>,
+}
+
+/// Tile generation in progress
+#[derive(Default)]
+struct Flight {
+    state: Mutex<FlightState>,
+    done: Condvar,
+}
+
+type Flights = Mutex<HashMap<String, Arc<Flight>>>;
+
+/// Finishes a flight when dropped, also when the generation panics
+struct FlightGuard<'a> {
+    flights: &'a Flights,
+    path: &'a str,
+    flight: Arc<Flight>,
+    result: Option<Option<Vec<u8>>>,
+}
+
+impl Drop for FlightGuard<'_> {
+    fn drop(&mut self) {
+        self.flights.lock().unwrap().remove(self.path);
+        let mut state = self.flight.state.lock().unwrap();
+        state.finished = true;
+        state.result = self.result.take();
+        self.flight.done.notify_all();
+    }
+}
+
+/// Cache decorator preventing cache stampedes. Concurrent misses of the same tile
+/// wait for a single generation and share its result. Clones share in-flight generations.
+#[derive(Clone)]
+pub struct SingleFlightCache<C: Cache> {
+    cache: C,
+    flights: Arc<Flights>,
+}
+
+impl<C: Cache> SingleFlightCache<C> {
+    pub fn new(cache: C) -> SingleFlightCache<C> {
+        SingleFlightCache {
+            cache,
+            flights: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+    /// Wrapped cache
+    pub fn inner(&self) -> &C {
+        &self.cache
+    }
+    /// Cached tile or tile returned by `generate`, which is written into the cache.
+    /// `generate` returns `None` for empty tiles, which are not written.
+    ///
+    /// While a tile is generated, requests for the same tile wait for the result
+    /// instead of calling their `generate`. If the generation fails with an error or a panic,
+    /// waiting requests try again.
+    pub fn get_or_generate<G>(
+        &self,
+        tileset: &str,
+        zoom: u8,
+        xtile: u32,
+        ytile: u32,
+        generate: G,
+    ) -> Result<Option<Vec<u8>>, CacheError>
+    where
+        G: FnOnce() -> Option<Vec<u8>>,
+    {
+        let path = tile_path(tileset, zoom, xtile, ytile);
+        if let Some(tile) = self.cache.read_to_vec(&path)? {
+            return Ok(Some(tile));
+        }
+        let (flight, leader) = {
+            let mut flights = self.flights.lock().unwrap();
+            match flights.get(&path) {
+                Some(flight) => (flight.clone(), false),
+                None => {
+                    let flight = Arc::new(Flight::default());
+                    flights.insert(path.clone(), flight.clone());
+                    (flight, true)
+                }
+            }
+        };
+        if !leader {
+            debug!("SingleFlightCache: waiting for {}", path);
+            let result = {
+                let mut state = flight.state.lock().unwrap();
+                while !state.finished {
+                    state = flight.done.wait(state).unwrap();
+                }
+                state.result.clone()
+            };
+            return match result {
+                Some(tile) => Ok(tile),
+                None => {
+                    debug!("SingleFlightCache: generation of {} failed, retrying", path);
+                    self.get_or_generate(tileset, zoom, xtile, ytile, generate)
+                }
+            };
+        }
+
+        let mut guard = FlightGuard {
+            flights: &self.flights,
+            path: &path,
+            flight,
+            result: None,
+        };
+        // Tile may have been written by a generation finished after our lookup
+        if let Some(tile) = self.cache.read_to_vec(&path)? {
+            guard.result = Some(Some(tile.clone()));
+            return Ok(Some(tile));
+        }
+        let tile = generate();
+        if let Some(ref obj) = tile {
+            if let Err(e) = self.cache.write(&path, obj) {
+                error!("Error writing {}: {}", path, e);
+            }
+        }
+        guard.result = Some(tile.clone());
+        Ok(tile)
+    }
+}
+
+impl<C: Cache> Cache for SingleFlightCache<C> {
+    fn info(&self) -> String {
+        self.cache.info()
+    }
+    fn baseurl(&self) -> String {
+        self.cache.baseurl()
+    }
+    fn read<F>(&self, path: &str, read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
+    {
+        self.cache.read(path, read)
+    }
+    fn read_encoded<F>(&self, path: &str, read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read, Encoding) -> Result<(), io::Error>,
+    {
+        self.cache.read_encoded(path, read)
+    }
+    fn read_meta(&self, path: &str) -> Option<TileMeta> {
+        self.cache.read_meta(path)
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
+        self.cache.write(path, obj)
+    }
+    fn write_stream<F>(&self, path: &str, write: F) -> Result<(), CacheError>
+    where
+        F: FnOnce(&mut dyn Write) -> Result<(), io::Error>,
+    {
+        self.cache.write_stream(path, write)
+    }
+    fn write_batch<I>(&self, tileset_name: &str, tiles: I) -> Result<(), CacheError>
+    where
+        I: IntoIterator<Item = (u8, u32, u32, Vec<u8>)>,
+    {
+        self.cache.write_batch(tileset_name, tiles)
+    }
+    fn preferred_encoding(&self) -> Encoding {
+        self.cache.preferred_encoding()
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.cache.exists(path)
+    }
+    fn exists_many(&self, tileset: &str, coords: &[(u8, u32, u32)]) -> Vec<bool> {
+        self.cache.exists_many(tileset, coords)
+    }
+    fn remove(&self, path: &str) -> bool {
+        self.cache.remove(path)
+    }
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError> {
+        self.cache.delete(tileset, zoom, xtile, ytile)
+    }
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError> {
+        self.cache.delete_tileset(tileset)
+    }
+    fn healthcheck(&self) -> Result<(), CacheError> {
+        self.cache.healthcheck()
+    }
+}
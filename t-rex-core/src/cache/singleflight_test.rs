@@ -0,0 +1,92 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::recordingcache::RecordingCache;
+use crate::cache::singleflight::SingleFlightCache;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Barrier;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_singleflight() {
+    const READERS: usize = 8;
+    let cache = SingleFlightCache::new(RecordingCache::new());
+    let generated = AtomicUsize::new(0);
+    let barrier = Barrier::new(READERS);
+    let tiles: Vec<_> = thread::scope(|s| {
+        let handles: Vec<_> = (0..READERS)
+            .map(|_| {
+                s.spawn(|| {
+                    barrier.wait();
+                    cache
+                        .get_or_generate("tileset", 10, 536, 358, || {
+                            generated.fetch_add(1, Ordering::SeqCst);
+                            thread::sleep(Duration::from_millis(100));
+                            Some(b"tile".to_vec())
+                        })
+                        .unwrap()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    assert_eq!(generated.load(Ordering::SeqCst), 1);
+    assert!(tiles
+        .iter()
+        .all(|tile| tile.as_deref() == Some(&b"tile"[..])));
+    assert_eq!(
+        cache.inner().get("tileset", 10, 536, 358),
+        Some(b"tile".to_vec())
+    );
+
+    // Cached tile is not generated again
+    let tile = cache
+        .get_or_generate("tileset", 10, 536, 358, || panic!("cached"))
+        .unwrap();
+    assert_eq!(tile, Some(b"tile".to_vec()));
+
+    // Empty tiles are shared, but not cached
+    let tile = cache
+        .get_or_generate("tileset", 10, 536, 359, || None)
+        .unwrap();
+    assert_eq!(tile, None);
+    assert_eq!(cache.inner().len(), 1);
+}
+
+#[test]
+fn test_singleflight_failed() {
+    const READERS: usize = 4;
+    let cache = SingleFlightCache::new(RecordingCache::new());
+    let generated = AtomicUsize::new(0);
+    let barrier = Barrier::new(READERS);
+    let results: Vec<_> = thread::scope(|s| {
+        let handles: Vec<_> = (0..READERS)
+            .map(|_| {
+                s.spawn(|| {
+                    barrier.wait();
+                    cache
+                        .get_or_generate("tileset", 10, 536, 358, || {
+                            let cnt = generated.fetch_add(1, Ordering::SeqCst);
+                            thread::sleep(Duration::from_millis(100));
+                            if cnt == 0 {
+                                panic!("generation failed");
+                            }
+                            Some(b"tile".to_vec())
+                        })
+                        .unwrap()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join()).collect()
+    });
+    // Waiting requests generate the tile again instead of getting an empty tile
+    assert_eq!(generated.load(Ordering::SeqCst), 2);
+    assert_eq!(results.iter().filter(|r| r.is_err()).count(), 1);
+    assert!(results
+        .iter()
+        .filter_map(|r| r.as_ref().ok())
+        .all(|tile| tile.as_deref() == Some(&b"tile"[..])));
+}
@@ -0,0 +1,120 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Two-level cache with a fast front cache and a persistent backing cache
+
+use crate::cache::cache::{Cache, CacheError, Encoding, TileMeta};
+use std::io::{self, Cursor, Read};
+
+/// Cache reading from `front` first and falling back to `back`.
+/// Hits in `back` are promoted into `front`, writes go to both.
+#[derive(Clone)]
+pub struct TieredCache<A: Cache, B: Cache> {
+    pub front: A,
+    pub back: B,
+}
+
+impl<A: Cache, B: Cache> TieredCache<A, B> {
+    pub fn new(front: A, back: B) -> TieredCache<A, B> {
+        TieredCache { front, back }
+    }
+    /// Read from `back` and promote hits into `front`
+    fn read_back<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
+    {
+        // Buffer content for passing to callback and promoting into front cache
+        let data = match self.back.read_to_vec(path)? {
+            Some(data) => data,
+            None => return Ok(false),
+        };
+        if let Err(e) = self.front.write(path, &data) {
+            warn!("TieredCache: promoting {} failed: {}", path, e);
+        }
+        read(&mut Cursor::new(data))?;
+        Ok(true)
+    }
+}
+
+impl<A: Cache, B: Cache> Cache for TieredCache<A, B> {
+    fn info(&self) -> String {
+        format!("{} / {}", self.front.info(), self.back.info())
+    }
+    fn baseurl(&self) -> String {
+        self.back.baseurl()
+    }
+    fn read<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read) -> Result<(), io::Error>,
+    {
+        if self.front.read(path, &mut read)? {
+            return Ok(true);
+        }
+        self.read_back(path, read)
+    }
+    fn read_encoded<F>(&self, path: &str, mut read: F) -> Result<bool, CacheError>
+    where
+        F: FnMut(&mut dyn Read, Encoding) -> Result<(), io::Error>,
+    {
+        if self.front.read_encoded(path, &mut read)? {
+            return Ok(true);
+        }
+        self.read_back(path, |f| read(f, Encoding::Identity))
+    }
+    fn read_meta(&self, path: &str) -> Option<TileMeta> {
+        self.front
+            .read_meta(path)
+            .or_else(|| self.back.read_meta(path))
+    }
+    fn write(&self, path: &str, obj: &[u8]) -> Result<(), CacheError> {
+        self.back.write(path, obj)?;
+        self.front.write(path, obj)
+    }
+    fn write_batch<I>(&self, tileset_name: &str, tiles: I) -> Result<(), CacheError>
+    where
+        I: IntoIterator<Item = (u8, u32, u32, Vec<u8>)>,
+    {
+        let tiles: Vec<_> = tiles.into_iter().collect();
+        self.back.write_batch(tileset_name, tiles.clone())?;
+        self.front.write_batch(tileset_name, tiles)
+    }
+    /// Encoding of the persistent backing cache
+    fn preferred_encoding(&self) -> Encoding {
+        self.back.preferred_encoding()
+    }
+    fn exists(&self, path: &str) -> bool {
+        self.front.exists(path) || self.back.exists(path)
+    }
+    fn exists_many(&self, tileset: &str, coords: &[(u8, u32, u32)]) -> Vec<bool> {
+        let mut found = self.front.exists_many(tileset, coords);
+        let missing: Vec<usize> = (0..coords.len()).filter(|&i| !found[i]).collect();
+        let missing_coords: Vec<(u8, u32, u32)> = missing.iter().map(|&i| coords[i]).collect();
+        for (i, exists) in missing
+            .into_iter()
+            .zip(self.back.exists_many(tileset, &missing_coords))
+        {
+            found[i] = exists;
+        }
+        found
+    }
+    fn remove(&self, path: &str) -> bool {
+        let removed_front = self.front.remove(path);
+        let removed_back = self.back.remove(path);
+        removed_front || removed_back
+    }
+    fn delete(&self, tileset: &str, zoom: u8, xtile: u32, ytile: u32) -> Result<(), CacheError> {
+        self.front.delete(tileset, zoom, xtile, ytile)?;
+        self.back.delete(tileset, zoom, xtile, ytile)
+    }
+    fn delete_tileset(&self, tileset: &str) -> Result<(), CacheError> {
+        self.front.delete_tileset(tileset)?;
+        self.back.delete_tileset(tileset)
+    }
+    /// Both tiers have to pass
+    fn healthcheck(&self) -> Result<(), CacheError> {
+        self.front.healthcheck()?;
+        self.back.healthcheck()
+    }
+}
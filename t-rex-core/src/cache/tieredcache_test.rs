@@ -0,0 +1,73 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::cache::cache::Cache;
+use crate::cache::filecache::Filecache;
+use crate::cache::lrucache::Lrucache;
+use crate::cache::tieredcache::TieredCache;
+use std::env;
+use std::fs;
+
+#[test]
+fn test_tieredcache() {
+    let mut dir = env::temp_dir();
+    dir.push("t_rex_test_tiered");
+    let basepath = format!("{}", &dir.display());
+    let _ = fs::remove_dir_all(&basepath);
+
+    let cache = TieredCache::new(
+        Lrucache::new(Some(10), None),
+        Filecache {
+            basepath: basepath.into(),
+            ..Default::default()
+        },
+    );
+    let path = "tileset/0/0/0.pbf";
+
+    // Tile only in backing cache
+    cache.back.write(path, b"0123456789").unwrap();
+    assert!(!cache.front.exists(path));
+    assert!(cache.exists(path));
+
+    // First read promotes tile into front cache
+    let mut s = String::new();
+    assert!(cache
+        .read(path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(&s, "0123456789");
+    assert!(cache.front.exists(path));
+
+    // Served from front cache
+    cache.back.remove(path);
+    let mut s = String::new();
+    assert!(cache
+        .read(path, |f| {
+            f.read_to_string(&mut s)?;
+            Ok(())
+        })
+        .unwrap());
+    assert_eq!(&s, "0123456789");
+
+    // Write through
+    cache.write("tileset/1/0/0.pbf", b"1").unwrap();
+    assert!(cache.front.exists("tileset/1/0/0.pbf"));
+    assert!(cache.back.exists("tileset/1/0/0.pbf"));
+
+    assert!(!cache.read("tileset/2/0/0.pbf", |_| Ok(())).unwrap());
+}
+
+#[test]
+fn test_tieredcache_exists_many() {
+    let cache = TieredCache::new(Lrucache::new(None, None), Lrucache::new(None, None));
+    cache.front.write("tileset/0/0/0.pbf", b"front").unwrap();
+    cache.back.write("tileset/1/1/0.pbf", b"back").unwrap();
+    assert_eq!(
+        cache.exists_many("tileset", &[(1, 1, 0), (1, 0, 0), (0, 0, 0)]),
+        vec![true, false, true]
+    );
+}
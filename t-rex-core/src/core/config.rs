@@ -109,6 +109,8 @@ pub struct TilesetCfg {
     // Inline style
     pub style: Option<Value>,
     pub cache_limits: Option<TilesetCacheCfg>,
+    /// Simplification in screen units by zoom level
+    pub zoom_simplification: Option<Vec<TilesetSimplifyCfg>>,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -154,6 +156,9 @@ pub struct LayerCfg {
     pub tolerance: String,
     /// Tile buffer size in pixels (None: no clipping)
     pub buffer_size: Option<u32>,
+    /// Skip clipping of pre-clipped geometries (buffer is not enforced)
+    #[serde(default)]
+    pub no_clip: bool,
     /// Fix invalid geometries before clipping (lines and polygons)
     #[serde(default)]
     pub make_valid: bool,
@@ -174,6 +179,17 @@ pub fn default_tolerance() -> String {
     DEFAULT_TOLERANCE.to_string()
 }
 
+#[derive(Deserialize, Clone, Debug)]
+pub struct TilesetSimplifyCfg {
+    /// Lowest zoom level of the settings, valid up to the next entry
+    #[serde(default)]
+    pub minzoom: u8,
+    #[serde(default)]
+    pub tolerance: i32,
+    #[serde(default)]
+    pub quantize_grid: i32,
+}
+
 #[derive(Deserialize, Clone, Debug)]
 pub struct TilesetCacheCfg {
     #[serde(default)]
@@ -186,6 +202,7 @@ pub struct TilesetCacheCfg {
 #[derive(Deserialize, Clone, Debug)]
 pub struct CacheCfg {
     pub file: Option<CacheFileCfg>,
+    pub mbtiles: Option<CacheMbtilesCfg>,
     pub s3: Option<S3CacheFileCfg>,
 }
 
@@ -193,6 +210,35 @@ pub struct CacheCfg {
 pub struct CacheFileCfg {
     pub base: String,
     pub baseurl: Option<String>,
+    /// Directory layout: "simple" or "fanout"
+    pub layout: Option<String>,
+    /// Tile row numbering: "xyz" or "tms"
+    pub scheme: Option<String>,
+    pub gzip: Option<bool>,
+    /// Compression of stored tiles: "none", "gzip" or "brotli"
+    pub compression: Option<String>,
+    /// Maximal age of cached tiles in seconds
+    pub max_age: Option<u64>,
+    /// Maximal total size of cached files in bytes
+    pub max_bytes: Option<u64>,
+    /// Write `.meta` files with content type and write time
+    pub meta: Option<bool>,
+    /// Don't store empty tiles
+    pub skip_empty: Option<bool>,
+    /// Store empty tiles as zero-byte files when skipping empty tiles
+    pub empty_sentinel: Option<bool>,
+    /// Verify tile checksums when reading
+    pub checksum: Option<bool>,
+    /// Flush written tiles to disk before returning
+    pub sync: Option<bool>,
+    /// Check and clean up cache directory on startup
+    pub fsck: Option<bool>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct CacheMbtilesCfg {
+    pub base: String,
+    pub baseurl: Option<String>,
 }
 
 #[derive(Deserialize, Clone, Debug)]
@@ -24,7 +24,7 @@ pub trait Feature {
     fn geometry(&self) -> Result<GeometryType, String>;
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct FeatureAttr {
     pub key: String,
     pub value: FeatureAttrValType,
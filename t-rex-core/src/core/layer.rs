@@ -35,7 +35,8 @@ pub struct Layer {
     pub query: Vec<LayerQuery>,
     pub minzoom: Option<u8>,
     pub maxzoom: Option<u8>,
-    /// Width and height of the tiles
+    /// Width and height of the tiles in screen units.
+    /// Used as MVT layer extent (default 4096)
     pub tile_size: u32,
     /// Simplify geometry (lines and polygons)
     pub simplify: bool,
@@ -43,6 +44,8 @@ pub struct Layer {
     pub tolerance: String,
     /// Tile buffer size in pixels (None: no clipping)
     pub buffer_size: Option<u32>,
+    /// Skip clipping of pre-clipped geometries (buffer is not enforced)
+    pub no_clip: bool,
     /// Fix invalid geometries before clipping (lines and polygons)
     pub make_valid: bool,
     /// Apply ST_Shift_Longitude to (transformed) bbox
@@ -159,6 +162,7 @@ impl<'a> Config<'a, LayerCfg> for Layer {
             simplify: layer_cfg.simplify,
             tolerance: layer_cfg.tolerance.clone(),
             buffer_size: layer_cfg.buffer_size,
+            no_clip: layer_cfg.no_clip,
             make_valid: layer_cfg.make_valid,
             shift_longitude: layer_cfg.shift_longitude,
             style: style,
@@ -173,6 +177,8 @@ name = "points"
 #maxzoom = 22
 #attribution = "© Contributeurs de OpenStreetMap" # Acknowledgment of ownership, authorship or copyright.
 #cache_limits = {minzoom = 0, maxzoom = 22, no_cache = false}
+# Simplification in screen units from given zoom level up to the next entry
+#zoom_simplification = [{minzoom = 0, tolerance = 8}, {minzoom = 9, tolerance = 2}, {minzoom = 14, tolerance = 0}]
 
 [[tileset.layer]]
 name = "points"
@@ -182,6 +188,7 @@ geometry_type = "POINT"
 #simplify = true
 #tolerance = "!pixel_width!/2"
 #buffer_size = 10
+#no_clip = false
 #make_valid = true
 #[[tileset.layer.query]]
 #minzoom = 0
@@ -225,12 +232,15 @@ geometry_type = "POINT"
             lines.push(format!("fid_field = \"{}\"", fid_field));
         }
         if self.tile_size != 4096 {
-            lines.push(format!(r#"tile_size = "{}""#, self.tile_size));
+            lines.push(format!("tile_size = {}", self.tile_size));
         }
         match self.buffer_size {
             Some(ref buffer_size) => lines.push(format!("buffer_size = {}", buffer_size)),
             _ => lines.push(format!("#buffer_size = 10")),
         }
+        if self.no_clip {
+            lines.push(format!("no_clip = true"));
+        }
         match self.make_valid {
             true => lines.push(format!("make_valid = true")),
             _ => lines.push(format!("#make_valid = true")),
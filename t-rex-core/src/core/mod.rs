@@ -10,6 +10,8 @@ pub mod geom;
 mod gridcfg;
 pub mod layer;
 pub mod screen;
+#[cfg(feature = "with-geo-types")]
+pub mod screen_geo;
 pub mod stats;
 
 pub use self::config::{parse_config, read_config, ApplicationCfg, Config};
@@ -22,3 +24,7 @@ mod geom_test;
 mod gridcfg_test;
 #[cfg(test)]
 mod layer_test;
+#[cfg(all(test, feature = "with-geo-types"))]
+mod screen_geo_test;
+#[cfg(test)]
+mod screen_test;
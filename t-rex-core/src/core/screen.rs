@@ -5,41 +5,567 @@
 
 //! Geometry types in screen coordinates
 
+use std::ops::{Add, Sub};
 use std::vec::Vec;
+use tile_grid::Extent;
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Clone, Default, Debug)]
 pub struct Point {
     pub x: i32,
     pub y: i32,
 }
 
 impl Point {
+    /// ```
+    /// use t_rex_core::core::screen::Point;
+    ///
+    /// let point = Point::new(25, -17);
+    /// assert_eq!((point.x, point.y), (25, -17));
+    /// ```
+    pub fn new(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+    /// Point at `(0, 0)`. The MVT geometry cursor starts at the origin for every feature.
     pub fn origin() -> Point {
-        Point { x: 0, y: 0 }
+        Point::new(0, 0)
+    }
+    /// Corner opposite to the origin of a tile with `tile_size` screen units.
+    ///
+    /// ```
+    /// use t_rex_core::core::screen::Point;
+    ///
+    /// assert_eq!(Point::tile_corner(4096), Point::new(4096, 4096));
+    /// ```
+    pub fn tile_corner(tile_size: u32) -> Point {
+        Point::new(tile_size as i32, tile_size as i32)
+    }
+    /// Squared euclidean distance to `other`.
+    /// Saturates at `i64::MAX` for coordinates far apart.
+    pub fn distance_sq(&self, other: &Point) -> i64 {
+        saturate(self.delta_to(other).map(|d| d * d).iter().sum())
+    }
+    /// Squared distance to the line through `a` and `b` (rounded down).
+    /// Falls back to the distance to `a` if `a` and `b` are equal.
+    pub fn perpendicular_distance_sq(&self, a: &Point, b: &Point) -> i64 {
+        let [dx, dy] = a.delta_to(b);
+        let len_sq = dx * dx + dy * dy;
+        if len_sq == 0 {
+            return self.distance_sq(a);
+        }
+        let [px, py] = a.delta_to(self);
+        let cross = dx * py - dy * px;
+        match cross.checked_mul(cross) {
+            Some(cross_sq) => saturate(cross_sq / len_sq),
+            // Only possible for coordinates spanning the full i32 range
+            None => ((cross as f64).powi(2) / len_sq as f64).min(i64::MAX as f64) as i64,
+        }
+    }
+    /// Point snapped to the nearest multiple of `grid` (halves rounded up).
+    /// Grid sizes below 2 return the point unchanged.
+    pub fn quantize(&self, grid: i32) -> Point {
+        if grid < 2 {
+            return self.clone();
+        }
+        let snap = |v: i32| {
+            let grid = grid as i64;
+            let snapped = (v as i64 + grid / 2).div_euclid(grid) * grid;
+            snapped.min(i32::MAX as i64) as i32
+        };
+        Point {
+            x: snap(self.x),
+            y: snap(self.y),
+        }
+    }
+    /// Coordinate differences to `other`, which can't overflow
+    fn delta_to(&self, other: &Point) -> [i128; 2] {
+        [
+            other.x as i128 - self.x as i128,
+            other.y as i128 - self.y as i128,
+        ]
+    }
+}
+
+/// Clamp to i64 range
+fn saturate(v: i128) -> i64 {
+    v.max(i64::MIN as i128).min(i64::MAX as i128) as i64
+}
+
+/// Rounding of world coordinates to integer screen units.
+/// Adjacent tiles have to be generated with the same mode, otherwise coordinates
+/// on a shared tile edge can differ by one unit and produce visible seams.
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum Rounding {
+    /// Round to nearest, ties to even
+    #[default]
+    HalfEven,
+    /// Round to nearest, ties away from zero
+    HalfAwayFromZero,
+    /// Round towards negative infinity
+    Floor,
+    /// Round towards zero
+    Truncate,
+}
+
+impl Rounding {
+    pub fn apply(self, v: f64) -> f64 {
+        match self {
+            Rounding::HalfEven => {
+                let rounded = v.round();
+                if (v - v.trunc()).abs() == 0.5 {
+                    2.0 * (v / 2.0).round()
+                } else {
+                    rounded
+                }
+            }
+            Rounding::HalfAwayFromZero => v.round(),
+            Rounding::Floor => v.floor(),
+            Rounding::Truncate => v.trunc(),
+        }
+    }
+}
+
+/// Transformation of world coordinates within a tile extent into screen coordinates
+#[derive(Clone, Debug)]
+pub struct PointTransform {
+    minx: f64,
+    miny: f64,
+    pixel_size_x: f64,
+    pixel_size_y: f64,
+    tile_size: i32,
+    reverse_y: bool,
+    rounding: Rounding,
+}
+
+impl PointTransform {
+    /// Transformation into `0..tile_size` screen units.
+    /// With `reverse_y`, the y axis points down (origin at the top left corner).
+    pub fn new(extent: &Extent, tile_size: u32, reverse_y: bool) -> PointTransform {
+        PointTransform {
+            minx: extent.minx,
+            miny: extent.miny,
+            pixel_size_x: (extent.maxx - extent.minx) / tile_size as f64,
+            pixel_size_y: (extent.maxy - extent.miny) / tile_size as f64,
+            tile_size: tile_size as i32,
+            reverse_y,
+            rounding: Rounding::default(),
+        }
+    }
+    /// Transformation with rounding mode `rounding` (default: `Rounding::HalfEven`)
+    pub fn with_rounding(mut self, rounding: Rounding) -> PointTransform {
+        self.rounding = rounding;
+        self
+    }
+    /// Screen point of world coordinates, rounded to integer screen units.
+    /// Rounding is applied in world orientation, before reversing the y axis.
+    pub fn point(&self, x: f64, y: f64) -> Point {
+        let mut screen_pt = Point {
+            x: self.rounding.apply((x - self.minx) / self.pixel_size_x) as i32,
+            y: self.rounding.apply((y - self.miny) / self.pixel_size_y) as i32,
+        };
+        if self.reverse_y {
+            screen_pt.y = self.tile_size.saturating_sub(screen_pt.y)
+        }
+        screen_pt
+    }
+    /// World coordinates of a screen point (inverse of `point`)
+    pub fn world(&self, point: &Point) -> (f64, f64) {
+        let y = if self.reverse_y {
+            self.tile_size as f64 - point.y as f64
+        } else {
+            point.y as f64
+        };
+        (
+            self.minx + point.x as f64 * self.pixel_size_x,
+            self.miny + y * self.pixel_size_y,
+        )
     }
 }
 
-#[derive(PartialEq, Debug)]
+impl Add for &Point {
+    type Output = Point;
+    fn add(self, other: &Point) -> Point {
+        Point {
+            x: self.x + other.x,
+            y: self.y + other.y,
+        }
+    }
+}
+
+impl Add for Point {
+    type Output = Point;
+    fn add(self, other: Point) -> Point {
+        &self + &other
+    }
+}
+
+/// Delta from `other` to `self`
+impl Sub for &Point {
+    type Output = Point;
+    fn sub(self, other: &Point) -> Point {
+        Point {
+            x: self.x - other.x,
+            y: self.y - other.y,
+        }
+    }
+}
+
+impl Sub for Point {
+    type Output = Point;
+    fn sub(self, other: Point) -> Point {
+        &self - &other
+    }
+}
+
+#[derive(PartialEq, Clone, Default, Debug)]
 pub struct MultiPoint {
     pub points: Vec<Point>,
 }
 
-#[derive(PartialEq, Debug)]
+impl MultiPoint {
+    pub fn from_points(points: Vec<Point>) -> MultiPoint {
+        MultiPoint { points }
+    }
+    pub fn quantize(&self, grid: i32) -> MultiPoint {
+        MultiPoint {
+            points: quantize_points(&self.points, grid),
+        }
+    }
+    /// Bounding box (min, max corner). None for empty geometries.
+    pub fn bbox(&self) -> Option<(Point, Point)> {
+        points_bbox(self.points.iter())
+    }
+}
+
+#[derive(PartialEq, Clone, Default, Debug)]
 pub struct LineString {
     pub points: Vec<Point>,
 }
 
-#[derive(PartialEq, Debug)]
+impl LineString {
+    /// Line or polygon ring. Rings may be explicitly closed or open.
+    ///
+    /// ```
+    /// use t_rex_core::core::screen::{LineString, Point};
+    ///
+    /// let line = LineString::from_points(vec![Point::new(2, 2), Point::new(2, 10)]);
+    /// assert_eq!(line.points[1], Point::new(2, 10));
+    /// ```
+    pub fn from_points(points: Vec<Point>) -> LineString {
+        LineString { points }
+    }
+    /// Twice the signed area of a ring (surveyor's formula).
+    /// The ring may be explicitly closed (last point equal to first) or not.
+    /// Exterior rings have a positive area in screen coordinates (y axis pointing down).
+    /// The result saturates at the i64 range, keeping its sign.
+    pub fn double_signed_area(&self) -> i64 {
+        let cross = |a: &Point, b: &Point| a.x as i128 * b.y as i128 - b.x as i128 * a.y as i128;
+        let closing = match (self.points.last(), self.points.first()) {
+            (Some(last), Some(first)) => cross(last, first),
+            _ => 0,
+        };
+        saturate(
+            self.points
+                .windows(2)
+                .map(|w| cross(&w[0], &w[1]))
+                .sum::<i128>()
+                + closing,
+        )
+    }
+    /// Line with points in reverse order. Closed rings stay closed and keep
+    /// their start point, while their winding order is inverted.
+    pub fn reversed(&self) -> LineString {
+        LineString {
+            points: self.points.iter().rev().cloned().collect(),
+        }
+    }
+    /// Reverse point order in place, see `reversed`
+    pub fn reverse(&mut self) {
+        self.points.reverse();
+    }
+    /// Douglas-Peucker simplification with tolerance in screen units.
+    /// Closed rings are kept closed and are returned unchanged
+    /// if they would collapse below 4 points.
+    pub fn simplify(&self, tolerance: i32) -> LineString {
+        let points = douglas_peucker(&self.points, tolerance);
+        let is_ring = self.points.len() > 3 && self.points.first() == self.points.last();
+        if is_ring && points.len() < 4 {
+            return LineString {
+                points: self.points.clone(),
+            };
+        }
+        LineString { points }
+    }
+    /// Points snapped to `grid`. Resulting consecutive duplicates are removed when encoding.
+    pub fn quantize(&self, grid: i32) -> LineString {
+        LineString {
+            points: quantize_points(&self.points, grid),
+        }
+    }
+    /// Bounding box (min, max corner). None for empty geometries.
+    pub fn bbox(&self) -> Option<(Point, Point)> {
+        points_bbox(self.points.iter())
+    }
+    /// Line split into consecutive parts with at most `max_points` points
+    /// (at least 2). Each part starts at the last point of the previous part,
+    /// so the parts form a continuous line.
+    ///
+    /// ```
+    /// use t_rex_core::core::screen::{LineString, Point};
+    ///
+    /// let line = LineString::from_points((0..5).map(|x| Point::new(x, 0)).collect());
+    /// let parts = line.split(3);
+    /// assert_eq!(parts.len(), 2);
+    /// assert_eq!(parts[0].points.last(), parts[1].points.first());
+    /// ```
+    pub fn split(&self, max_points: usize) -> Vec<LineString> {
+        let max_points = max_points.max(2);
+        if self.points.len() <= max_points {
+            return vec![self.clone()];
+        }
+        let mut parts = Vec::new();
+        let mut start = 0;
+        while start + 1 < self.points.len() {
+            let end = (start + max_points).min(self.points.len());
+            parts.push(LineString {
+                points: self.points[start..end].to_vec(),
+            });
+            start = end - 1;
+        }
+        parts
+    }
+}
+
+fn quantize_points(points: &[Point], grid: i32) -> Vec<Point> {
+    points.iter().map(|p| p.quantize(grid)).collect()
+}
+
+/// Min and max corner of `points`
+fn points_bbox<'a, I>(points: I) -> Option<(Point, Point)>
+where
+    I: Iterator<Item = &'a Point>,
+{
+    points.fold(None, |bbox, p| match bbox {
+        None => Some((p.clone(), p.clone())),
+        Some((min, max)) => Some((
+            Point::new(min.x.min(p.x), min.y.min(p.y)),
+            Point::new(max.x.max(p.x), max.y.max(p.y)),
+        )),
+    })
+}
+
+fn douglas_peucker(points: &[Point], tolerance: i32) -> Vec<Point> {
+    if points.len() < 3 || tolerance <= 0 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let tolerance_sq = tolerance as i64 * tolerance as i64;
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let mut max_dist = 0;
+        let mut max_idx = first;
+        for i in first + 1..last {
+            let dist = points[i].perpendicular_distance_sq(&points[first], &points[last]);
+            if dist > max_dist {
+                max_dist = dist;
+                max_idx = i;
+            }
+        }
+        if max_dist > tolerance_sq {
+            keep[max_idx] = true;
+            stack.push((first, max_idx));
+            stack.push((max_idx, last));
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(p, _)| p.clone())
+        .collect()
+}
+
+#[derive(PartialEq, Clone, Default, Debug)]
 pub struct MultiLineString {
     pub lines: Vec<LineString>,
 }
 
-#[derive(PartialEq, Debug)]
+impl MultiLineString {
+    pub fn from_lines(lines: Vec<LineString>) -> MultiLineString {
+        MultiLineString { lines }
+    }
+    pub fn simplify(&self, tolerance: i32) -> MultiLineString {
+        MultiLineString {
+            lines: self.lines.iter().map(|l| l.simplify(tolerance)).collect(),
+        }
+    }
+    pub fn quantize(&self, grid: i32) -> MultiLineString {
+        MultiLineString {
+            lines: self.lines.iter().map(|l| l.quantize(grid)).collect(),
+        }
+    }
+    /// Bounding box (min, max corner). None for empty geometries.
+    pub fn bbox(&self) -> Option<(Point, Point)> {
+        points_bbox(self.lines.iter().flat_map(|l| &l.points))
+    }
+    /// Lines split into groups with at most `max_points` points in total.
+    /// Lines with more points are split with `LineString::split`.
+    pub fn split(&self, max_points: usize) -> Vec<MultiLineString> {
+        let mut parts = Vec::new();
+        let mut lines: Vec<LineString> = Vec::new();
+        let mut count = 0;
+        for piece in self.lines.iter().flat_map(|line| line.split(max_points)) {
+            if count + piece.points.len() > max_points && !lines.is_empty() {
+                parts.push(MultiLineString::from_lines(lines.split_off(0)));
+                count = 0;
+            }
+            count += piece.points.len();
+            lines.push(piece);
+        }
+        if !lines.is_empty() {
+            parts.push(MultiLineString::from_lines(lines));
+        }
+        parts
+    }
+}
+
+#[derive(PartialEq, Clone, Default, Debug)]
 pub struct Polygon {
     pub rings: Vec<LineString>,
 }
 
-#[derive(PartialEq, Debug)]
+impl Polygon {
+    /// Polygon with exterior ring followed by interior rings.
+    /// The winding order of the rings is normalized when encoding.
+    ///
+    /// ```
+    /// use t_rex_core::core::screen::{LineString, Point, Polygon};
+    ///
+    /// let ring = |coords: &[(i32, i32)]| {
+    ///     LineString::from_points(coords.iter().map(|&(x, y)| Point::new(x, y)).collect())
+    /// };
+    /// let polygon = Polygon::from_rings(vec![
+    ///     ring(&[(0, 0), (10, 0), (10, 10), (0, 10), (0, 0)]),
+    ///     ring(&[(2, 2), (2, 8), (8, 8), (8, 2), (2, 2)]),
+    /// ]);
+    /// assert_eq!(polygon.rings.len(), 2);
+    /// ```
+    pub fn from_rings(rings: Vec<LineString>) -> Polygon {
+        Polygon { rings }
+    }
+    pub fn simplify(&self, tolerance: i32) -> Polygon {
+        Polygon {
+            rings: self.rings.iter().map(|r| r.simplify(tolerance)).collect(),
+        }
+    }
+    pub fn quantize(&self, grid: i32) -> Polygon {
+        Polygon {
+            rings: self.rings.iter().map(|r| r.quantize(grid)).collect(),
+        }
+    }
+    /// Bounding box (min, max corner). None for empty geometries.
+    pub fn bbox(&self) -> Option<(Point, Point)> {
+        points_bbox(self.rings.iter().flat_map(|r| &r.points))
+    }
+}
+
+#[derive(PartialEq, Clone, Default, Debug)]
 pub struct MultiPolygon {
     pub polygons: Vec<Polygon>,
 }
+
+impl MultiPolygon {
+    pub fn from_polygons(polygons: Vec<Polygon>) -> MultiPolygon {
+        MultiPolygon { polygons }
+    }
+    pub fn simplify(&self, tolerance: i32) -> MultiPolygon {
+        MultiPolygon {
+            polygons: self
+                .polygons
+                .iter()
+                .map(|p| p.simplify(tolerance))
+                .collect(),
+        }
+    }
+    pub fn quantize(&self, grid: i32) -> MultiPolygon {
+        MultiPolygon {
+            polygons: self.polygons.iter().map(|p| p.quantize(grid)).collect(),
+        }
+    }
+    /// Bounding box (min, max corner). None for empty geometries.
+    pub fn bbox(&self) -> Option<(Point, Point)> {
+        points_bbox(
+            self.polygons
+                .iter()
+                .flat_map(|p| &p.rings)
+                .flat_map(|r| &r.points),
+        )
+    }
+}
+
+/// Any screen geometry
+#[derive(PartialEq, Clone, Debug)]
+pub enum Geometry {
+    Point(Point),
+    MultiPoint(MultiPoint),
+    LineString(LineString),
+    MultiLineString(MultiLineString),
+    Polygon(Polygon),
+    MultiPolygon(MultiPolygon),
+}
+
+impl Geometry {
+    /// Douglas-Peucker simplification of lines and polygons. Points are returned unchanged.
+    pub fn simplify(&self, tolerance: i32) -> Geometry {
+        match *self {
+            Geometry::Point(ref g) => Geometry::Point(g.clone()),
+            Geometry::MultiPoint(ref g) => Geometry::MultiPoint(MultiPoint {
+                points: g.points.clone(),
+            }),
+            Geometry::LineString(ref g) => Geometry::LineString(g.simplify(tolerance)),
+            Geometry::MultiLineString(ref g) => Geometry::MultiLineString(g.simplify(tolerance)),
+            Geometry::Polygon(ref g) => Geometry::Polygon(g.simplify(tolerance)),
+            Geometry::MultiPolygon(ref g) => Geometry::MultiPolygon(g.simplify(tolerance)),
+        }
+    }
+    /// Coordinates snapped to a grid of `grid` screen units
+    pub fn quantize(&self, grid: i32) -> Geometry {
+        match *self {
+            Geometry::Point(ref g) => Geometry::Point(g.quantize(grid)),
+            Geometry::MultiPoint(ref g) => Geometry::MultiPoint(g.quantize(grid)),
+            Geometry::LineString(ref g) => Geometry::LineString(g.quantize(grid)),
+            Geometry::MultiLineString(ref g) => Geometry::MultiLineString(g.quantize(grid)),
+            Geometry::Polygon(ref g) => Geometry::Polygon(g.quantize(grid)),
+            Geometry::MultiPolygon(ref g) => Geometry::MultiPolygon(g.quantize(grid)),
+        }
+    }
+    /// Number of points of all parts
+    pub fn num_points(&self) -> usize {
+        match *self {
+            Geometry::Point(_) => 1,
+            Geometry::MultiPoint(ref g) => g.points.len(),
+            Geometry::LineString(ref g) => g.points.len(),
+            Geometry::MultiLineString(ref g) => g.lines.iter().map(|l| l.points.len()).sum(),
+            Geometry::Polygon(ref g) => g.rings.iter().map(|r| r.points.len()).sum(),
+            Geometry::MultiPolygon(ref g) => g
+                .polygons
+                .iter()
+                .flat_map(|p| &p.rings)
+                .map(|r| r.points.len())
+                .sum(),
+        }
+    }
+    /// Bounding box (min, max corner), e.g. for rejecting geometries outside
+    /// of the tile before clipping. None for empty geometries.
+    pub fn bbox(&self) -> Option<(Point, Point)> {
+        match *self {
+            Geometry::Point(ref g) => Some((g.clone(), g.clone())),
+            Geometry::MultiPoint(ref g) => g.bbox(),
+            Geometry::LineString(ref g) => g.bbox(),
+            Geometry::MultiLineString(ref g) => g.bbox(),
+            Geometry::Polygon(ref g) => g.bbox(),
+            Geometry::MultiPolygon(ref g) => g.bbox(),
+        }
+    }
+}
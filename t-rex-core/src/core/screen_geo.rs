@@ -0,0 +1,156 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Conversions between geo-types geometries in world coordinates and screen geometries
+
+use crate::core::screen::{self, PointTransform};
+
+/// Conversion of a geo-types geometry into screen coordinates
+pub trait ToScreen {
+    type Screen;
+    fn to_screen(&self, transform: &PointTransform) -> Self::Screen;
+}
+
+/// Conversion of a screen geometry into geo-types world coordinates
+pub trait ToGeo {
+    type Geo;
+    fn to_geo(&self, transform: &PointTransform) -> Self::Geo;
+}
+
+impl ToScreen for geo_types::Point<f64> {
+    type Screen = screen::Point;
+    fn to_screen(&self, transform: &PointTransform) -> screen::Point {
+        transform.point(self.x(), self.y())
+    }
+}
+
+impl ToScreen for geo_types::MultiPoint<f64> {
+    type Screen = screen::MultiPoint;
+    fn to_screen(&self, transform: &PointTransform) -> screen::MultiPoint {
+        screen::MultiPoint::from_points(self.0.iter().map(|p| p.to_screen(transform)).collect())
+    }
+}
+
+impl ToScreen for geo_types::LineString<f64> {
+    type Screen = screen::LineString;
+    fn to_screen(&self, transform: &PointTransform) -> screen::LineString {
+        screen::LineString::from_points(
+            self.0
+                .iter()
+                .map(|coord| transform.point(coord.x, coord.y))
+                .collect(),
+        )
+    }
+}
+
+impl ToScreen for geo_types::MultiLineString<f64> {
+    type Screen = screen::MultiLineString;
+    fn to_screen(&self, transform: &PointTransform) -> screen::MultiLineString {
+        screen::MultiLineString::from_lines(self.0.iter().map(|l| l.to_screen(transform)).collect())
+    }
+}
+
+impl ToScreen for geo_types::Polygon<f64> {
+    type Screen = screen::Polygon;
+    fn to_screen(&self, transform: &PointTransform) -> screen::Polygon {
+        screen::Polygon::from_rings(
+            std::iter::once(self.exterior())
+                .chain(self.interiors())
+                .map(|ring| ring.to_screen(transform))
+                .collect(),
+        )
+    }
+}
+
+impl ToScreen for geo_types::MultiPolygon<f64> {
+    type Screen = screen::MultiPolygon;
+    fn to_screen(&self, transform: &PointTransform) -> screen::MultiPolygon {
+        screen::MultiPolygon::from_polygons(self.0.iter().map(|p| p.to_screen(transform)).collect())
+    }
+}
+
+/// Geometry collections have no screen equivalent and are converted to `None`
+impl ToScreen for geo_types::Geometry<f64> {
+    type Screen = Option<screen::Geometry>;
+    fn to_screen(&self, transform: &PointTransform) -> Option<screen::Geometry> {
+        use geo_types::Geometry::*;
+        let geom = match *self {
+            Point(ref g) => screen::Geometry::Point(g.to_screen(transform)),
+            MultiPoint(ref g) => screen::Geometry::MultiPoint(g.to_screen(transform)),
+            Line(ref g) => screen::Geometry::LineString(
+                geo_types::LineString::from(vec![g.start, g.end]).to_screen(transform),
+            ),
+            LineString(ref g) => screen::Geometry::LineString(g.to_screen(transform)),
+            MultiLineString(ref g) => screen::Geometry::MultiLineString(g.to_screen(transform)),
+            Polygon(ref g) => screen::Geometry::Polygon(g.to_screen(transform)),
+            MultiPolygon(ref g) => screen::Geometry::MultiPolygon(g.to_screen(transform)),
+            Rect(ref g) => screen::Geometry::Polygon(g.to_polygon().to_screen(transform)),
+            Triangle(ref g) => screen::Geometry::Polygon(g.to_polygon().to_screen(transform)),
+            GeometryCollection(_) => return None,
+        };
+        Some(geom)
+    }
+}
+
+impl ToGeo for screen::Point {
+    type Geo = geo_types::Point<f64>;
+    fn to_geo(&self, transform: &PointTransform) -> geo_types::Point<f64> {
+        transform.world(self).into()
+    }
+}
+
+impl ToGeo for screen::MultiPoint {
+    type Geo = geo_types::MultiPoint<f64>;
+    fn to_geo(&self, transform: &PointTransform) -> geo_types::MultiPoint<f64> {
+        geo_types::MultiPoint(self.points.iter().map(|p| p.to_geo(transform)).collect())
+    }
+}
+
+impl ToGeo for screen::LineString {
+    type Geo = geo_types::LineString<f64>;
+    fn to_geo(&self, transform: &PointTransform) -> geo_types::LineString<f64> {
+        self.points.iter().map(|p| transform.world(p)).collect()
+    }
+}
+
+impl ToGeo for screen::MultiLineString {
+    type Geo = geo_types::MultiLineString<f64>;
+    fn to_geo(&self, transform: &PointTransform) -> geo_types::MultiLineString<f64> {
+        geo_types::MultiLineString(self.lines.iter().map(|l| l.to_geo(transform)).collect())
+    }
+}
+
+/// The first ring is the exterior, an empty polygon has an empty exterior
+impl ToGeo for screen::Polygon {
+    type Geo = geo_types::Polygon<f64>;
+    fn to_geo(&self, transform: &PointTransform) -> geo_types::Polygon<f64> {
+        let mut rings = self.rings.iter().map(|ring| ring.to_geo(transform));
+        let exterior = rings
+            .next()
+            .unwrap_or_else(|| geo_types::LineString(Vec::new()));
+        geo_types::Polygon::new(exterior, rings.collect())
+    }
+}
+
+impl ToGeo for screen::MultiPolygon {
+    type Geo = geo_types::MultiPolygon<f64>;
+    fn to_geo(&self, transform: &PointTransform) -> geo_types::MultiPolygon<f64> {
+        geo_types::MultiPolygon(self.polygons.iter().map(|p| p.to_geo(transform)).collect())
+    }
+}
+
+impl ToGeo for screen::Geometry {
+    type Geo = geo_types::Geometry<f64>;
+    fn to_geo(&self, transform: &PointTransform) -> geo_types::Geometry<f64> {
+        match *self {
+            screen::Geometry::Point(ref g) => g.to_geo(transform).into(),
+            screen::Geometry::MultiPoint(ref g) => g.to_geo(transform).into(),
+            screen::Geometry::LineString(ref g) => g.to_geo(transform).into(),
+            screen::Geometry::MultiLineString(ref g) => g.to_geo(transform).into(),
+            screen::Geometry::Polygon(ref g) => g.to_geo(transform).into(),
+            screen::Geometry::MultiPolygon(ref g) => g.to_geo(transform).into(),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::core::screen::{self, PointTransform};
+use crate::core::screen_geo::{ToGeo, ToScreen};
+use crate::mvt::geom_encoder::EncodableGeom;
+use crate::mvt::vector_tile;
+use tile_grid::Extent;
+
+fn transform() -> PointTransform {
+    let extent = Extent {
+        minx: 1000.0,
+        miny: 2000.0,
+        maxx: 1000.0 + 4096.0 * 10.0,
+        maxy: 2000.0 + 4096.0 * 10.0,
+    };
+    PointTransform::new(&extent, 4096, true)
+}
+
+#[test]
+fn test_point() {
+    let point = geo_types::Point::new(1100.0, 2050.0);
+    let screen_pt = point.to_screen(&transform());
+    assert_eq!(screen_pt, screen::Point::new(10, 4091));
+    assert_eq!(screen_pt.to_geo(&transform()), point);
+}
+
+#[test]
+fn test_geometry() {
+    let line = geo_types::Geometry::Line(geo_types::Line::new(
+        geo_types::Coord {
+            x: 1000.0,
+            y: 2000.0,
+        },
+        geo_types::Coord {
+            x: 1100.0,
+            y: 2100.0,
+        },
+    ));
+    assert_eq!(
+        line.to_screen(&transform()),
+        Some(screen::Geometry::LineString(
+            screen::LineString::from_points(vec![
+                screen::Point::new(0, 4096),
+                screen::Point::new(10, 4086)
+            ])
+        ))
+    );
+    let collection = geo_types::Geometry::GeometryCollection(geo_types::GeometryCollection(vec![]));
+    assert_eq!(collection.to_screen(&transform()), None);
+}
+
+#[test]
+fn test_polygon_roundtrip() {
+    use geozero::mvt::Message;
+    use geozero::ToGeo as _;
+
+    let ring = |coords: &[(f64, f64)]| geo_types::LineString::from(coords.to_vec());
+    let polygon = geo_types::Polygon::new(
+        ring(&[
+            (1000.0, 2000.0),
+            (1400.0, 2000.0),
+            (1400.0, 2400.0),
+            (1000.0, 2400.0),
+            (1000.0, 2000.0),
+        ]),
+        vec![ring(&[
+            (1100.0, 2100.0),
+            (1100.0, 2300.0),
+            (1300.0, 2300.0),
+            (1300.0, 2100.0),
+            (1100.0, 2100.0),
+        ])],
+    );
+
+    let screen_polygon = polygon.to_screen(&transform());
+    assert_eq!(
+        screen_polygon.rings[0].points[1],
+        screen::Point::new(40, 4096)
+    );
+    assert_eq!(screen_polygon.to_geo(&transform()), polygon);
+
+    // Encode into an MVT tile and decode with geozero
+    let mut mvt_feature = vector_tile::Tile_Feature::new();
+    mvt_feature.set_field_type(screen_polygon.geom_type());
+    mvt_feature.set_geometry(screen_polygon.encode().unwrap().into_vec());
+    let mut mvt_layer = vector_tile::Tile_Layer::new();
+    mvt_layer.set_version(2);
+    mvt_layer.set_name(String::from("polygons"));
+    mvt_layer.set_extent(4096);
+    mvt_layer.mut_features().push(mvt_feature);
+    let mut mvt_tile = vector_tile::Tile::new();
+    mvt_tile.mut_layers().push(mvt_layer);
+    let bytes = protobuf::Message::write_to_bytes(&mvt_tile).unwrap();
+    let decoded = geozero::mvt::Tile::decode(bytes.as_slice()).unwrap();
+    let decoded = decoded.layers[0].features[0].to_geo().unwrap();
+
+    // Decoded geometry is in screen units
+    let identity = PointTransform::new(
+        &Extent {
+            minx: 0.0,
+            miny: 0.0,
+            maxx: 4096.0,
+            maxy: 4096.0,
+        },
+        4096,
+        false,
+    );
+    let decoded = match decoded.to_screen(&identity) {
+        Some(screen::Geometry::Polygon(p)) => p,
+        other => panic!("unexpected geometry {:?}", other),
+    };
+    // Rings are reoriented to the MVT winding order (exterior clockwise in world coordinates)
+    let reversed = |ring: &geo_types::LineString<f64>| {
+        geo_types::LineString(ring.0.iter().rev().cloned().collect())
+    };
+    let expected = geo_types::Polygon::new(
+        reversed(polygon.exterior()),
+        polygon.interiors().iter().map(reversed).collect(),
+    );
+    assert_eq!(decoded.to_geo(&transform()), expected);
+}
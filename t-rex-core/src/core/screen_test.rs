@@ -0,0 +1,373 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::core::screen::{
+    Geometry, LineString, MultiPolygon, Point, PointTransform, Polygon, Rounding,
+};
+use tile_grid::Extent;
+
+#[test]
+fn test_point_arithmetic() {
+    let p1 = Point { x: 10, y: -5 };
+    let p2 = Point { x: 3, y: 4 };
+    assert_eq!(&p1 - &p2, Point { x: 7, y: -9 });
+    assert_eq!(&p1 + &p2, Point { x: 13, y: -1 });
+    assert_eq!(p1.clone() - p1.clone(), Point::origin());
+    assert_eq!(p2.distance_sq(&Point::origin()), 25);
+    // No i32 overflow
+    let far = Point {
+        x: i32::MAX,
+        y: i32::MAX,
+    };
+    assert_eq!(
+        far.distance_sq(&Point::origin()),
+        2 * (i32::MAX as i64).pow(2)
+    );
+}
+
+#[test]
+fn test_perpendicular_distance() {
+    let a = Point { x: 0, y: 0 };
+    let b = Point { x: 8, y: 0 };
+    // Distance to the line, not only the segment
+    assert_eq!(Point { x: 4, y: 3 }.perpendicular_distance_sq(&a, &b), 9);
+    assert_eq!(Point { x: 20, y: -3 }.perpendicular_distance_sq(&a, &b), 9);
+    // 3-4-5 triangle: distance of (0,5) to line (0,0)-(4,3) is 4
+    let c = Point { x: 4, y: 3 };
+    assert_eq!(Point { x: 0, y: 5 }.perpendicular_distance_sq(&a, &c), 16);
+    // Point on the line
+    assert_eq!(Point { x: 8, y: 6 }.perpendicular_distance_sq(&a, &c), 0);
+    // Degenerate line
+    assert_eq!(Point { x: 3, y: 4 }.perpendicular_distance_sq(&a, &a), 25);
+}
+
+#[test]
+fn test_large_coordinates() {
+    let min = Point {
+        x: i32::MIN,
+        y: i32::MIN,
+    };
+    let max = Point {
+        x: i32::MAX,
+        y: i32::MAX,
+    };
+    // Differences don't overflow, results saturate
+    assert_eq!(min.distance_sq(&max), i64::MAX);
+    assert_eq!(
+        Point { x: -100, y: -50 }.distance_sq(&Point { x: 4200, y: 4200 }),
+        4300 * 4300 + 4250 * 4250
+    );
+    let corner = Point {
+        x: i32::MIN,
+        y: i32::MAX,
+    };
+    assert_eq!(corner.perpendicular_distance_sq(&min, &min), i64::MAX);
+    // (2^32-1)^2 / 2, computed in floating point
+    let dist = corner.perpendicular_distance_sq(&min, &max);
+    assert!(dist > 9_223_372_030_000_000_000, "{}", dist);
+    assert_eq!(max.perpendicular_distance_sq(&min, &max), 0);
+    let far = Point { x: -1, y: 1 << 30 };
+    assert_eq!(
+        far.perpendicular_distance_sq(
+            &Point {
+                x: -(1 << 30),
+                y: 0
+            },
+            &Point { x: 1 << 30, y: 0 }
+        ),
+        1 << 60
+    );
+
+    // Orientation of rings spanning the full coordinate range
+    let ring = LineString {
+        points: vec![
+            min.clone(),
+            Point {
+                x: i32::MAX,
+                y: i32::MIN,
+            },
+            max.clone(),
+            corner.clone(),
+            min.clone(),
+        ],
+    };
+    assert!(ring.double_signed_area() > 0);
+    let reversed = LineString {
+        points: ring.points.iter().rev().cloned().collect(),
+    };
+    assert!(reversed.double_signed_area() < 0);
+}
+
+#[test]
+fn test_simplify_line() {
+    // Nearly straight line with +-1 jitter
+    let line = LineString {
+        points: (0..100)
+            .map(|i| Point {
+                x: i * 10,
+                y: i % 2,
+            })
+            .collect(),
+    };
+    let simplified = line.simplify(2);
+    assert_eq!(
+        simplified.points,
+        vec![Point { x: 0, y: 0 }, Point { x: 990, y: 1 }]
+    );
+    // Tolerance 0 keeps all points
+    assert_eq!(line.simplify(0).points.len(), 100);
+}
+
+#[test]
+fn test_quantize() {
+    let snapped = |x, y| {
+        let p = Point::new(x, y).quantize(4);
+        (p.x, p.y)
+    };
+    assert_eq!(snapped(0, 3), (0, 4));
+    assert_eq!(snapped(5, 6), (4, 8));
+    assert_eq!(snapped(-5, -6), (-4, -4));
+    assert_eq!(snapped(4095, 4097), (4096, 4096));
+    assert_eq!(snapped(i32::MAX, i32::MIN), (i32::MAX, i32::MIN));
+    // No snapping
+    assert_eq!(Point::new(5, 6).quantize(1), Point::new(5, 6));
+    assert_eq!(Point::new(5, 6).quantize(0), Point::new(5, 6));
+}
+
+#[test]
+fn test_simplify_ring() {
+    let polygon = Polygon {
+        rings: vec![LineString {
+            points: vec![
+                Point { x: 0, y: 0 },
+                Point { x: 50, y: 1 },
+                Point { x: 100, y: 0 },
+                Point { x: 100, y: 100 },
+                Point { x: 0, y: 100 },
+                Point { x: 0, y: 0 },
+            ],
+        }],
+    };
+    let simplified = polygon.simplify(5);
+    assert_eq!(
+        simplified.rings[0].points,
+        vec![
+            Point { x: 0, y: 0 },
+            Point { x: 100, y: 0 },
+            Point { x: 100, y: 100 },
+            Point { x: 0, y: 100 },
+            Point { x: 0, y: 0 },
+        ]
+    );
+
+    // Ring doesn't collapse below 4 points
+    let small = LineString {
+        points: vec![
+            Point { x: 0, y: 0 },
+            Point { x: 2, y: 0 },
+            Point { x: 2, y: 2 },
+            Point { x: 0, y: 0 },
+        ],
+    };
+    assert_eq!(small.simplify(100), small);
+}
+
+#[test]
+fn test_point_transform() {
+    let extent = Extent {
+        minx: 1000.0,
+        miny: 2000.0,
+        maxx: 5096.0,
+        maxy: 10192.0,
+    };
+    let transform = PointTransform::new(&extent, 4096, true);
+    assert_eq!(transform.point(1000.0, 10192.0), Point { x: 0, y: 0 });
+    assert_eq!(transform.point(5096.0, 2000.0), Point { x: 4096, y: 4096 });
+    assert_eq!(transform.point(3048.0, 6096.0), Point { x: 2048, y: 2048 });
+    // Outside of the tile
+    assert_eq!(transform.point(0.0, 12192.0), Point { x: -1000, y: -1000 });
+    // Inverse transformation
+    assert_eq!(transform.world(&Point { x: 0, y: 0 }), (1000.0, 10192.0));
+    assert_eq!(
+        transform.world(&Point { x: 2048, y: 2048 }),
+        (3048.0, 6096.0)
+    );
+
+    // y axis pointing up
+    let transform = PointTransform::new(&extent, 4096, false);
+    assert_eq!(transform.point(1000.0, 10192.0), Point { x: 0, y: 4096 });
+    assert_eq!(transform.point(5096.0, 2000.0), Point { x: 4096, y: 0 });
+    assert_eq!(transform.world(&Point { x: 0, y: 4096 }), (1000.0, 10192.0));
+}
+
+#[test]
+fn test_point_transform_rounding() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let x_of = |rounding, x| {
+        PointTransform::new(&extent, 4096, false)
+            .with_rounding(rounding)
+            .point(x, 0.0)
+            .x
+    };
+    // Half boundaries: 2.5, 3.5, -2.5
+    assert_eq!(
+        [2.5, 3.5, -2.5].map(|x| x_of(Rounding::HalfEven, x)),
+        [2, 4, -2]
+    );
+    assert_eq!(
+        [2.5, 3.5, -2.5].map(|x| x_of(Rounding::HalfAwayFromZero, x)),
+        [3, 4, -3]
+    );
+    assert_eq!(
+        [2.5, 3.5, -2.5].map(|x| x_of(Rounding::Floor, x)),
+        [2, 3, -3]
+    );
+    assert_eq!(
+        [2.5, 3.5, -2.5].map(|x| x_of(Rounding::Truncate, x)),
+        [2, 3, -2]
+    );
+    // Default
+    assert_eq!(
+        PointTransform::new(&extent, 4096, false).point(2.5, 2.6),
+        Point::new(2, 3)
+    );
+
+    // Rounding is applied before reversing the y axis
+    let transform = PointTransform::new(&extent, 4096, true).with_rounding(Rounding::Floor);
+    assert_eq!(transform.point(0.0, 2.5), Point::new(0, 4094));
+}
+
+#[test]
+fn test_clone_default() {
+    let poly = Polygon {
+        rings: vec![LineString {
+            points: vec![Point::new(0, 0), Point::new(10, 0), Point::new(10, 10)],
+        }],
+    };
+    let mut copy = poly.clone();
+    assert_eq!(copy, poly);
+    // The clone owns its points
+    copy.rings[0].points[1].x = 20;
+    assert_ne!(copy, poly);
+    assert_eq!(poly.rings[0].points[1], Point::new(10, 0));
+
+    assert_eq!(Point::default(), Point::origin());
+    assert!(LineString::default().points.is_empty());
+    assert!(Polygon::default().rings.is_empty());
+}
+
+#[test]
+fn test_bbox() {
+    let ring = |coords: &[(i32, i32)]| {
+        LineString::from_points(coords.iter().map(|&(x, y)| Point::new(x, y)).collect())
+    };
+    let multipolygon = MultiPolygon::from_polygons(vec![
+        Polygon::from_rings(vec![ring(&[(0, 0), (10, 0), (10, 10), (0, 10), (0, 0)])]),
+        Polygon::from_rings(vec![
+            ring(&[(20, -5), (40, -5), (40, 30), (20, 30), (20, -5)]),
+            ring(&[(25, 0), (25, 20), (35, 20), (25, 0)]),
+        ]),
+    ]);
+    assert_eq!(
+        multipolygon.bbox(),
+        Some((Point::new(0, -5), Point::new(40, 30)))
+    );
+    assert_eq!(
+        Geometry::MultiPolygon(multipolygon).bbox(),
+        Some((Point::new(0, -5), Point::new(40, 30)))
+    );
+    assert_eq!(
+        Geometry::Point(Point::new(3, 4)).bbox(),
+        Some((Point::new(3, 4), Point::new(3, 4)))
+    );
+
+    // Empty geometries
+    assert_eq!(MultiPolygon::default().bbox(), None);
+    assert_eq!(Polygon::from_rings(vec![ring(&[])]).bbox(), None);
+    assert_eq!(LineString::default().bbox(), None);
+}
+
+#[test]
+fn test_point_constructors() {
+    assert_eq!(Point::origin(), Point::new(0, 0));
+    assert_eq!(Point::origin(), Point::default());
+    assert_eq!(Point::new(-3, 7), Point { x: -3, y: 7 });
+    assert_eq!(Point::tile_corner(256), Point { x: 256, y: 256 });
+    // Tile extent spans from origin to the opposite corner
+    let transform = PointTransform::new(
+        &Extent {
+            minx: 0.0,
+            miny: 0.0,
+            maxx: 100.0,
+            maxy: 100.0,
+        },
+        4096,
+        true,
+    );
+    assert_eq!(transform.point(0.0, 100.0), Point::origin());
+    assert_eq!(transform.point(100.0, 0.0), Point::tile_corner(4096));
+}
+
+#[test]
+fn test_split_lines() {
+    use crate::core::screen::MultiLineString;
+
+    let line = |n: i32| LineString::from_points((0..n).map(|x| Point::new(x, 0)).collect());
+    assert_eq!(line(3).split(3), vec![line(3)]);
+    let parts = line(7).split(3);
+    assert_eq!(parts.len(), 3);
+    assert_eq!(
+        parts[1].points,
+        vec![Point::new(2, 0), Point::new(3, 0), Point::new(4, 0)]
+    );
+    assert_eq!(parts[2].points.last(), Some(&Point::new(6, 0)));
+    // At least 2 points per part
+    assert_eq!(line(3).split(0).len(), 2);
+
+    // Short lines are grouped, long lines split
+    let multiline = MultiLineString::from_lines(vec![line(2), line(2), line(2), line(5)]);
+    let parts = multiline.split(4);
+    let sizes: Vec<Vec<usize>> = parts
+        .iter()
+        .map(|part| part.lines.iter().map(|l| l.points.len()).collect())
+        .collect();
+    assert_eq!(sizes, vec![vec![2, 2], vec![2], vec![4], vec![2]]);
+    assert_eq!(Geometry::MultiLineString(multiline).num_points(), 11);
+}
+
+#[test]
+fn test_reverse_line() {
+    let line =
+        LineString::from_points(vec![Point::new(0, 0), Point::new(10, 0), Point::new(10, 5)]);
+    let reversed = line.reversed();
+    assert_eq!(
+        reversed.points,
+        vec![Point::new(10, 5), Point::new(10, 0), Point::new(0, 0)]
+    );
+    assert_eq!(reversed.reversed(), line);
+
+    let ring = LineString::from_points(vec![
+        Point::new(0, 0),
+        Point::new(10, 0),
+        Point::new(10, 10),
+        Point::new(0, 0),
+    ]);
+    let mut reversed = ring.clone();
+    reversed.reverse();
+    assert_eq!(reversed, ring.reversed());
+    // Closed with the same start point, opposite winding order
+    assert_eq!(reversed.points.first(), reversed.points.last());
+    assert_eq!(reversed.points[0], ring.points[0]);
+    assert_eq!(reversed.double_signed_area(), -ring.double_signed_area());
+
+    let mut empty = LineString::from_points(vec![]);
+    empty.reverse();
+    assert!(empty.reversed().points.is_empty());
+}
@@ -37,6 +37,10 @@ pub trait DatasourceType {
     ) -> u64
     where
         F: FnMut(&dyn Feature);
+    /// Retrieved geometries of `layer` are already clipped to the buffered tile extent
+    fn clips_geometries(&self, _layer: &Layer) -> bool {
+        false
+    }
 }
 
 #[derive(Clone)]
@@ -287,7 +287,7 @@ impl PostgisDatasource {
         };
 
         // Clipping
-        if layer.buffer_size.is_some() {
+        if layer.buffer_size.is_some() && !layer.no_clip {
             let valid_geom = if layer.make_valid {
                 format!("ST_MakeValid({})", geom_expr)
             } else {
@@ -791,6 +791,10 @@ impl DatasourceType for PostgisDatasource {
         }
         cnt
     }
+    fn clips_geometries(&self, layer: &Layer) -> bool {
+        // See build_geom_expr
+        layer.buffer_size.is_some() && !layer.no_clip
+    }
 }
 
 impl<'a> Config<'a, DatasourceCfg> for PostgisDatasource {
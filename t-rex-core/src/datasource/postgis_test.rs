@@ -175,7 +175,10 @@ fn test_feature_query() {
         "SELECT geometry FROM osm_place_point WHERE geometry && ST_MakeEnvelope($1,$2,$3,$4,3857)"
     );
 
+    assert!(pg.clips_geometries(&layer));
+
     layer.buffer_size = None;
+    assert!(!pg.clips_geometries(&layer));
     layer.geometry_type = Some("POLYGON".to_string());
 
     // simplification
@@ -0,0 +1,258 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Clip screen geometries to the tile area
+//!
+//! Lines are clipped with the Liang-Barsky algorithm, polygon rings with Sutherland-Hodgman.
+
+use crate::core::screen;
+use std::mem;
+
+/// Clipping rectangle in screen coordinates
+#[derive(Clone, PartialEq, Debug)]
+pub struct ClipRect {
+    pub minx: i32,
+    pub miny: i32,
+    pub maxx: i32,
+    pub maxy: i32,
+}
+
+#[derive(Clone, Copy)]
+enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl ClipRect {
+    pub fn new(minx: i32, miny: i32, maxx: i32, maxy: i32) -> ClipRect {
+        ClipRect {
+            minx,
+            miny,
+            maxx,
+            maxy,
+        }
+    }
+
+    /// Tile area `0..extent` expanded by `buffer` on all sides
+    pub fn tile(extent: i32, buffer: i32) -> ClipRect {
+        ClipRect::new(-buffer, -buffer, extent + buffer, extent + buffer)
+    }
+
+    pub fn contains(&self, point: &screen::Point) -> bool {
+        point.x >= self.minx && point.x <= self.maxx && point.y >= self.miny && point.y <= self.maxy
+    }
+
+    /// Liang-Barsky segment clipping
+    fn clip_segment(
+        &self,
+        a: &screen::Point,
+        b: &screen::Point,
+    ) -> Option<(screen::Point, screen::Point)> {
+        let (x0, y0) = (a.x as f64, a.y as f64);
+        let (dx, dy) = (b.x as f64 - x0, b.y as f64 - y0);
+        let mut t0 = 0.0;
+        let mut t1 = 1.0;
+        let checks = [
+            (-dx, x0 - self.minx as f64),
+            (dx, self.maxx as f64 - x0),
+            (-dy, y0 - self.miny as f64),
+            (dy, self.maxy as f64 - y0),
+        ];
+        for &(p, q) in checks.iter() {
+            if p == 0.0 {
+                if q < 0.0 {
+                    return None;
+                }
+            } else {
+                let r = q / p;
+                if p < 0.0 {
+                    if r > t1 {
+                        return None;
+                    }
+                    if r > t0 {
+                        t0 = r;
+                    }
+                } else {
+                    if r < t0 {
+                        return None;
+                    }
+                    if r < t1 {
+                        t1 = r;
+                    }
+                }
+            }
+        }
+        let at = |t: f64| screen::Point {
+            x: (x0 + t * dx).round() as i32,
+            y: (y0 + t * dy).round() as i32,
+        };
+        let start = if t0 > 0.0 { at(t0) } else { a.clone() };
+        let end = if t1 < 1.0 { at(t1) } else { b.clone() };
+        Some((start, end))
+    }
+
+    fn inside(&self, edge: Edge, p: &screen::Point) -> bool {
+        match edge {
+            Edge::Left => p.x >= self.minx,
+            Edge::Right => p.x <= self.maxx,
+            Edge::Top => p.y >= self.miny,
+            Edge::Bottom => p.y <= self.maxy,
+        }
+    }
+
+    /// Intersection of segment `a`-`b` with the clip edge
+    fn intersection(&self, edge: Edge, a: &screen::Point, b: &screen::Point) -> screen::Point {
+        let (ax, ay, bx, by) = (a.x as f64, a.y as f64, b.x as f64, b.y as f64);
+        match edge {
+            Edge::Left | Edge::Right => {
+                let x = if let Edge::Left = edge {
+                    self.minx
+                } else {
+                    self.maxx
+                };
+                let t = (x as f64 - ax) / (bx - ax);
+                screen::Point {
+                    x,
+                    y: (ay + t * (by - ay)).round() as i32,
+                }
+            }
+            Edge::Top | Edge::Bottom => {
+                let y = if let Edge::Top = edge {
+                    self.miny
+                } else {
+                    self.maxy
+                };
+                let t = (y as f64 - ay) / (by - ay);
+                screen::Point {
+                    x: (ax + t * (bx - ax)).round() as i32,
+                    y,
+                }
+            }
+        }
+    }
+
+    /// Sutherland-Hodgman ring clipping. Returns None if less than a triangle remains.
+    fn clip_ring(&self, ring: &screen::LineString) -> Option<screen::LineString> {
+        if ring.points.iter().all(|p| self.contains(p)) {
+            return Some(screen::LineString {
+                points: ring.points.clone(),
+            });
+        }
+        let mut points = ring.points.clone();
+        if points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        for &edge in [Edge::Left, Edge::Right, Edge::Top, Edge::Bottom].iter() {
+            let input = mem::take(&mut points);
+            let mut prev = match input.last() {
+                Some(p) => p,
+                None => break,
+            };
+            for cur in &input {
+                if self.inside(edge, cur) {
+                    if !self.inside(edge, prev) {
+                        points.push(self.intersection(edge, prev, cur));
+                    }
+                    points.push(cur.clone());
+                } else if self.inside(edge, prev) {
+                    points.push(self.intersection(edge, prev, cur));
+                }
+                prev = cur;
+            }
+        }
+        points.dedup();
+        while points.len() > 1 && points.first() == points.last() {
+            points.pop();
+        }
+        if points.len() < 3 {
+            return None;
+        }
+        points.push(points[0].clone());
+        Some(screen::LineString { points })
+    }
+}
+
+impl screen::LineString {
+    /// Clip line to rectangle. Parts leaving and re-entering the rectangle become separate lines.
+    pub fn clip(&self, rect: &ClipRect) -> screen::MultiLineString {
+        if self.points.iter().all(|p| rect.contains(p)) {
+            return screen::MultiLineString {
+                lines: vec![screen::LineString {
+                    points: self.points.clone(),
+                }],
+            };
+        }
+        let mut lines = Vec::new();
+        let mut current: Vec<screen::Point> = Vec::new();
+        for segment in self.points.windows(2) {
+            match rect.clip_segment(&segment[0], &segment[1]) {
+                Some((start, end)) => {
+                    if current.last() != Some(&start) {
+                        if current.len() > 1 {
+                            lines.push(screen::LineString { points: current });
+                        }
+                        current = vec![start];
+                    }
+                    if current.last() != Some(&end) {
+                        current.push(end);
+                    }
+                }
+                None => {
+                    if current.len() > 1 {
+                        lines.push(screen::LineString { points: current });
+                    }
+                    current = Vec::new();
+                }
+            }
+        }
+        if current.len() > 1 {
+            lines.push(screen::LineString { points: current });
+        }
+        screen::MultiLineString { lines }
+    }
+}
+
+impl screen::MultiLineString {
+    pub fn clip(&self, rect: &ClipRect) -> screen::MultiLineString {
+        screen::MultiLineString {
+            lines: self
+                .lines
+                .iter()
+                .flat_map(|line| line.clip(rect).lines)
+                .collect(),
+        }
+    }
+}
+
+impl screen::Polygon {
+    /// Clip polygon to rectangle. The result has no rings if the exterior ring is outside.
+    pub fn clip(&self, rect: &ClipRect) -> screen::Polygon {
+        let mut rings = Vec::with_capacity(self.rings.len());
+        if let Some(exterior) = self.rings.first().and_then(|ring| rect.clip_ring(ring)) {
+            rings.push(exterior);
+            rings.extend(
+                self.rings[1..]
+                    .iter()
+                    .filter_map(|ring| rect.clip_ring(ring)),
+            );
+        }
+        screen::Polygon { rings }
+    }
+}
+
+impl screen::MultiPolygon {
+    pub fn clip(&self, rect: &ClipRect) -> screen::MultiPolygon {
+        screen::MultiPolygon {
+            polygons: self
+                .polygons
+                .iter()
+                .map(|polygon| polygon.clip(rect))
+                .filter(|polygon| !polygon.rings.is_empty())
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::core::screen;
+use crate::mvt::clip::ClipRect;
+use crate::mvt::geom_decoder::decode_lines;
+use crate::mvt::geom_encoder::{CommandSequence, EncodableGeom};
+
+#[test]
+fn test_clip_line() {
+    let rect = ClipRect::new(0, 0, 4096, 4096);
+    let line = screen::LineString {
+        points: vec![
+            screen::Point { x: 1000, y: 1000 },
+            screen::Point { x: 5000, y: 1000 },
+        ],
+    };
+    let clipped = line.clip(&rect);
+    assert_eq!(
+        clipped.lines,
+        vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 1000, y: 1000 },
+                screen::Point { x: 4096, y: 1000 },
+            ],
+        }]
+    );
+    let decoded = decode_lines(&clipped.encode().unwrap().0).unwrap();
+    assert!(decoded
+        .lines
+        .iter()
+        .flat_map(|l| l.points.iter())
+        .all(|p| rect.contains(p)));
+
+    // Line leaving and re-entering the tile is split
+    let line = screen::LineString {
+        points: vec![
+            screen::Point { x: 100, y: 100 },
+            screen::Point { x: 100, y: -100 },
+            screen::Point { x: 300, y: -100 },
+            screen::Point { x: 300, y: 100 },
+        ],
+    };
+    assert_eq!(
+        line.clip(&rect).lines,
+        vec![
+            screen::LineString {
+                points: vec![
+                    screen::Point { x: 100, y: 100 },
+                    screen::Point { x: 100, y: 0 },
+                ],
+            },
+            screen::LineString {
+                points: vec![
+                    screen::Point { x: 300, y: 0 },
+                    screen::Point { x: 300, y: 100 },
+                ],
+            },
+        ]
+    );
+
+    // Line completely outside
+    let line = screen::LineString {
+        points: vec![
+            screen::Point { x: -100, y: -100 },
+            screen::Point { x: 5000, y: -100 },
+        ],
+    };
+    assert!(line.clip(&rect).lines.is_empty());
+}
+
+#[test]
+fn test_clip_polygon() {
+    let rect = ClipRect::new(0, 0, 100, 100);
+    let polygon = screen::Polygon {
+        rings: vec![
+            screen::LineString {
+                points: vec![
+                    screen::Point { x: 50, y: 50 },
+                    screen::Point { x: 150, y: 50 },
+                    screen::Point { x: 150, y: 150 },
+                    screen::Point { x: 50, y: 150 },
+                    screen::Point { x: 50, y: 50 },
+                ],
+            },
+            // Hole outside of clip rect
+            screen::LineString {
+                points: vec![
+                    screen::Point { x: 120, y: 120 },
+                    screen::Point { x: 120, y: 130 },
+                    screen::Point { x: 130, y: 130 },
+                    screen::Point { x: 130, y: 120 },
+                    screen::Point { x: 120, y: 120 },
+                ],
+            },
+        ],
+    };
+    let clipped = polygon.clip(&rect);
+    assert_eq!(
+        clipped.rings,
+        vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 50, y: 100 },
+                screen::Point { x: 50, y: 50 },
+                screen::Point { x: 100, y: 50 },
+                screen::Point { x: 100, y: 100 },
+                screen::Point { x: 50, y: 100 },
+            ],
+        }]
+    );
+
+    let outside = screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 200, y: 200 },
+                screen::Point { x: 300, y: 200 },
+                screen::Point { x: 300, y: 300 },
+                screen::Point { x: 200, y: 200 },
+            ],
+        }],
+    };
+    assert!(outside.clip(&rect).rings.is_empty());
+    assert_eq!(
+        outside.clip(&rect).encode().unwrap(),
+        CommandSequence::new()
+    );
+}
+
+#[test]
+fn test_clip_buffer() {
+    // Line just outside of the tile, but within the buffer
+    let line = screen::LineString {
+        points: vec![
+            screen::Point { x: -50, y: 1000 },
+            screen::Point { x: -50, y: 2000 },
+            screen::Point { x: 4150, y: 2000 },
+        ],
+    };
+    assert_eq!(
+        line.clip(&ClipRect::tile(4096, 0)).lines[0].points,
+        vec![
+            screen::Point { x: 0, y: 2000 },
+            screen::Point { x: 4096, y: 2000 }
+        ]
+    );
+
+    let rect = ClipRect::tile(4096, 64);
+    assert_eq!(rect, ClipRect::new(-64, -64, 4160, 4160));
+    let clipped = line.clip(&rect);
+    assert_eq!(clipped.lines.len(), 1);
+    assert_eq!(clipped.lines[0], line);
+
+    // Deltas in the buffer zone round-trip
+    let decoded = decode_lines(&clipped.encode().unwrap().0).unwrap();
+    assert_eq!(decoded.lines[0], line);
+}
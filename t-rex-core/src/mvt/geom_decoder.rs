@@ -0,0 +1,206 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Decode MVT geometry command sequences into screen geometries
+//! https://github.com/mapbox/vector-tile-spec/tree/master/2.1#43-geometry-encoding
+
+use crate::core::screen;
+use crate::mvt::geom_encoder::{Command, CommandInteger, ParameterInteger};
+use crate::mvt::vector_tile;
+
+#[derive(PartialEq, Debug)]
+pub enum DecodeError {
+    /// Command id not defined in the spec
+    UnknownCommand(u32),
+    /// Command not allowed at this position for the geometry type
+    UnexpectedCommand(u32),
+    /// Stream ended before all parameters of a command were read
+    MissingParameters,
+    /// Line or ring with too few vertices, or ring without ClosePath
+    InvalidGeometry,
+    /// Geometry type UNKNOWN can't be decoded
+    UnknownGeomType,
+}
+
+/// Decode geometry commands of a feature with the given geometry type.
+/// Single and multi geometries share the same encoding,
+/// so the result is always the multi variant.
+pub fn decode(
+    geom_type: vector_tile::Tile_GeomType,
+    data: &[u32],
+) -> Result<screen::Geometry, DecodeError> {
+    match geom_type {
+        vector_tile::Tile_GeomType::POINT => decode_points(data).map(screen::Geometry::MultiPoint),
+        vector_tile::Tile_GeomType::LINESTRING => {
+            decode_lines(data).map(screen::Geometry::MultiLineString)
+        }
+        vector_tile::Tile_GeomType::POLYGON => {
+            decode_polygons(data).map(screen::Geometry::MultiPolygon)
+        }
+        vector_tile::Tile_GeomType::UNKNOWN => Err(DecodeError::UnknownGeomType),
+    }
+}
+
+/// Command stream reader keeping track of the cursor position
+struct Decoder<'a> {
+    data: &'a [u32],
+    idx: usize,
+    cursor: screen::Point,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(data: &'a [u32]) -> Decoder<'a> {
+        Decoder {
+            data,
+            idx: 0,
+            cursor: screen::Point::origin(),
+        }
+    }
+    /// Next command with its repeat count
+    fn next_command(&mut self) -> Result<Option<(Command, u32)>, DecodeError> {
+        let cmd = match self.data.get(self.idx) {
+            Some(v) => CommandInteger(*v),
+            None => return Ok(None),
+        };
+        self.idx += 1;
+        let id = match cmd.id() {
+            1 => Command::MoveTo,
+            2 => Command::LineTo,
+            7 => Command::ClosePath,
+            id => return Err(DecodeError::UnknownCommand(id)),
+        };
+        Ok(Some((id, cmd.count())))
+    }
+    /// Read a parameter pair and move the cursor
+    fn next_point(&mut self) -> Result<screen::Point, DecodeError> {
+        if self.idx + 1 >= self.data.len() {
+            return Err(DecodeError::MissingParameters);
+        }
+        let dx = ParameterInteger(self.data[self.idx]).value();
+        let dy = ParameterInteger(self.data[self.idx + 1]).value();
+        self.idx += 2;
+        self.cursor.x = self.cursor.x.wrapping_add(dx);
+        self.cursor.y = self.cursor.y.wrapping_add(dy);
+        Ok(screen::Point {
+            x: self.cursor.x,
+            y: self.cursor.y,
+        })
+    }
+}
+
+/// Number of vertices in a command sequence without decoding the geometry.
+/// Unknown commands end the count, truncated parameters are not counted.
+pub fn vertex_count(data: &[u32]) -> usize {
+    let mut vertices = 0;
+    let mut idx = 0;
+    while idx < data.len() {
+        let cmd = CommandInteger(data[idx]);
+        idx += 1;
+        match cmd.id() {
+            1 | 2 => {
+                let count = (cmd.count() as usize).min((data.len() - idx) / 2);
+                vertices += count;
+                idx += 2 * count;
+            }
+            7 => {}
+            _ => break,
+        }
+    }
+    vertices
+}
+
+pub fn decode_points(data: &[u32]) -> Result<screen::MultiPoint, DecodeError> {
+    let mut decoder = Decoder::new(data);
+    let mut points = Vec::new();
+    while let Some(cmd) = decoder.next_command()? {
+        match cmd {
+            (Command::MoveTo, count) if count > 0 => {
+                for _ in 0..count {
+                    points.push(decoder.next_point()?);
+                }
+            }
+            (id, _) => return Err(DecodeError::UnexpectedCommand(id as u32)),
+        }
+    }
+    Ok(screen::MultiPoint { points })
+}
+
+pub fn decode_lines(data: &[u32]) -> Result<screen::MultiLineString, DecodeError> {
+    let mut decoder = Decoder::new(data);
+    let mut lines: Vec<screen::LineString> = Vec::new();
+    while let Some(cmd) = decoder.next_command()? {
+        match cmd {
+            (Command::MoveTo, 1) => {
+                if matches!(lines.last(), Some(l) if l.points.len() < 2) {
+                    return Err(DecodeError::InvalidGeometry);
+                }
+                let start = decoder.next_point()?;
+                lines.push(screen::LineString {
+                    points: vec![start],
+                });
+            }
+            (Command::LineTo, count) if count > 0 && !lines.is_empty() => {
+                let line = lines.last_mut().unwrap();
+                for _ in 0..count {
+                    line.points.push(decoder.next_point()?);
+                }
+            }
+            (id, _) => return Err(DecodeError::UnexpectedCommand(id as u32)),
+        }
+    }
+    if matches!(lines.last(), Some(l) if l.points.len() < 2) {
+        return Err(DecodeError::InvalidGeometry);
+    }
+    Ok(screen::MultiLineString { lines })
+}
+
+/// Decode polygon rings. Rings with a positive area start a new polygon,
+/// the following rings with a negative area are its holes.
+pub fn decode_polygons(data: &[u32]) -> Result<screen::MultiPolygon, DecodeError> {
+    let mut decoder = Decoder::new(data);
+    let mut polygons: Vec<screen::Polygon> = Vec::new();
+    let mut ring: Option<screen::LineString> = None;
+    while let Some(cmd) = decoder.next_command()? {
+        match cmd {
+            (Command::MoveTo, 1) if ring.is_none() => {
+                let start = decoder.next_point()?;
+                ring = Some(screen::LineString {
+                    points: vec![start],
+                });
+            }
+            (Command::LineTo, count) if count > 0 && ring.is_some() => {
+                let points = &mut ring.as_mut().unwrap().points;
+                for _ in 0..count {
+                    points.push(decoder.next_point()?);
+                }
+            }
+            (Command::ClosePath, 1) if ring.is_some() => {
+                let mut closed = ring.take().unwrap();
+                if closed.points.len() < 3 {
+                    return Err(DecodeError::InvalidGeometry);
+                }
+                // ClosePath doesn't move the cursor, but rings are stored
+                // with the first point repeated
+                let first = screen::Point {
+                    x: closed.points[0].x,
+                    y: closed.points[0].y,
+                };
+                closed.points.push(first);
+                if closed.double_signed_area() > 0 || polygons.is_empty() {
+                    polygons.push(screen::Polygon {
+                        rings: vec![closed],
+                    });
+                } else {
+                    polygons.last_mut().unwrap().rings.push(closed);
+                }
+            }
+            (id, _) => return Err(DecodeError::UnexpectedCommand(id as u32)),
+        }
+    }
+    if ring.is_some() {
+        return Err(DecodeError::InvalidGeometry);
+    }
+    Ok(screen::MultiPolygon { polygons })
+}
@@ -0,0 +1,114 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::core::screen;
+use crate::mvt::geom_decoder::*;
+use crate::mvt::geom_encoder::EncodableGeom;
+use crate::mvt::vector_tile::Tile_GeomType;
+
+#[test]
+fn test_geom_decoding() {
+    let multipoint = screen::MultiPoint {
+        points: vec![screen::Point { x: 5, y: 7 }, screen::Point { x: 3, y: 2 }],
+    };
+    assert_eq!(
+        decode(Tile_GeomType::POINT, &[17, 10, 14, 3, 9]),
+        Ok(screen::Geometry::MultiPoint(multipoint))
+    );
+
+    // Cursor is carried from the end of the first line to the start of the second
+    let multilinestring = screen::MultiLineString {
+        lines: vec![
+            screen::LineString {
+                points: vec![
+                    screen::Point { x: 2, y: 2 },
+                    screen::Point { x: 2, y: 10 },
+                    screen::Point { x: 10, y: 10 },
+                ],
+            },
+            screen::LineString {
+                points: vec![screen::Point { x: 1, y: 1 }, screen::Point { x: 3, y: 5 }],
+            },
+        ],
+    };
+    assert_eq!(
+        decode(
+            Tile_GeomType::LINESTRING,
+            &[9, 4, 4, 18, 0, 16, 16, 0, 9, 17, 17, 10, 4, 8]
+        ),
+        Ok(screen::Geometry::MultiLineString(multilinestring))
+    );
+
+    // ClosePath re-emits the first point of the ring
+    let polygon = screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 3, y: 6 },
+                screen::Point { x: 8, y: 12 },
+                screen::Point { x: 20, y: 34 },
+                screen::Point { x: 3, y: 6 },
+            ],
+        }],
+    };
+    assert_eq!(
+        decode_polygons(&[9, 6, 12, 18, 10, 12, 24, 44, 15]),
+        Ok(screen::MultiPolygon {
+            polygons: vec![polygon]
+        })
+    );
+
+    let expected = [
+        9, 0, 0, 26, 20, 0, 0, 20, 19, 0, 15, 9, 22, 2, 26, 18, 0, 0, 18, 17, 0, 15, 9, 4, 13, 26,
+        0, 8, 8, 0, 0, 7, 15,
+    ];
+    let multipolygon = decode_polygons(&expected).unwrap();
+    assert_eq!(multipolygon.polygons.len(), 2);
+    assert_eq!(multipolygon.polygons[1].rings.len(), 2);
+    assert_eq!(multipolygon.encode().unwrap().0, &expected[..]);
+}
+
+#[test]
+fn test_decoding_errors() {
+    assert_eq!(
+        decode_points(&[17, 10, 14, 3]),
+        Err(DecodeError::MissingParameters)
+    );
+    assert_eq!(decode_points(&[12]), Err(DecodeError::UnknownCommand(4)));
+    assert_eq!(
+        decode_lines(&[18, 0, 16]),
+        Err(DecodeError::UnexpectedCommand(2))
+    );
+    assert_eq!(decode_lines(&[9, 4, 4]), Err(DecodeError::InvalidGeometry));
+    assert_eq!(
+        decode_polygons(&[9, 6, 12, 18, 10, 12, 24, 44]),
+        Err(DecodeError::InvalidGeometry)
+    );
+    assert_eq!(
+        decode_polygons(&[9, 6, 12, 10, 10, 12, 15]),
+        Err(DecodeError::InvalidGeometry)
+    );
+    assert_eq!(
+        decode(Tile_GeomType::UNKNOWN, &[9, 50, 34]),
+        Err(DecodeError::UnknownGeomType)
+    );
+}
+
+#[test]
+fn test_vertex_count() {
+    let line = screen::LineString::from_points(vec![
+        screen::Point::new(2, 2),
+        screen::Point::new(2, 10),
+        screen::Point::new(10, 10),
+    ]);
+    assert_eq!(vertex_count(&line.encode().unwrap().0), 3);
+    // Multipolygon with two rings closed by ClosePath
+    assert_eq!(
+        vertex_count(&[9, 0, 0, 26, 20, 0, 0, 20, 19, 0, 15, 9, 4, 4, 26, 8, 0, 0, 8, 7, 0, 15]),
+        8
+    );
+    assert_eq!(vertex_count(&[]), 0);
+    // Truncated parameters
+    assert_eq!(vertex_count(&[9, 50, 34, 18, 4]), 1);
+}
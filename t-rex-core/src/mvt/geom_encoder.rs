@@ -7,28 +7,82 @@
 //! https://github.com/mapbox/vector-tile-spec/tree/master/2.1
 
 use crate::core::screen;
+use crate::mvt::geom_decoder::{vertex_count, DecodeError};
+use crate::mvt::vector_tile;
+use std::fmt;
+use std::io::{self, Write};
 use std::vec::Vec;
 
 /// Command to be executed and the number of times that the command will be executed
 /// https://github.com/mapbox/vector-tile-spec/tree/master/2.1#431-command-integers
-struct CommandInteger(u32);
+///
+/// Command and parameter integers can be combined to encode geometries
+/// not covered by `EncodableGeom`:
+///
+/// ```
+/// use t_rex_core::mvt::geom_encoder::{Command, CommandInteger, ParameterInteger};
+///
+/// // Line (2,2) - (2,10) - (10,10)
+/// let geom = vec![
+///     CommandInteger::new(Command::MoveTo, 1).0,
+///     ParameterInteger::new(2).0,
+///     ParameterInteger::new(2).0,
+///     CommandInteger::new(Command::LineTo, 2).0,
+///     ParameterInteger::new(0).0,
+///     ParameterInteger::new(8).0,
+///     ParameterInteger::new(8).0,
+///     ParameterInteger::new(0).0,
+/// ];
+/// assert_eq!(geom, [9, 4, 4, 18, 0, 16, 16, 0]);
+/// ```
+pub struct CommandInteger(pub u32);
 
-enum Command {
+/// Command ids
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Command {
     MoveTo = 1,
     LineTo = 2,
     ClosePath = 7,
 }
 
+/// Maximal command count, limited by the 29 bits available in a command integer
+pub const MAX_COMMAND_COUNT: u32 = (1 << 29) - 1;
+
 impl CommandInteger {
-    fn new(id: Command, count: u32) -> CommandInteger {
+    /// Command `id` to be executed `count` times.
+    /// `count` must not exceed `MAX_COMMAND_COUNT`.
+    pub fn new(id: Command, count: u32) -> CommandInteger {
+        debug_assert!(
+            count <= MAX_COMMAND_COUNT,
+            "Command count {} too large",
+            count
+        );
         CommandInteger(((id as u32) & 0x7) | (count << 3))
     }
-    #[cfg(test)]
-    fn id(&self) -> u32 {
+    /// Like `new`, but returns an error for counts too large to be packed
+    pub fn try_new(id: Command, count: u32) -> Result<CommandInteger, String> {
+        if count > MAX_COMMAND_COUNT {
+            return Err(format!(
+                "Command count {} exceeds maximum of {}",
+                count, MAX_COMMAND_COUNT
+            ));
+        }
+        Ok(CommandInteger::new(id, count))
+    }
+    /// Command id (`Command as u32`)
+    ///
+    /// ```
+    /// use t_rex_core::mvt::geom_encoder::{Command, CommandInteger};
+    ///
+    /// let cmd = CommandInteger::new(Command::LineTo, 3);
+    /// assert_eq!(cmd.id(), Command::LineTo as u32);
+    /// assert_eq!(cmd.count(), 3);
+    /// ```
+    pub fn id(&self) -> u32 {
         self.0 & 0x7
     }
-    #[cfg(test)]
-    fn count(&self) -> u32 {
+    /// Number of times the command is executed
+    pub fn count(&self) -> u32 {
         self.0 >> 3
     }
 }
@@ -41,18 +95,41 @@ fn test_commands() {
     assert_eq!(CommandInteger::new(Command::MoveTo, 1).0, 9);
     assert_eq!(CommandInteger::new(Command::LineTo, 3).0, 26);
     assert_eq!(CommandInteger::new(Command::ClosePath, 1).0, 15);
+
+    let max = CommandInteger::try_new(Command::LineTo, MAX_COMMAND_COUNT).unwrap();
+    assert_eq!(max.id(), Command::LineTo as u32);
+    assert_eq!(max.count(), MAX_COMMAND_COUNT);
+    assert!(CommandInteger::try_new(Command::LineTo, MAX_COMMAND_COUNT + 1).is_err());
 }
 
 /// Commands requiring parameters are followed by a ParameterInteger for each parameter required by that command
 /// https://github.com/mapbox/vector-tile-spec/tree/master/2.1#432-parameter-integers
-struct ParameterInteger(u32);
+pub struct ParameterInteger(pub u32);
 
 impl ParameterInteger {
-    fn new(value: i32) -> ParameterInteger {
+    /// Zigzag encoded parameter value
+    ///
+    /// ```
+    /// use t_rex_core::mvt::geom_encoder::ParameterInteger;
+    ///
+    /// assert_eq!(ParameterInteger::new(-3).0, 5);
+    /// assert_eq!(ParameterInteger::new(-3).value(), -3);
+    /// ```
+    pub fn new(value: i32) -> ParameterInteger {
         ParameterInteger(((value << 1) ^ (value >> 31)) as u32)
     }
-    #[cfg(test)]
-    fn value(&self) -> i32 {
+    /// Zigzag encoded delta from `pos` to `value`.
+    /// Zigzag covers the full i32 range, but the difference of two coordinates
+    /// may not fit into an i32. A clamped delta would move the cursor of the
+    /// decoder to a wrong position, so these geometries can't be encoded.
+    fn delta(pos: i32, value: i32) -> Result<ParameterInteger, EncodeError> {
+        value
+            .checked_sub(pos)
+            .map(ParameterInteger::new)
+            .ok_or(EncodeError::DeltaOverflow { pos, value })
+    }
+    /// Decoded parameter value
+    pub fn value(&self) -> i32 {
         ((self.0 >> 1) as i32) ^ (-((self.0 & 1) as i32))
     }
 }
@@ -61,24 +138,169 @@ impl ParameterInteger {
 fn test_paremeters() {
     assert_eq!(ParameterInteger(50).value(), 25);
     assert_eq!(ParameterInteger::new(25).value(), 25);
+
+    // Zigzag encoding round-trips the full i32 range
+    for v in &[i32::MAX, i32::MAX - 1, i32::MIN, i32::MIN + 1, -1, 0] {
+        assert_eq!(ParameterInteger::new(*v).value(), *v);
+    }
+    // Out of range deltas are rejected
+    assert_eq!(
+        ParameterInteger::delta(-10, i32::MAX).err(),
+        Some(EncodeError::DeltaOverflow {
+            pos: -10,
+            value: i32::MAX
+        })
+    );
+    assert!(ParameterInteger::delta(10, i32::MIN).is_err());
+    assert_eq!(ParameterInteger::delta(-10, 2).unwrap().value(), 12);
 }
 
+/// Command with its relative parameter pairs (dx, dy)
+pub type ParsedCommand = (Command, Vec<(i32, i32)>);
+
+/// Encoded geometry. `Debug` shows commands with their count and decoded
+/// parameters, e.g. `MoveTo(1)[3,6] LineTo(2)[5,6,12,22] ClosePath(1)`.
+#[derive(PartialEq, Eq)]
 pub struct CommandSequence(pub Vec<u32>);
 
+impl fmt::Debug for CommandSequence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "(empty)");
+        }
+        // One command per line with `{:#?}`
+        let separator = if f.alternate() { "\n" } else { " " };
+        let mut idx = 0;
+        while idx < self.0.len() {
+            if idx > 0 {
+                write!(f, "{}", separator)?;
+            }
+            let cmd = CommandInteger(self.0[idx]);
+            idx += 1;
+            let num_params = match cmd.id() {
+                1 => {
+                    write!(f, "MoveTo")?;
+                    2 * cmd.count() as usize
+                }
+                2 => {
+                    write!(f, "LineTo")?;
+                    2 * cmd.count() as usize
+                }
+                7 => {
+                    write!(f, "ClosePath")?;
+                    0
+                }
+                id => {
+                    write!(f, "Unknown{}", id)?;
+                    0
+                }
+            };
+            write!(f, "({})", cmd.count())?;
+            if num_params > 0 {
+                let params = &self.0[idx..self.0.len().min(idx + num_params)];
+                idx += params.len();
+                let values: Vec<String> = params
+                    .iter()
+                    .map(|&p| ParameterInteger(p).value().to_string())
+                    .collect();
+                write!(f, "[{}]", values.join(","))?;
+                if params.len() < num_params {
+                    write!(f, "<missing {}>", num_params - params.len())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 impl CommandSequence {
     pub fn new() -> CommandSequence {
         CommandSequence(Vec::new())
     }
+    #[deprecated(note = "use `as_slice` or `into_vec` to avoid cloning")]
     pub fn vec(&self) -> Vec<u32> {
-        self.0.clone() // FIXME: ref
+        self.0.clone()
+    }
+    pub fn as_slice(&self) -> &[u32] {
+        &self.0
+    }
+    pub fn into_vec(self) -> Vec<u32> {
+        self.0
+    }
+    /// Number of command and parameter integers
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    /// Remove all integers, keeping the allocated capacity
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+    /// Size of the integers in bytes when encoded as varints,
+    /// without field tag and length prefix
+    pub fn encoded_size_hint(&self) -> usize {
+        self.0
+            .iter()
+            .map(|v| 1 + (31 - (v | 1).leading_zeros() as usize) / 7)
+            .sum()
+    }
+    /// Write integers as protobuf varints, without field tag and length prefix
+    pub fn write_varints<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut buf = [0u8; 5];
+        for &value in &self.0 {
+            let mut v = value;
+            let mut len = 0;
+            while v >= 0x80 {
+                buf[len] = (v as u8) | 0x80;
+                v >>= 7;
+                len += 1;
+            }
+            buf[len] = v as u8;
+            w.write_all(&buf[..=len])?;
+        }
+        Ok(())
     }
     #[cfg(test)]
     fn append(&mut self, other: &mut CommandSequence) {
         self.0.append(&mut other.0);
     }
-    fn push(&mut self, value: u32) {
+    /// Append command or parameter integer
+    pub fn push(&mut self, value: u32) {
         self.0.push(value);
     }
+    /// Group command integers with their decoded parameter pairs.
+    /// Parameters are returned as relative offsets, without accumulating the cursor position.
+    pub fn parse(&self) -> Result<Vec<ParsedCommand>, DecodeError> {
+        let mut commands = Vec::new();
+        let mut idx = 0;
+        while idx < self.0.len() {
+            let cmd = CommandInteger(self.0[idx]);
+            idx += 1;
+            let (command, num_pairs) = match cmd.id() {
+                1 => (Command::MoveTo, cmd.count() as usize),
+                2 => (Command::LineTo, cmd.count() as usize),
+                7 => (Command::ClosePath, 0),
+                id => return Err(DecodeError::UnknownCommand(id)),
+            };
+            if self.0.len() - idx < 2 * num_pairs {
+                return Err(DecodeError::MissingParameters);
+            }
+            let params = self.0[idx..idx + 2 * num_pairs]
+                .chunks(2)
+                .map(|p| {
+                    (
+                        ParameterInteger(p[0]).value(),
+                        ParameterInteger(p[1]).value(),
+                    )
+                })
+                .collect();
+            idx += 2 * num_pairs;
+            commands.push((command, params));
+        }
+        Ok(commands)
+    }
 }
 
 #[test]
@@ -93,103 +315,650 @@ fn test_sequence() {
     seq2.push(CommandInteger::new(Command::MoveTo, 1).0);
     seq.append(&mut seq2);
     assert_eq!(seq.0, &[9, 50, 34, 9]);
+    assert_eq!(seq.as_slice(), &[9, 50, 34, 9]);
+    assert_eq!(seq.into_vec(), vec![9, 50, 34, 9]);
 }
 
+/// Encoding of the closing segment of polygon rings
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum RingClosing {
+    /// ClosePath command as required by the spec
+    #[default]
+    ClosePath,
+    /// Explicit LineTo back to the start of the ring, for clients
+    /// not supporting ClosePath
+    LineTo,
+}
+
+/// Encoding of lines with less than 2 distinct points, which can't be encoded
+/// as valid line (MoveTo without LineTo)
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum DegenerateLines {
+    /// Skip degenerate lines
+    #[default]
+    Skip,
+    /// Encode as point geometry, if all lines of the geometry are degenerate
+    Point,
+}
+
+/// Encoding of consecutive coincident points in multipoints,
+/// which result in zero deltas
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub enum CoincidentPoints {
+    /// Encode all points
+    #[default]
+    Keep,
+    /// Encode only the first of consecutive coincident points
+    Dedup,
+}
+
+/// Error encoding a geometry. Only the affected feature is skipped.
+#[derive(PartialEq, Debug)]
+pub enum EncodeError {
+    /// Line or ring path without points
+    EmptyPath,
+    /// Number of points differs from the expected command count
+    PointCount { expected: usize },
+    /// Polygon with more vertices than allowed per feature
+    TooManyVertices { vertices: usize, max: usize },
+    /// Delta from cursor position `pos` to coordinate `value` exceeding the i32 range
+    DeltaOverflow { pos: i32, value: i32 },
+}
+
+/// Geometries encodable as MVT command sequence.
+/// Empty geometries and geometries with only empty parts produce an empty sequence.
 pub trait EncodableGeom {
-    fn encode(&self) -> CommandSequence {
+    fn encode(&self) -> Result<CommandSequence, EncodeError> {
         let mut seq = CommandSequence::new();
-        self.encode_from(&screen::Point::origin(), &mut seq);
-        seq
+        self.encode_into(&mut seq, &screen::Point::origin())?;
+        Ok(seq)
+    }
+    /// Append commands to `buf`, starting at cursor position `startpos`.
+    /// Reusing a cleared buffer for each feature avoids allocations.
+    /// On error, `buf` may contain partially encoded commands.
+    fn encode_into(
+        &self,
+        buf: &mut CommandSequence,
+        startpos: &screen::Point,
+    ) -> Result<(), EncodeError> {
+        self.encode_from(startpos, buf)
     }
-    fn encode_from(&self, startpos: &screen::Point, seq: &mut CommandSequence);
+    fn encode_from(
+        &self,
+        startpos: &screen::Point,
+        seq: &mut CommandSequence,
+    ) -> Result<(), EncodeError>;
+    /// MVT geometry type of the encoded geometry
+    fn geom_type(&self) -> vector_tile::Tile_GeomType;
 }
 
 impl EncodableGeom for screen::Point {
-    fn encode_from(&self, startpos: &screen::Point, seq: &mut CommandSequence) {
+    fn encode_from(
+        &self,
+        startpos: &screen::Point,
+        seq: &mut CommandSequence,
+    ) -> Result<(), EncodeError> {
         seq.push(CommandInteger::new(Command::MoveTo, 1).0);
-        seq.push(ParameterInteger::new(self.x.saturating_sub(startpos.x)).0);
-        seq.push(ParameterInteger::new(self.y.saturating_sub(startpos.y)).0);
+        seq.push(ParameterInteger::delta(startpos.x, self.x)?.0);
+        seq.push(ParameterInteger::delta(startpos.y, self.y)?.0);
+        Ok(())
+    }
+    fn geom_type(&self) -> vector_tile::Tile_GeomType {
+        vector_tile::Tile_GeomType::POINT
     }
 }
 
 impl EncodableGeom for screen::MultiPoint {
-    fn encode_from(&self, startpos: &screen::Point, seq: &mut CommandSequence) {
-        seq.push(CommandInteger::new(Command::MoveTo, self.points.len() as u32).0);
-        let (mut posx, mut posy) = (startpos.x, startpos.y);
-        for point in &self.points {
-            seq.push(ParameterInteger::new(point.x.saturating_sub(posx)).0);
-            seq.push(ParameterInteger::new(point.y.saturating_sub(posy)).0);
-            posx = point.x;
-            posy = point.y;
+    fn encode_from(
+        &self,
+        startpos: &screen::Point,
+        seq: &mut CommandSequence,
+    ) -> Result<(), EncodeError> {
+        push_runs(
+            seq,
+            Command::MoveTo,
+            self.points.iter(),
+            self.points.len(),
+            startpos,
+            MAX_COMMAND_COUNT,
+        )
+    }
+    fn geom_type(&self) -> vector_tile::Tile_GeomType {
+        vector_tile::Tile_GeomType::POINT
+    }
+}
+
+impl screen::MultiPoint {
+    /// Encode with given handling of consecutive coincident points
+    pub fn encode_with_coincident(
+        &self,
+        coincident: CoincidentPoints,
+    ) -> Result<CommandSequence, EncodeError> {
+        let mut seq = CommandSequence::new();
+        let origin = screen::Point::origin();
+        match coincident {
+            CoincidentPoints::Keep => self.encode_from(&origin, &mut seq)?,
+            CoincidentPoints::Dedup => {
+                let points = dedup_points(self.points.iter());
+                push_runs(
+                    &mut seq,
+                    Command::MoveTo,
+                    points.iter().copied(),
+                    points.len(),
+                    &origin,
+                    MAX_COMMAND_COUNT,
+                )?;
+            }
         }
+        Ok(seq)
     }
 }
 
 impl EncodableGeom for screen::LineString {
-    fn encode_from(&self, startpos: &screen::Point, seq: &mut CommandSequence) {
-        if self.points.len() > 1 {
-            self.points[0].encode_from(startpos, seq);
-            seq.push(CommandInteger::new(Command::LineTo, (self.points.len() - 1) as u32).0);
-            for i in 1..self.points.len() {
-                let ref pos = &self.points[i - 1];
-                let ref point = &self.points[i];
-                seq.push(ParameterInteger::new(point.x.saturating_sub(pos.x)).0);
-                seq.push(ParameterInteger::new(point.y.saturating_sub(pos.y)).0);
+    fn encode_from(
+        &self,
+        startpos: &screen::Point,
+        seq: &mut CommandSequence,
+    ) -> Result<(), EncodeError> {
+        self.encode_line_from(startpos, seq).map(|_| ())
+    }
+    fn geom_type(&self) -> vector_tile::Tile_GeomType {
+        vector_tile::Tile_GeomType::LINESTRING
+    }
+}
+
+/// Push `command` with the deltas to `points`, split into multiple commands
+/// if `count` (the number of points) exceeds `max_count`
+fn push_runs<'a, I>(
+    seq: &mut CommandSequence,
+    command: Command,
+    points: I,
+    count: usize,
+    startpos: &screen::Point,
+    max_count: u32,
+) -> Result<(), EncodeError>
+where
+    I: Iterator<Item = &'a screen::Point>,
+{
+    let (mut posx, mut posy) = (startpos.x, startpos.y);
+    let mut remaining = count;
+    let mut run = 0;
+    for point in points {
+        if run == 0 {
+            if remaining == 0 {
+                return Err(EncodeError::PointCount { expected: count });
             }
+            run = remaining.min(max_count as usize);
+            remaining -= run;
+            seq.push(CommandInteger::new(command, run as u32).0);
         }
+        seq.push(ParameterInteger::delta(posx, point.x)?.0);
+        seq.push(ParameterInteger::delta(posy, point.y)?.0);
+        posx = point.x;
+        posy = point.y;
+        run -= 1;
+    }
+    if remaining > 0 || run > 0 {
+        return Err(EncodeError::PointCount { expected: count });
     }
+    Ok(())
 }
+
+#[test]
+fn test_push_runs() {
+    let points: Vec<screen::Point> = (1..=5).map(|i| screen::Point::new(i, 0)).collect();
+    let mut seq = CommandSequence::new();
+    push_runs(
+        &mut seq,
+        Command::LineTo,
+        points.iter(),
+        points.len(),
+        &screen::Point::origin(),
+        2,
+    )
+    .unwrap();
+    assert_eq!(
+        format!("{:?}", seq),
+        "LineTo(2)[1,0,1,0] LineTo(2)[1,0,1,0] LineTo(1)[1,0]"
+    );
+    // Count not matching the points
+    let mut seq = CommandSequence::new();
+    let origin = screen::Point::origin();
+    assert_eq!(
+        push_runs(&mut seq, Command::LineTo, points.iter(), 4, &origin, 2),
+        Err(EncodeError::PointCount { expected: 4 })
+    );
+    assert_eq!(
+        push_runs(&mut seq, Command::LineTo, points.iter(), 6, &origin, 2),
+        Err(EncodeError::PointCount { expected: 6 })
+    );
+}
+
+/// Points without consecutive duplicates, which would be encoded as zero-length LineTo
+fn dedup_points<'a, I>(points: I) -> Vec<&'a screen::Point>
+where
+    I: Iterator<Item = &'a screen::Point>,
+{
+    let mut deduped: Vec<&screen::Point> = Vec::new();
+    for point in points {
+        if deduped.last() != Some(&point) {
+            deduped.push(point);
+        }
+    }
+    deduped
+}
+
 impl screen::LineString {
-    fn encode_ring_from(&self, startpos: &screen::Point, seq: &mut CommandSequence) {
-        // almost same as LineString.encode_from, with ClosePath instead of last point
-        if self.points.len() > 3 {
-            self.points[0].encode_from(startpos, seq);
-            seq.push(CommandInteger::new(Command::LineTo, (self.points.len() - 2) as u32).0);
-            for i in 1..self.points.len() - 1 {
-                let ref pos = &self.points[i - 1];
-                let ref point = &self.points[i];
-                seq.push(ParameterInteger::new(point.x.saturating_sub(pos.x)).0);
-                seq.push(ParameterInteger::new(point.y.saturating_sub(pos.y)).0);
+    /// Encode line, skipping lines with less than 2 distinct points.
+    /// Returns the new cursor position.
+    fn encode_line_from<'a>(
+        &'a self,
+        startpos: &'a screen::Point,
+        seq: &mut CommandSequence,
+    ) -> Result<&'a screen::Point, EncodeError> {
+        let points = dedup_points(self.points.iter());
+        if points.len() < 2 {
+            return Ok(startpos);
+        }
+        encode_path(&points, startpos, seq, None)
+    }
+}
+
+/// Polygon rings encodable as MVT command sequence.
+/// Used for encoding polygons and custom polygon-like geometries.
+///
+/// ```
+/// use t_rex_core::core::screen::{LineString, Point};
+/// use t_rex_core::mvt::geom_encoder::{CommandSequence, RingClosing, RingEncodable};
+///
+/// let ring = LineString::from_points(vec![
+///     Point::new(0, 0),
+///     Point::new(10, 0),
+///     Point::new(10, 10),
+///     Point::new(0, 10),
+///     Point::new(0, 0),
+/// ]);
+/// let origin = Point::origin();
+/// let mut seq = CommandSequence::new();
+/// let pos = ring
+///     .encode_ring_from(&origin, &mut seq, true, RingClosing::ClosePath)
+///     .unwrap();
+/// assert_eq!(
+///     format!("{:?}", seq),
+///     "MoveTo(1)[0,0] LineTo(3)[10,0,0,10,-10,0] ClosePath(1)"
+/// );
+/// // Cursor at the last encoded point
+/// assert_eq!(pos, &Point::new(0, 10));
+/// ```
+pub trait RingEncodable {
+    /// Encode ring in the winding order required by the spec
+    /// (exterior rings clockwise, interior rings counter-clockwise),
+    /// starting at cursor position `startpos`. Rings with less than 3 distinct
+    /// points are skipped. Returns the new cursor position.
+    fn encode_ring_from<'a>(
+        &'a self,
+        startpos: &'a screen::Point,
+        seq: &mut CommandSequence,
+        exterior: bool,
+        closing: RingClosing,
+    ) -> Result<&'a screen::Point, EncodeError>;
+}
+
+impl RingEncodable for screen::LineString {
+    /// Rings may be explicitly closed (first point repeated) or open.
+    fn encode_ring_from<'a>(
+        &'a self,
+        startpos: &'a screen::Point,
+        seq: &mut CommandSequence,
+        exterior: bool,
+        closing: RingClosing,
+    ) -> Result<&'a screen::Point, EncodeError> {
+        if self.points.len() < 3 {
+            return Ok(startpos);
+        }
+        let area = self.double_signed_area();
+        let points = if area != 0 && (area > 0) != exterior {
+            // Reverse keeping the start point. The repeated last point
+            // of a closed ring is removed as duplicate of the first.
+            let mut points = self.points.iter();
+            dedup_points(points.next().into_iter().chain(points.rev()))
+        } else {
+            dedup_points(self.points.iter())
+        };
+        encode_ring_points(&points, startpos, seq, closing)
+    }
+}
+
+fn encode_ring_points<'a>(
+    points: &[&'a screen::Point],
+    startpos: &'a screen::Point,
+    seq: &mut CommandSequence,
+    closing: RingClosing,
+) -> Result<&'a screen::Point, EncodeError> {
+    // ClosePath instead of a repeated first point
+    let closed = points.len() > 1 && points.first() == points.last();
+    let ring = if closed {
+        &points[..points.len() - 1]
+    } else {
+        points
+    };
+    // A valid ring needs at least 3 distinct points
+    if ring.len() < 3 {
+        return Ok(startpos);
+    }
+    encode_path(ring, startpos, seq, Some(closing))
+}
+
+/// Encode MoveTo to the first point and LineTo to the following points.
+/// Rings are closed according to `closing`. Returns the new cursor position.
+fn encode_path<'a>(
+    points: &[&'a screen::Point],
+    startpos: &screen::Point,
+    seq: &mut CommandSequence,
+    closing: Option<RingClosing>,
+) -> Result<&'a screen::Point, EncodeError> {
+    let (first, line_points) = points.split_first().ok_or(EncodeError::EmptyPath)?;
+    first.encode_from(startpos, seq)?;
+    let closing_point = match closing {
+        Some(RingClosing::LineTo) => &points[..1],
+        _ => &[],
+    };
+    push_runs(
+        seq,
+        Command::LineTo,
+        line_points.iter().chain(closing_point).copied(),
+        line_points.len() + closing_point.len(),
+        first,
+        MAX_COMMAND_COUNT,
+    )?;
+    let pos = closing_point
+        .last()
+        .or_else(|| points.last())
+        .unwrap_or(first);
+    if closing == Some(RingClosing::ClosePath) {
+        seq.push(CommandInteger::new(Command::ClosePath, 1).0);
+    }
+    Ok(pos)
+}
+
+impl screen::Polygon {
+    /// Index of the exterior ring.
+    /// Holes are within the exterior ring, so it is the ring with the largest area.
+    /// The input winding order can't be used, since it's not normalized.
+    fn exterior_index(&self) -> usize {
+        let mut exterior = 0;
+        let mut max_area = 0;
+        for (i, ring) in self.rings.iter().enumerate() {
+            let area = ring.double_signed_area().unsigned_abs();
+            if area > max_area {
+                exterior = i;
+                max_area = area;
             }
-            seq.push(CommandInteger::new(Command::ClosePath, 1).0);
         }
+        exterior
+    }
+    /// Encode rings, skipping degenerate rings. Returns the new cursor position.
+    /// If the first ring is a hole, the exterior ring is encoded first.
+    fn encode_polygon_from<'a>(
+        &'a self,
+        startpos: &'a screen::Point,
+        seq: &mut CommandSequence,
+        closing: RingClosing,
+    ) -> Result<&'a screen::Point, EncodeError> {
+        let exterior = self.exterior_index();
+        if exterior != 0 {
+            warn!(
+                "Polygon with hole as first ring - using ring {} as exterior",
+                exterior
+            );
+        }
+        let interiors = self
+            .rings
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != exterior)
+            .map(|(_, ring)| ring);
+        let mut pos = startpos;
+        for (i, ring) in self
+            .rings
+            .get(exterior)
+            .into_iter()
+            .chain(interiors)
+            .enumerate()
+        {
+            pos = ring.encode_ring_from(pos, seq, i == 0, closing)?;
+        }
+        Ok(pos)
+    }
+    /// Encode with given ring closing instead of ClosePath
+    pub fn encode_with_closing(
+        &self,
+        closing: RingClosing,
+    ) -> Result<CommandSequence, EncodeError> {
+        let mut seq = CommandSequence::new();
+        self.encode_polygon_from(&screen::Point::origin(), &mut seq, closing)?;
+        Ok(seq)
+    }
+}
+
+impl screen::MultiPolygon {
+    fn encode_multipolygon_from(
+        &self,
+        startpos: &screen::Point,
+        seq: &mut CommandSequence,
+        closing: RingClosing,
+    ) -> Result<(), EncodeError> {
+        let mut pos = startpos;
+        for polygon in &self.polygons {
+            pos = polygon.encode_polygon_from(pos, seq, closing)?;
+        }
+        Ok(())
+    }
+    /// Encode with given ring closing instead of ClosePath
+    pub fn encode_with_closing(
+        &self,
+        closing: RingClosing,
+    ) -> Result<CommandSequence, EncodeError> {
+        let mut seq = CommandSequence::new();
+        self.encode_multipolygon_from(&screen::Point::origin(), &mut seq, closing)?;
+        Ok(seq)
+    }
+}
+
+/// Encode lines with given handling of degenerate lines, together with the MVT geom type
+fn encode_lines_with_degenerate(
+    lines: &[screen::LineString],
+    degenerate: DegenerateLines,
+) -> Result<(vector_tile::Tile_GeomType, CommandSequence), EncodeError> {
+    if degenerate == DegenerateLines::Point {
+        let lines_points: Vec<_> = lines
+            .iter()
+            .map(|line| dedup_points(line.points.iter()))
+            .collect();
+        if lines_points.iter().all(|points| points.len() < 2) {
+            let points = lines_points
+                .iter()
+                .filter_map(|points| points.first())
+                .map(|&point| point.clone())
+                .collect();
+            let multipoint = screen::MultiPoint::from_points(points);
+            return Ok((multipoint.geom_type(), multipoint.encode()?));
+        }
+    }
+    let mut seq = CommandSequence::new();
+    let origin = screen::Point::origin();
+    let mut pos = &origin;
+    for line in lines {
+        pos = line.encode_line_from(pos, &mut seq)?;
+    }
+    Ok((vector_tile::Tile_GeomType::LINESTRING, seq))
+}
+
+impl screen::LineString {
+    /// Encode with given handling of degenerate lines, together with the MVT geom type
+    pub fn encode_with_degenerate(
+        &self,
+        degenerate: DegenerateLines,
+    ) -> Result<(vector_tile::Tile_GeomType, CommandSequence), EncodeError> {
+        encode_lines_with_degenerate(std::slice::from_ref(self), degenerate)
+    }
+}
+
+impl screen::MultiLineString {
+    /// Encode with given handling of degenerate lines, together with the MVT geom type.
+    /// With `DegenerateLines::Point`, degenerate lines are only encoded as points
+    /// if all lines are degenerate, since a feature can't mix geometry types.
+    pub fn encode_with_degenerate(
+        &self,
+        degenerate: DegenerateLines,
+    ) -> Result<(vector_tile::Tile_GeomType, CommandSequence), EncodeError> {
+        encode_lines_with_degenerate(&self.lines, degenerate)
     }
 }
 
 impl EncodableGeom for screen::MultiLineString {
-    fn encode_from(&self, startpos: &screen::Point, seq: &mut CommandSequence) {
+    fn encode_from(
+        &self,
+        startpos: &screen::Point,
+        seq: &mut CommandSequence,
+    ) -> Result<(), EncodeError> {
         let mut pos = startpos;
         for line in &self.lines {
-            if line.points.len() > 0 {
-                line.encode_from(&pos, seq);
-                pos = &line.points[line.points.len() - 1];
-            }
+            pos = line.encode_line_from(pos, seq)?;
         }
+        Ok(())
+    }
+    fn geom_type(&self) -> vector_tile::Tile_GeomType {
+        vector_tile::Tile_GeomType::LINESTRING
     }
 }
 
 impl EncodableGeom for screen::Polygon {
-    fn encode_from(&self, startpos: &screen::Point, seq: &mut CommandSequence) {
-        let mut pos = startpos;
-        for line in &self.rings {
-            if line.points.len() > 1 {
-                line.encode_ring_from(&pos, seq);
-                pos = &line.points[line.points.len() - 2];
-            }
-        }
+    fn encode_from(
+        &self,
+        startpos: &screen::Point,
+        seq: &mut CommandSequence,
+    ) -> Result<(), EncodeError> {
+        self.encode_polygon_from(startpos, seq, RingClosing::ClosePath)
+            .map(|_| ())
+    }
+    fn geom_type(&self) -> vector_tile::Tile_GeomType {
+        vector_tile::Tile_GeomType::POLYGON
     }
 }
 
 impl EncodableGeom for screen::MultiPolygon {
-    fn encode_from(&self, startpos: &screen::Point, seq: &mut CommandSequence) {
-        let mut pos = startpos;
-        for polygon in &self.polygons {
-            for line in &polygon.rings {
-                if line.points.len() > 1 {
-                    line.encode_ring_from(&pos, seq);
-                    pos = &line.points[line.points.len() - 2];
-                }
-            }
+    fn encode_from(
+        &self,
+        startpos: &screen::Point,
+        seq: &mut CommandSequence,
+    ) -> Result<(), EncodeError> {
+        self.encode_multipolygon_from(startpos, seq, RingClosing::ClosePath)
+    }
+    fn geom_type(&self) -> vector_tile::Tile_GeomType {
+        vector_tile::Tile_GeomType::POLYGON
+    }
+}
+
+impl EncodableGeom for screen::Geometry {
+    fn encode_from(
+        &self,
+        startpos: &screen::Point,
+        seq: &mut CommandSequence,
+    ) -> Result<(), EncodeError> {
+        match *self {
+            screen::Geometry::Point(ref g) => g.encode_from(startpos, seq),
+            screen::Geometry::MultiPoint(ref g) => g.encode_from(startpos, seq),
+            screen::Geometry::LineString(ref g) => g.encode_from(startpos, seq),
+            screen::Geometry::MultiLineString(ref g) => g.encode_from(startpos, seq),
+            screen::Geometry::Polygon(ref g) => g.encode_from(startpos, seq),
+            screen::Geometry::MultiPolygon(ref g) => g.encode_from(startpos, seq),
         }
     }
+    fn geom_type(&self) -> vector_tile::Tile_GeomType {
+        match *self {
+            screen::Geometry::Point(ref g) => g.geom_type(),
+            screen::Geometry::MultiPoint(ref g) => g.geom_type(),
+            screen::Geometry::LineString(ref g) => g.geom_type(),
+            screen::Geometry::MultiLineString(ref g) => g.geom_type(),
+            screen::Geometry::Polygon(ref g) => g.geom_type(),
+            screen::Geometry::MultiPolygon(ref g) => g.geom_type(),
+        }
+    }
+}
+
+/// Geometry split into parts with at most `max_vertices` points each, to be encoded
+/// as separate features. Lines are split into continuous parts. Polygons can't be split
+/// without changing their topology and return an error instead. Points are not split.
+pub fn split_max_vertices(
+    geom: &screen::Geometry,
+    max_vertices: usize,
+) -> Result<Vec<screen::Geometry>, EncodeError> {
+    let vertices = geom.num_points();
+    if vertices <= max_vertices {
+        return Ok(vec![geom.clone()]);
+    }
+    match *geom {
+        screen::Geometry::LineString(ref g) => Ok(g
+            .split(max_vertices)
+            .into_iter()
+            .map(screen::Geometry::LineString)
+            .collect()),
+        screen::Geometry::MultiLineString(ref g) => Ok(g
+            .split(max_vertices)
+            .into_iter()
+            .map(screen::Geometry::MultiLineString)
+            .collect()),
+        screen::Geometry::Polygon(_) | screen::Geometry::MultiPolygon(_) => {
+            Err(EncodeError::TooManyVertices {
+                vertices,
+                max: max_vertices,
+            })
+        }
+        screen::Geometry::Point(_) | screen::Geometry::MultiPoint(_) => Ok(vec![geom.clone()]),
+    }
+}
+
+/// Encode geometry of any type together with its MVT geom type
+pub fn encode_geometry(
+    geom: &screen::Geometry,
+) -> Result<(vector_tile::Tile_GeomType, CommandSequence), EncodeError> {
+    Ok((geom.geom_type(), geom.encode()?))
+}
+
+/// Encoded size and vertex count of a geometry without and with simplification
+#[derive(PartialEq, Debug)]
+pub struct SimplifyReport {
+    /// `encoded_size_hint` of the original geometry
+    pub size: usize,
+    /// `encoded_size_hint` of the simplified geometry
+    pub simplified_size: usize,
+    pub vertices: usize,
+    pub simplified_vertices: usize,
+}
+
+impl SimplifyReport {
+    /// Fraction of vertices removed by simplification (0.0 for empty geometries)
+    pub fn vertex_reduction(&self) -> f64 {
+        if self.vertices == 0 {
+            return 0.0;
+        }
+        1.0 - self.simplified_vertices as f64 / self.vertices as f64
+    }
+}
+
+/// Compare encoding with and without simplification, e.g. to choose a tolerance
+/// per zoom level. Nothing is written to a tile.
+pub fn simplify_report(
+    geom: &screen::Geometry,
+    tolerance: i32,
+) -> Result<SimplifyReport, EncodeError> {
+    let original = geom.encode()?;
+    let simplified = geom.simplify(tolerance).encode()?;
+    Ok(SimplifyReport {
+        size: original.encoded_size_hint(),
+        simplified_size: simplified.encoded_size_hint(),
+        vertices: vertex_count(original.as_slice()),
+        simplified_vertices: vertex_count(simplified.as_slice()),
+    })
 }
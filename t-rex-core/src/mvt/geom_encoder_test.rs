@@ -4,17 +4,22 @@
 //
 
 use crate::core::screen;
-use crate::mvt::geom_encoder::EncodableGeom;
+use crate::mvt::geom_decoder::DecodeError;
+use crate::mvt::geom_encoder::{
+    simplify_report, CoincidentPoints, Command, CommandSequence, DegenerateLines, EncodableGeom,
+    EncodeError, RingClosing,
+};
+use protobuf::CodedOutputStream;
 
 #[test]
 fn test_geom_encoding() {
     let point = screen::Point { x: 25, y: 17 };
-    assert_eq!(point.encode().0, &[9, 50, 34]);
+    assert_eq!(point.encode().unwrap().0, &[9, 50, 34]);
 
     let multipoint = screen::MultiPoint {
         points: vec![screen::Point { x: 5, y: 7 }, screen::Point { x: 3, y: 2 }],
     };
-    assert_eq!(multipoint.encode().0, &[17, 10, 14, 3, 9]);
+    assert_eq!(multipoint.encode().unwrap().0, &[17, 10, 14, 3, 9]);
 
     let linestring = screen::LineString {
         points: vec![
@@ -23,7 +28,7 @@ fn test_geom_encoding() {
             screen::Point { x: 10, y: 10 },
         ],
     };
-    assert_eq!(linestring.encode().0, &[9, 4, 4, 18, 0, 16, 16, 0]);
+    assert_eq!(linestring.encode().unwrap().0, &[9, 4, 4, 18, 0, 16, 16, 0]);
 
     let multilinestring = screen::MultiLineString {
         lines: vec![
@@ -40,7 +45,7 @@ fn test_geom_encoding() {
         ],
     };
     assert_eq!(
-        multilinestring.encode().0,
+        multilinestring.encode().unwrap().0,
         &[9, 4, 4, 18, 0, 16, 16, 0, 9, 17, 17, 10, 4, 8]
     );
 
@@ -54,7 +59,10 @@ fn test_geom_encoding() {
             ],
         }],
     };
-    assert_eq!(polygon.encode().0, &[9, 6, 12, 18, 10, 12, 24, 44, 15]);
+    assert_eq!(
+        polygon.encode().unwrap().0,
+        &[9, 6, 12, 18, 10, 12, 24, 44, 15]
+    );
 
     let multipolygon = screen::MultiPolygon {
         polygons: vec![
@@ -94,11 +102,45 @@ fn test_geom_encoding() {
             },
         ],
     };
+    // Duplicate point (11, 20) is dropped
     let expected = [
-        9, 0, 0, 26, 20, 0, 0, 20, 19, 0, 15, 9, 22, 2, 34, 18, 0, 0, 18, 17, 0, 0, 0, 15, 9, 4,
-        13, 26, 0, 8, 8, 0, 0, 7, 15,
+        9, 0, 0, 26, 20, 0, 0, 20, 19, 0, 15, 9, 22, 2, 26, 18, 0, 0, 18, 17, 0, 15, 9, 4, 13, 26,
+        0, 8, 8, 0, 0, 7, 15,
     ];
-    assert_eq!(multipolygon.encode().0, &expected[0..35]);
+    assert_eq!(multipolygon.encode().unwrap().0, &expected[..]);
+}
+
+#[test]
+fn test_parse() {
+    let polygon = screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 3, y: 6 },
+                screen::Point { x: 8, y: 12 },
+                screen::Point { x: 20, y: 34 },
+                screen::Point { x: 3, y: 6 },
+            ],
+        }],
+    };
+    assert_eq!(
+        polygon.encode().unwrap().parse().unwrap(),
+        vec![
+            (Command::MoveTo, vec![(3, 6)]),
+            (Command::LineTo, vec![(5, 6), (12, 22)]),
+            (Command::ClosePath, vec![]),
+        ]
+    );
+
+    assert_eq!(CommandSequence::new().parse().unwrap(), vec![]);
+    // LineTo(2) with one parameter pair
+    assert_eq!(
+        CommandSequence(vec![9, 4, 4, 18, 0, 16]).parse(),
+        Err(DecodeError::MissingParameters)
+    );
+    assert_eq!(
+        CommandSequence(vec![9, 4, 4, 3]).parse(),
+        Err(DecodeError::UnknownCommand(3))
+    );
 }
 
 #[test]
@@ -110,6 +152,35 @@ fn test_overflow() {
     assert_eq!(i32::MAX, 2147483647);
     assert_eq!(u32::MAX, 4294967295);
 
+    let multipoint = screen::MultiPoint {
+        points: vec![
+            screen::Point { x: 5, y: 7 },
+            screen::Point {
+                x: i32::MIN + 5,
+                y: i32::MIN + 7,
+            },
+        ],
+    };
+    assert_eq!(
+        multipoint.encode().unwrap().0,
+        &[17, 10, 14, u32::MAX, u32::MAX]
+    );
+
+    let multipoint = screen::MultiPoint {
+        points: vec![
+            screen::Point { x: -5, y: -10 },
+            screen::Point {
+                x: i32::MAX - 5,
+                y: i32::MAX - 10,
+            },
+        ],
+    };
+    assert_eq!(
+        multipoint.encode().unwrap().0,
+        &[17, 9, 19, u32::MAX - 1, u32::MAX - 1]
+    );
+
+    // Deltas exceeding the i32 range
     let multipoint = screen::MultiPoint {
         points: vec![
             screen::Point { x: 5, y: 7 },
@@ -119,19 +190,1152 @@ fn test_overflow() {
             },
         ],
     };
-    assert_eq!(multipoint.encode().0, &[17, 10, 14, u32::MAX, u32::MAX]);
+    assert_eq!(
+        multipoint.encode(),
+        Err(EncodeError::DeltaOverflow {
+            pos: 5,
+            value: i32::MIN
+        })
+    );
+}
+
+#[test]
+fn test_winding_order() {
+    // Counter-clockwise exterior ring with clockwise hole
+    let polygon = screen::Polygon {
+        rings: vec![
+            screen::LineString {
+                points: vec![
+                    screen::Point { x: 0, y: 0 },
+                    screen::Point { x: 0, y: 10 },
+                    screen::Point { x: 10, y: 10 },
+                    screen::Point { x: 10, y: 0 },
+                    screen::Point { x: 0, y: 0 },
+                ],
+            },
+            screen::LineString {
+                points: vec![
+                    screen::Point { x: 2, y: 2 },
+                    screen::Point { x: 8, y: 2 },
+                    screen::Point { x: 8, y: 8 },
+                    screen::Point { x: 2, y: 8 },
+                    screen::Point { x: 2, y: 2 },
+                ],
+            },
+        ],
+    };
+    assert!(polygon.rings[0].double_signed_area() < 0);
+    assert!(polygon.rings[1].double_signed_area() > 0);
+    // Exterior: (0,0) (10,0) (10,10) (0,10), hole: (2,2) (2,8) (8,8) (8,2)
+    assert_eq!(
+        polygon.encode().unwrap().0,
+        &[9, 0, 0, 26, 20, 0, 0, 20, 19, 0, 15, 9, 4, 15, 26, 0, 12, 12, 0, 0, 11, 15]
+    );
+}
+
+#[test]
+fn test_degenerate_rings() {
+    let points = vec![
+        screen::Point { x: 3, y: 6 },
+        screen::Point { x: 8, y: 12 },
+        screen::Point { x: 20, y: 34 },
+    ];
+    // Open rings need at least 3 points
+    for len in 0..3 {
+        let polygon = screen::Polygon {
+            rings: vec![screen::LineString {
+                points: points
+                    .iter()
+                    .take(len)
+                    .map(|p| screen::Point { x: p.x, y: p.y })
+                    .collect(),
+            }],
+        };
+        assert_eq!(
+            polygon.encode().unwrap(),
+            CommandSequence::new(),
+            "ring with {} points",
+            len
+        );
+    }
+    // Closed rings need at least 4 points
+    let polygon = screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 3, y: 6 },
+                screen::Point { x: 8, y: 12 },
+                screen::Point { x: 3, y: 6 },
+            ],
+        }],
+    };
+    assert!(polygon.encode().unwrap().0.is_empty());
 
+    // Degenerate hole is skipped without moving the cursor
+    let polygon = screen::Polygon {
+        rings: vec![
+            screen::LineString {
+                points: vec![
+                    screen::Point { x: 3, y: 6 },
+                    screen::Point { x: 8, y: 12 },
+                    screen::Point { x: 20, y: 34 },
+                    screen::Point { x: 3, y: 6 },
+                ],
+            },
+            screen::LineString {
+                points: vec![screen::Point { x: 1, y: 1 }, screen::Point { x: 2, y: 2 }],
+            },
+        ],
+    };
+    assert_eq!(
+        polygon.encode().unwrap().0,
+        &[9, 6, 12, 18, 10, 12, 24, 44, 15]
+    );
+}
+
+#[test]
+fn test_multipolygon_cursor() {
+    let first = screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 0, y: 0 },
+                screen::Point { x: 10, y: 0 },
+                screen::Point { x: 10, y: 10 },
+                screen::Point { x: 0, y: 10 },
+                screen::Point { x: 0, y: 0 },
+            ],
+        }],
+    };
+    let second = screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 20, y: 20 },
+                screen::Point { x: 30, y: 20 },
+                screen::Point { x: 30, y: 30 },
+                screen::Point { x: 20, y: 20 },
+            ],
+        }],
+    };
+    // Second part starts relative to the last vertex of the first part (0,10)
+    let mut expected = first.encode().unwrap();
+    second
+        .encode_from(&screen::Point { x: 0, y: 10 }, &mut expected)
+        .unwrap();
+    assert_eq!(&expected.0[11..14], &[9, 40, 20]);
+
+    let multipolygon = screen::MultiPolygon {
+        polygons: vec![first, second],
+    };
+    assert_eq!(multipolygon.encode().unwrap().0, expected.0);
+}
+
+#[test]
+fn test_geometry_encoding() {
+    use crate::mvt::geom_encoder::encode_geometry;
+    use crate::mvt::vector_tile::Tile_GeomType;
+
+    let geoms = vec![
+        screen::Geometry::Point(screen::Point { x: 25, y: 17 }),
+        screen::Geometry::LineString(screen::LineString {
+            points: vec![screen::Point { x: 1, y: 1 }, screen::Point { x: 3, y: 5 }],
+        }),
+        screen::Geometry::Polygon(screen::Polygon {
+            rings: vec![screen::LineString {
+                points: vec![
+                    screen::Point { x: 3, y: 6 },
+                    screen::Point { x: 8, y: 12 },
+                    screen::Point { x: 20, y: 34 },
+                    screen::Point { x: 3, y: 6 },
+                ],
+            }],
+        }),
+    ];
+    let encoded: Vec<_> = geoms
+        .iter()
+        .map(|geom| encode_geometry(geom).unwrap())
+        .collect();
+    assert_eq!(encoded[0].0, Tile_GeomType::POINT);
+    assert_eq!(encoded[0].1 .0, &[9, 50, 34]);
+    assert_eq!(encoded[1].0, Tile_GeomType::LINESTRING);
+    assert_eq!(encoded[1].1 .0, &[9, 2, 2, 10, 4, 8]);
+    assert_eq!(encoded[2].0, Tile_GeomType::POLYGON);
+    assert_eq!(encoded[2].1 .0, &[9, 6, 12, 18, 10, 12, 24, 44, 15]);
+}
+
+#[test]
+fn test_duplicate_points() {
+    let linestring = screen::LineString {
+        points: vec![
+            screen::Point { x: 2, y: 2 },
+            screen::Point { x: 2, y: 2 },
+            screen::Point { x: 2, y: 10 },
+            screen::Point { x: 2, y: 10 },
+            screen::Point { x: 2, y: 10 },
+            screen::Point { x: 10, y: 10 },
+        ],
+    };
+    // LineTo count 2 instead of 5
+    let seq = linestring.encode().unwrap();
+    assert_eq!(seq.0, &[9, 4, 4, 18, 0, 16, 16, 0]);
+    assert!(seq.0[4..].chunks(2).all(|delta| delta != [0, 0]));
+
+    // Line collapsing to a single point is skipped without moving the cursor
+    let multilinestring = screen::MultiLineString {
+        lines: vec![
+            screen::LineString {
+                points: vec![screen::Point { x: 5, y: 5 }, screen::Point { x: 5, y: 5 }],
+            },
+            screen::LineString {
+                points: vec![screen::Point { x: 1, y: 1 }, screen::Point { x: 3, y: 5 }],
+            },
+        ],
+    };
+    assert_eq!(multilinestring.encode().unwrap().0, &[9, 2, 2, 10, 4, 8]);
+
+    // Ring closure is kept
+    let polygon = screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 3, y: 6 },
+                screen::Point { x: 8, y: 12 },
+                screen::Point { x: 8, y: 12 },
+                screen::Point { x: 20, y: 34 },
+                screen::Point { x: 3, y: 6 },
+                screen::Point { x: 3, y: 6 },
+            ],
+        }],
+    };
+    assert_eq!(
+        polygon.encode().unwrap().0,
+        &[9, 6, 12, 18, 10, 12, 24, 44, 15]
+    );
+
+    // Ring collapsing below 4 points is skipped
+    let polygon = screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 3, y: 6 },
+                screen::Point { x: 8, y: 12 },
+                screen::Point { x: 8, y: 12 },
+                screen::Point { x: 3, y: 6 },
+            ],
+        }],
+    };
+    assert!(polygon.encode().unwrap().0.is_empty());
+}
+
+#[test]
+fn test_empty_geometries() {
+    let empty_line = || screen::LineString { points: vec![] };
+    let empty_polygon = || screen::Polygon {
+        rings: vec![empty_line()],
+    };
+    let geometries = vec![
+        screen::Geometry::MultiPoint(screen::MultiPoint { points: vec![] }),
+        screen::Geometry::LineString(empty_line()),
+        screen::Geometry::MultiLineString(screen::MultiLineString { lines: vec![] }),
+        screen::Geometry::MultiLineString(screen::MultiLineString {
+            lines: vec![empty_line(), empty_line()],
+        }),
+        screen::Geometry::Polygon(screen::Polygon { rings: vec![] }),
+        screen::Geometry::Polygon(empty_polygon()),
+        screen::Geometry::MultiPolygon(screen::MultiPolygon { polygons: vec![] }),
+        screen::Geometry::MultiPolygon(screen::MultiPolygon {
+            polygons: vec![empty_polygon(), screen::Polygon { rings: vec![] }],
+        }),
+    ];
+    for geom in &geometries {
+        assert!(geom.encode().unwrap().0.is_empty(), "{:?}", geom);
+    }
+}
+
+#[test]
+fn test_empty_sub_geometries() {
+    use crate::mvt::geom_encoder::encode_geometry;
+
+    let empty_line = || screen::LineString { points: vec![] };
+    let line = screen::LineString {
+        points: vec![screen::Point::new(1, 1), screen::Point::new(3, 1)],
+    };
+    let ring = screen::LineString {
+        points: vec![
+            screen::Point::new(0, 0),
+            screen::Point::new(4, 0),
+            screen::Point::new(4, 4),
+            screen::Point::new(0, 0),
+        ],
+    };
+    let multilinestring = screen::MultiLineString {
+        lines: vec![empty_line(), line.clone(), empty_line()],
+    };
+    let polygon = screen::Polygon {
+        rings: vec![empty_line(), ring.clone(), empty_line()],
+    };
+    let multipolygon = screen::MultiPolygon {
+        polygons: vec![
+            screen::Polygon { rings: vec![] },
+            polygon.clone(),
+            screen::Polygon {
+                rings: vec![empty_line()],
+            },
+        ],
+    };
+
+    // Empty parts are skipped
+    assert_eq!(
+        multilinestring.encode().unwrap(),
+        screen::MultiLineString { lines: vec![line] }
+            .encode()
+            .unwrap()
+    );
+    let single = screen::Polygon { rings: vec![ring] };
+    assert_eq!(polygon.encode().unwrap(), single.encode().unwrap());
+    assert_eq!(multipolygon.encode().unwrap(), single.encode().unwrap());
+    for closing in &[RingClosing::ClosePath, RingClosing::LineTo] {
+        assert_eq!(
+            multipolygon.encode_with_closing(*closing).unwrap(),
+            single.encode_with_closing(*closing).unwrap()
+        );
+    }
+
+    // Alternative encoders of empty geometries
+    let empty_multipoint = screen::MultiPoint { points: vec![] };
+    for coincident in &[CoincidentPoints::Keep, CoincidentPoints::Dedup] {
+        assert!(empty_multipoint
+            .encode_with_coincident(*coincident)
+            .unwrap()
+            .is_empty());
+    }
+    let empty_lines = screen::MultiLineString {
+        lines: vec![empty_line(), empty_line()],
+    };
+    for degenerate in &[DegenerateLines::Skip, DegenerateLines::Point] {
+        let (_, seq) = empty_line().encode_with_degenerate(*degenerate).unwrap();
+        assert!(seq.is_empty());
+        let (_, seq) = empty_lines.encode_with_degenerate(*degenerate).unwrap();
+        assert!(seq.is_empty());
+    }
+    let empty_polygon = screen::Polygon {
+        rings: vec![empty_line()],
+    };
+    assert!(empty_polygon
+        .encode_with_closing(RingClosing::LineTo)
+        .unwrap()
+        .is_empty());
+    let geom = screen::Geometry::MultiLineString(empty_lines);
+    let (_, seq) = encode_geometry(&geom).unwrap();
+    assert!(seq.is_empty());
+    assert_eq!(simplify_report(&geom, 2).unwrap().vertices, 0);
+}
+
+#[test]
+fn test_encoded_size_hint() {
+    let seq = CommandSequence(vec![0, 9, 127, 128, 16383, 16384, 1 << 28, u32::MAX]);
+    assert_eq!(seq.len(), 8);
+    assert!(!seq.is_empty());
+
+    let mut buf = Vec::new();
+    {
+        let mut os = CodedOutputStream::vec(&mut buf);
+        for v in seq.as_slice() {
+            os.write_raw_varint32(*v).unwrap();
+        }
+        os.flush().unwrap();
+    }
+    assert_eq!(seq.encoded_size_hint(), buf.len());
+    assert_eq!(seq.encoded_size_hint(), 1 + 1 + 1 + 2 + 2 + 3 + 5 + 5);
+
+    let empty = CommandSequence::new();
+    assert!(empty.is_empty());
+    assert_eq!(empty.encoded_size_hint(), 0);
+}
+
+#[test]
+fn test_open_ring() {
+    let closed = screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 3, y: 6 },
+                screen::Point { x: 8, y: 12 },
+                screen::Point { x: 20, y: 34 },
+                screen::Point { x: 3, y: 6 },
+            ],
+        }],
+    };
+    assert_eq!(
+        closed.encode().unwrap().0,
+        &[9, 6, 12, 18, 10, 12, 24, 44, 15]
+    );
+
+    // Last vertex is not dropped
+    let open = screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 3, y: 6 },
+                screen::Point { x: 8, y: 12 },
+                screen::Point { x: 20, y: 34 },
+            ],
+        }],
+    };
+    assert_eq!(
+        open.encode().unwrap().0,
+        &[9, 6, 12, 18, 10, 12, 24, 44, 15]
+    );
+
+    // Winding order of open rings is corrected
+    let square = |points: &[(i32, i32)]| screen::LineString {
+        points: points
+            .iter()
+            .map(|&(x, y)| screen::Point { x, y })
+            .collect(),
+    };
+    let open_ccw = square(&[(0, 0), (0, 10), (10, 10), (10, 0)]);
+    let closed_ccw = square(&[(0, 0), (0, 10), (10, 10), (10, 0), (0, 0)]);
+    assert_eq!(
+        open_ccw.double_signed_area(),
+        closed_ccw.double_signed_area()
+    );
+    let open = screen::Polygon {
+        rings: vec![open_ccw],
+    };
+    let closed = screen::Polygon {
+        rings: vec![closed_ccw],
+    };
+    assert_eq!(open.encode().unwrap().0, closed.encode().unwrap().0);
+    assert_eq!(
+        open.encode().unwrap().0,
+        &[9, 0, 0, 26, 20, 0, 0, 20, 19, 0, 15]
+    );
+}
+
+#[test]
+fn test_write_varints() {
+    let linestring = screen::LineString {
+        points: vec![
+            screen::Point { x: 2, y: 2 },
+            screen::Point { x: 2, y: 10 },
+            screen::Point { x: 1000, y: -70000 },
+        ],
+    };
+    let mut seq = linestring.encode().unwrap();
+    seq.0.push(u32::MAX);
+
+    let mut reference = Vec::new();
+    {
+        let mut os = CodedOutputStream::vec(&mut reference);
+        for v in seq.as_slice() {
+            os.write_raw_varint32(*v).unwrap();
+        }
+        os.flush().unwrap();
+    }
+    let mut streamed = Vec::new();
+    seq.write_varints(&mut streamed).unwrap();
+    assert_eq!(streamed, reference);
+    assert_eq!(streamed.len(), seq.encoded_size_hint());
+    assert_eq!(&streamed[..8], &[9, 4, 4, 18, 0, 16, 204, 15]);
+}
+
+#[test]
+fn test_cursor_empty_parts() {
+    let line = |points: &[(i32, i32)]| screen::LineString {
+        points: points
+            .iter()
+            .map(|&(x, y)| screen::Point { x, y })
+            .collect(),
+    };
+    let multilinestring = screen::MultiLineString {
+        lines: vec![
+            line(&[(2, 2), (2, 10)]),
+            line(&[]),
+            line(&[(7, 7)]),
+            line(&[(3, 3), (3, 3)]),
+            line(&[(4, 10), (6, 10)]),
+        ],
+    };
+    // Second MoveTo relative to (2, 10)
+    assert_eq!(
+        multilinestring.encode().unwrap().0,
+        &[9, 4, 4, 10, 0, 16, 9, 4, 0, 10, 4, 0]
+    );
+
+    let multipolygon = screen::MultiPolygon {
+        polygons: vec![
+            screen::Polygon {
+                rings: vec![line(&[(0, 0), (10, 0), (10, 10), (0, 0)])],
+            },
+            screen::Polygon { rings: vec![] },
+            screen::Polygon {
+                rings: vec![line(&[]), line(&[(5, 5), (6, 6)])],
+            },
+            screen::Polygon {
+                rings: vec![line(&[(20, 20), (30, 20), (30, 30), (20, 20)])],
+            },
+        ],
+    };
+    // Second MoveTo relative to (10, 10)
+    assert_eq!(
+        multipolygon.encode().unwrap().0,
+        &[9, 0, 0, 18, 20, 0, 0, 20, 15, 9, 20, 20, 18, 20, 0, 0, 20, 15]
+    );
+}
+
+#[test]
+fn test_ring_closing() {
+    let polygon = screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                screen::Point { x: 3, y: 6 },
+                screen::Point { x: 8, y: 12 },
+                screen::Point { x: 20, y: 34 },
+                screen::Point { x: 3, y: 6 },
+            ],
+        }],
+    };
+    assert_eq!(
+        polygon
+            .encode_with_closing(RingClosing::ClosePath)
+            .unwrap()
+            .0,
+        &[9, 6, 12, 18, 10, 12, 24, 44, 15]
+    );
+    assert_eq!(
+        polygon.encode().unwrap().0,
+        &[9, 6, 12, 18, 10, 12, 24, 44, 15]
+    );
+    // LineTo back to (3, 6) instead of ClosePath
+    assert_eq!(
+        polygon.encode_with_closing(RingClosing::LineTo).unwrap().0,
+        &[9, 6, 12, 26, 10, 12, 24, 44, 33, 55]
+    );
+
+    // Cursor of next ring is at the start of the previous ring
+    let multipolygon = screen::MultiPolygon {
+        polygons: vec![
+            polygon,
+            screen::Polygon {
+                rings: vec![screen::LineString {
+                    points: vec![
+                        screen::Point { x: 13, y: 16 },
+                        screen::Point { x: 18, y: 22 },
+                        screen::Point { x: 30, y: 44 },
+                    ],
+                }],
+            },
+        ],
+    };
+    assert_eq!(
+        multipolygon
+            .encode_with_closing(RingClosing::LineTo)
+            .unwrap()
+            .0,
+        &[9, 6, 12, 26, 10, 12, 24, 44, 33, 55, 9, 20, 20, 26, 10, 12, 24, 44, 33, 55]
+    );
+}
+
+#[test]
+fn test_degenerate_lines() {
+    use crate::mvt::vector_tile::Tile_GeomType;
+
+    let one_point = screen::LineString {
+        points: vec![screen::Point { x: 3, y: 6 }],
+    };
+    // Lines need at least 2 distinct points
+    for line in &[
+        one_point.clone(),
+        screen::LineString {
+            points: vec![screen::Point { x: 3, y: 6 }, screen::Point { x: 3, y: 6 }],
+        },
+    ] {
+        assert!(line.encode().unwrap().is_empty());
+        let (geom_type, seq) = line.encode_with_degenerate(DegenerateLines::Skip).unwrap();
+        assert_eq!(geom_type, Tile_GeomType::LINESTRING);
+        assert!(seq.is_empty());
+        let (geom_type, seq) = line.encode_with_degenerate(DegenerateLines::Point).unwrap();
+        assert_eq!(geom_type, Tile_GeomType::POINT);
+        assert_eq!(format!("{:?}", seq), "MoveTo(1)[3,6]");
+    }
+
+    let two_points = screen::LineString {
+        points: vec![screen::Point { x: 3, y: 6 }, screen::Point { x: 8, y: 12 }],
+    };
+    for degenerate in &[DegenerateLines::Skip, DegenerateLines::Point] {
+        let (geom_type, seq) = two_points.encode_with_degenerate(*degenerate).unwrap();
+        assert_eq!(geom_type, Tile_GeomType::LINESTRING);
+        assert_eq!(format!("{:?}", seq), "MoveTo(1)[3,6] LineTo(1)[5,6]");
+    }
+
+    // Degenerate lines are skipped in geometries with valid lines
+    let multiline = screen::MultiLineString {
+        lines: vec![one_point.clone(), two_points],
+    };
+    let (geom_type, seq) = multiline
+        .encode_with_degenerate(DegenerateLines::Point)
+        .unwrap();
+    assert_eq!(geom_type, Tile_GeomType::LINESTRING);
+    assert_eq!(format!("{:?}", seq), "MoveTo(1)[3,6] LineTo(1)[5,6]");
+    // LineTo count is never zero
+    assert!(seq
+        .parse()
+        .unwrap()
+        .iter()
+        .all(|(command, params)| *command != Command::LineTo || !params.is_empty()));
+
+    let multiline = screen::MultiLineString {
+        lines: vec![
+            one_point,
+            screen::LineString {
+                points: vec![screen::Point { x: 5, y: 5 }],
+            },
+        ],
+    };
+    let (geom_type, seq) = multiline
+        .encode_with_degenerate(DegenerateLines::Point)
+        .unwrap();
+    assert_eq!(geom_type, Tile_GeomType::POINT);
+    assert_eq!(format!("{:?}", seq), "MoveTo(2)[3,6,2,-1]");
+}
+
+#[test]
+fn test_geom_type() {
+    use crate::mvt::vector_tile::Tile_GeomType;
+
+    let point = || screen::Point { x: 1, y: 2 };
+    let line = || screen::LineString {
+        points: vec![point(), screen::Point { x: 3, y: 4 }],
+    };
+    let polygon = || screen::Polygon {
+        rings: vec![screen::LineString {
+            points: vec![
+                point(),
+                screen::Point { x: 3, y: 4 },
+                screen::Point { x: 1, y: 6 },
+            ],
+        }],
+    };
+
+    assert_eq!(point().geom_type(), Tile_GeomType::POINT);
+    assert_eq!(
+        screen::MultiPoint {
+            points: vec![point()]
+        }
+        .geom_type(),
+        Tile_GeomType::POINT
+    );
+    assert_eq!(line().geom_type(), Tile_GeomType::LINESTRING);
+    assert_eq!(
+        screen::MultiLineString {
+            lines: vec![line()]
+        }
+        .geom_type(),
+        Tile_GeomType::LINESTRING
+    );
+    assert_eq!(polygon().geom_type(), Tile_GeomType::POLYGON);
+    assert_eq!(
+        screen::MultiPolygon {
+            polygons: vec![polygon()]
+        }
+        .geom_type(),
+        Tile_GeomType::POLYGON
+    );
+
+    // Enum dispatches to the wrapped geometry
+    let geometries = vec![
+        (screen::Geometry::Point(point()), Tile_GeomType::POINT),
+        (
+            screen::Geometry::LineString(line()),
+            Tile_GeomType::LINESTRING,
+        ),
+        (screen::Geometry::Polygon(polygon()), Tile_GeomType::POLYGON),
+    ];
+    for (geom, geom_type) in geometries {
+        assert_eq!(geom.geom_type(), geom_type);
+    }
+}
+
+/// Encode geometries into a tile and decode it with the geozero MVT reader
+fn roundtrip(geoms: &[screen::Geometry]) -> Vec<geo_types::Geometry<f64>> {
+    use crate::mvt::vector_tile;
+    use geozero::mvt::Message;
+    use geozero::ToGeo;
+
+    let mut mvt_layer = vector_tile::Tile_Layer::new();
+    mvt_layer.set_version(2);
+    mvt_layer.set_name(String::from("roundtrip"));
+    mvt_layer.set_extent(4096);
+    for geom in geoms {
+        let mut mvt_feature = vector_tile::Tile_Feature::new();
+        mvt_feature.set_field_type(geom.geom_type());
+        mvt_feature.set_geometry(geom.encode().unwrap().into_vec());
+        mvt_layer.mut_features().push(mvt_feature);
+    }
+    let mut mvt_tile = vector_tile::Tile::new();
+    mvt_tile.mut_layers().push(mvt_layer);
+    let bytes = protobuf::Message::write_to_bytes(&mvt_tile).unwrap();
+
+    let decoded = geozero::mvt::Tile::decode(bytes.as_slice()).unwrap();
+    decoded.layers[0]
+        .features
+        .iter()
+        .map(|feature| feature.to_geo().unwrap())
+        .collect()
+}
+
+fn geo_line(line: &screen::LineString) -> geo_types::LineString<f64> {
+    line.points
+        .iter()
+        .map(|p| geo_types::Coord {
+            x: p.x as f64,
+            y: p.y as f64,
+        })
+        .collect()
+}
+
+fn geo_polygon(polygon: &screen::Polygon) -> geo_types::Polygon<f64> {
+    geo_types::Polygon::new(
+        geo_line(&polygon.rings[0]),
+        polygon.rings[1..].iter().map(geo_line).collect(),
+    )
+}
+
+/// Closed rings are equal, independent of start point and orientation
+fn same_ring(a: &geo_types::LineString<f64>, b: &geo_types::LineString<f64>) -> bool {
+    let a = &a.0[..a.0.len() - 1];
+    let b = &b.0[..b.0.len() - 1];
+    let rotations = |ring: &[geo_types::Coord<f64>]| {
+        (0..ring.len())
+            .map(|i| {
+                ring[i..]
+                    .iter()
+                    .chain(&ring[..i])
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+    let reversed = b.iter().rev().cloned().collect::<Vec<_>>();
+    a.len() == b.len()
+        && (rotations(b).iter().any(|r| r == a) || rotations(&reversed).iter().any(|r| r == a))
+}
+
+fn assert_same_polygon(decoded: &geo_types::Polygon<f64>, expected: &geo_types::Polygon<f64>) {
+    assert!(
+        same_ring(decoded.exterior(), expected.exterior()),
+        "{:?} != {:?}",
+        decoded,
+        expected
+    );
+    assert_eq!(decoded.interiors().len(), expected.interiors().len());
+    for (decoded_ring, expected_ring) in decoded.interiors().iter().zip(expected.interiors()) {
+        assert!(
+            same_ring(decoded_ring, expected_ring),
+            "{:?} != {:?}",
+            decoded,
+            expected
+        );
+    }
+}
+
+fn line(coords: &[(i32, i32)]) -> screen::LineString {
+    screen::LineString {
+        points: coords
+            .iter()
+            .map(|&(x, y)| screen::Point { x, y })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_roundtrip_point() {
+    let geoms = vec![
+        screen::Geometry::Point(screen::Point { x: 25, y: 17 }),
+        screen::Geometry::MultiPoint(screen::MultiPoint {
+            points: vec![screen::Point { x: 5, y: 7 }, screen::Point { x: 3, y: 2 }],
+        }),
+    ];
+    let decoded = roundtrip(&geoms);
+    assert_eq!(
+        decoded,
+        vec![
+            geo_types::Point::new(25., 17.).into(),
+            geo_types::MultiPoint::from(vec![(5., 7.), (3., 2.)]).into(),
+        ]
+    );
+}
+
+#[test]
+fn test_roundtrip_line() {
+    let coords = [(2, 2), (2, 10), (10, 10)];
+    let geoms = vec![
+        screen::Geometry::LineString(line(&coords)),
+        screen::Geometry::MultiLineString(screen::MultiLineString {
+            lines: vec![line(&coords), line(&[(1, 1), (3, 5)])],
+        }),
+    ];
+    let decoded = roundtrip(&geoms);
+    assert_eq!(
+        decoded,
+        vec![
+            geo_line(&line(&coords)).into(),
+            geo_types::MultiLineString(vec![
+                geo_line(&line(&coords)),
+                geo_line(&line(&[(1, 1), (3, 5)])),
+            ])
+            .into(),
+        ]
+    );
+}
+
+#[test]
+fn test_roundtrip_polygon_with_hole() {
+    let exterior = [(0, 0), (10, 0), (10, 10), (0, 10), (0, 0)];
+    let hole = [(2, 2), (8, 2), (8, 8), (2, 8), (2, 2)];
+    let reversed = |coords: &[(i32, i32)]| coords.iter().rev().cloned().collect::<Vec<_>>();
+    // Both ring orientations of the input have to result in
+    // an exterior and interior ring for the decoder
+    for (exterior, hole) in [
+        (exterior.to_vec(), hole.to_vec()),
+        (reversed(&exterior), reversed(&hole)),
+    ] {
+        let polygon = screen::Polygon {
+            rings: vec![line(&exterior), line(&hole)],
+        };
+        let decoded = roundtrip(&[screen::Geometry::Polygon(polygon)]);
+        let expected = geo_polygon(&screen::Polygon {
+            rings: vec![line(&exterior), line(&hole)],
+        });
+        match &decoded[..] {
+            [geo_types::Geometry::Polygon(decoded)] => assert_same_polygon(decoded, &expected),
+            _ => panic!("unexpected geometry {:?}", decoded),
+        }
+    }
+}
+
+#[test]
+fn test_roundtrip_multipolygon() {
+    let polygons = || {
+        vec![
+            screen::Polygon {
+                rings: vec![
+                    line(&[(0, 0), (10, 0), (10, 10), (0, 10), (0, 0)]),
+                    line(&[(2, 2), (2, 8), (8, 8), (8, 2), (2, 2)]),
+                ],
+            },
+            screen::Polygon {
+                rings: vec![line(&[(20, 20), (20, 30), (30, 30), (20, 20)])],
+            },
+        ]
+    };
+    let decoded = roundtrip(&[screen::Geometry::MultiPolygon(screen::MultiPolygon {
+        polygons: polygons(),
+    })]);
+    match &decoded[..] {
+        [geo_types::Geometry::MultiPolygon(decoded)] => {
+            assert_eq!(decoded.0.len(), 2);
+            for (decoded, polygon) in decoded.0.iter().zip(&polygons()) {
+                assert_same_polygon(decoded, &geo_polygon(polygon));
+            }
+        }
+        _ => panic!("unexpected geometry {:?}", decoded),
+    }
+}
+
+#[test]
+fn test_negative_coordinates() {
+    use crate::mvt::geom_decoder::decode_lines;
+
+    // Line starting outside of the tile
+    let line = screen::LineString {
+        points: vec![
+            screen::Point { x: -100, y: -50 },
+            screen::Point { x: 4200, y: 4200 },
+        ],
+    };
+    // Zigzag: -100 => 199, -50 => 99, 4300 => 8600, 4250 => 8500
+    assert_eq!(line.encode().unwrap().0, &[9, 199, 99, 10, 8600, 8500]);
+    assert_eq!(
+        decode_lines(&line.encode().unwrap().0).unwrap().lines,
+        vec![line]
+    );
+
+    // Deltas of large magnitude and sign changes
+    let line = screen::LineString {
+        points: vec![
+            screen::Point {
+                x: -1_000_000_000,
+                y: 1_000_000_000,
+            },
+            screen::Point {
+                x: 1_000_000_000,
+                y: -1_000_000_000,
+            },
+            screen::Point { x: -1, y: 0 },
+        ],
+    };
+    let encoded = line.encode().unwrap();
+    assert_eq!(
+        encoded.0,
+        &[
+            9,
+            1_999_999_999,
+            2_000_000_000,
+            18,
+            4_000_000_000,
+            3_999_999_999,
+            2_000_000_001,
+            2_000_000_000
+        ]
+    );
+    assert_eq!(decode_lines(&encoded.0).unwrap().lines, vec![line]);
+
+    // Delta -3_147_483_647 exceeds the i32 range
+    let line = screen::LineString {
+        points: vec![
+            screen::Point {
+                x: 1_000_000_000,
+                y: 0,
+            },
+            screen::Point {
+                x: i32::MIN + 1,
+                y: 0,
+            },
+        ],
+    };
+    assert_eq!(
+        line.encode(),
+        Err(EncodeError::DeltaOverflow {
+            pos: 1_000_000_000,
+            value: i32::MIN + 1
+        })
+    );
     let multipoint = screen::MultiPoint {
         points: vec![
-            screen::Point { x: -5, y: -10 },
             screen::Point {
-                x: i32::MAX,
+                x: i32::MIN,
                 y: i32::MAX,
             },
+            screen::Point {
+                x: i32::MAX,
+                y: i32::MIN,
+            },
         ],
     };
+    assert!(multipoint.encode().is_err());
+}
+
+#[test]
+fn test_hole_first() {
+    let exterior = || line(&[(0, 0), (10, 0), (10, 10), (0, 10), (0, 0)]);
+    let hole = || line(&[(2, 2), (2, 8), (8, 8), (8, 2), (2, 2)]);
+    let expected = screen::Polygon {
+        rings: vec![exterior(), hole()],
+    }
+    .encode()
+    .unwrap()
+    .0;
+
+    let hole_first = screen::Polygon {
+        rings: vec![hole(), exterior()],
+    };
+    assert_eq!(hole_first.encode().unwrap().0, expected);
+
+    // Independent of input winding order
+    let reversed = |ring: screen::LineString| screen::LineString {
+        points: ring.points.into_iter().rev().collect(),
+    };
+    let hole_first = screen::Polygon {
+        rings: vec![reversed(hole()), reversed(exterior())],
+    };
+    let decoded = roundtrip(&[screen::Geometry::Polygon(hole_first)]);
+    match &decoded[..] {
+        [geo_types::Geometry::Polygon(decoded)] => assert_same_polygon(
+            decoded,
+            &geo_polygon(&screen::Polygon {
+                rings: vec![exterior(), hole()],
+            }),
+        ),
+        _ => panic!("unexpected geometry {:?}", decoded),
+    }
+
+    // Degenerate first ring
+    let polygon = screen::Polygon {
+        rings: vec![line(&[(0, 0), (5, 5)]), exterior()],
+    };
     assert_eq!(
-        multipoint.encode().0,
-        &[17, 9, 19, u32::MAX - 1, u32::MAX - 1]
+        polygon.encode().unwrap().0,
+        screen::Polygon {
+            rings: vec![exterior()]
+        }
+        .encode()
+        .unwrap()
+        .0
+    );
+}
+
+#[test]
+fn test_encode_into() {
+    let geoms = vec![
+        screen::Geometry::Point(screen::Point { x: 25, y: 17 }),
+        screen::Geometry::LineString(line(&[(2, 2), (2, 10), (10, 10)])),
+        screen::Geometry::Polygon(screen::Polygon {
+            rings: vec![line(&[(3, 6), (8, 12), (20, 34), (3, 6)])],
+        }),
+    ];
+
+    // Buffer cleared between features
+    let mut buf = CommandSequence::new();
+    for geom in &geoms {
+        buf.clear();
+        geom.encode_into(&mut buf, &screen::Point::origin())
+            .unwrap();
+        assert_eq!(buf.as_slice(), geom.encode().unwrap().as_slice());
+    }
+
+    // Features appended into one buffer
+    buf.clear();
+    let mut segments = Vec::new();
+    for geom in &geoms {
+        let start = buf.len();
+        geom.encode_into(&mut buf, &screen::Point::origin())
+            .unwrap();
+        segments.push(start..buf.len());
+    }
+    for (geom, segment) in geoms.iter().zip(segments) {
+        assert_eq!(&buf.as_slice()[segment], geom.encode().unwrap().as_slice());
+    }
+}
+
+#[test]
+fn test_command_sequence_debug() {
+    let polygon = screen::Polygon {
+        rings: vec![line(&[(3, 6), (8, 12), (20, 34), (3, 6)])],
+    };
+    let encoded = polygon.encode().unwrap();
+    assert_eq!(
+        format!("{:?}", encoded),
+        "MoveTo(1)[3,6] LineTo(2)[5,6,12,22] ClosePath(1)"
+    );
+    assert_eq!(
+        format!("{:#?}", encoded),
+        "MoveTo(1)[3,6]\nLineTo(2)[5,6,12,22]\nClosePath(1)"
+    );
+    assert_eq!(format!("{:?}", CommandSequence::new()), "(empty)");
+    assert_eq!(
+        format!("{:?}", CommandSequence(vec![9, 50, 34, 18, 4, 12])),
+        "MoveTo(1)[25,17] LineTo(2)[2,6]<missing 2>"
+    );
+    assert_eq!(format!("{:?}", CommandSequence(vec![12])), "Unknown4(1)");
+
+    // Failing comparisons show the commands
+    assert_eq!(
+        encoded,
+        CommandSequence(vec![9, 6, 12, 18, 10, 12, 24, 44, 15])
+    );
+    let wrong = CommandSequence(vec![9, 6, 12, 18, 10, 12, 24, 42, 15]);
+    let msg = std::panic::catch_unwind(|| assert_eq!(polygon.encode().unwrap(), wrong))
+        .unwrap_err()
+        .downcast::<String>()
+        .unwrap();
+    assert!(
+        msg.contains("left: MoveTo(1)[3,6] LineTo(2)[5,6,12,22] ClosePath(1)"),
+        "{}",
+        msg
+    );
+    assert!(
+        msg.contains("right: MoveTo(1)[3,6] LineTo(2)[5,6,12,21] ClosePath(1)"),
+        "{}",
+        msg
+    );
+}
+
+#[test]
+fn test_simplify_report() {
+    // Dense line with small zigzag along the x axis
+    let dense = screen::Geometry::LineString(screen::LineString {
+        points: (0..=100)
+            .map(|i| screen::Point::new(i * 10, i % 2))
+            .collect(),
+    });
+    let report = simplify_report(&dense, 2).unwrap();
+    assert_eq!(report.vertices, 101);
+    assert_eq!(report.simplified_vertices, 2);
+    assert!(report.simplified_size < report.size);
+    assert_eq!(report.size, dense.encode().unwrap().encoded_size_hint());
+    assert!((report.vertex_reduction() - 99.0 / 101.0).abs() < 1e-9);
+
+    // No simplification
+    let report = simplify_report(&dense, 0).unwrap();
+    assert_eq!(report.simplified_size, report.size);
+    assert_eq!(report.vertex_reduction(), 0.0);
+
+    let point = screen::Geometry::Point(screen::Point::new(25, 17));
+    let report = simplify_report(&point, 2).unwrap();
+    assert_eq!((report.vertices, report.simplified_vertices), (1, 1));
+}
+
+#[test]
+fn test_encode_quantized() {
+    let line = screen::LineString {
+        points: vec![
+            screen::Point::new(2, 2),
+            screen::Point::new(3, 2),
+            screen::Point::new(9, 1),
+            screen::Point::new(10, 2),
+        ],
+    };
+    // Consecutive points in the same grid cell are removed
+    assert_eq!(
+        format!("{:?}", line.quantize(4).encode().unwrap()),
+        "MoveTo(1)[4,4] LineTo(2)[4,-4,4,4]"
+    );
+}
+
+#[test]
+fn test_coincident_points() {
+    let multipoint = screen::MultiPoint {
+        points: vec![
+            screen::Point { x: 5, y: 7 },
+            screen::Point { x: 5, y: 7 },
+            screen::Point { x: 3, y: 2 },
+            screen::Point { x: 3, y: 2 },
+            screen::Point { x: 3, y: 2 },
+            screen::Point { x: 5, y: 7 },
+        ],
+    };
+    // Default keeps zero deltas
+    assert_eq!(
+        multipoint
+            .encode_with_coincident(CoincidentPoints::default())
+            .unwrap(),
+        multipoint.encode().unwrap()
+    );
+    assert_eq!(
+        format!("{:?}", multipoint.encode().unwrap()),
+        "MoveTo(6)[5,7,0,0,-2,-5,0,0,0,0,2,5]"
+    );
+
+    let seq = multipoint
+        .encode_with_coincident(CoincidentPoints::Dedup)
+        .unwrap();
+    // Non-consecutive duplicates are kept
+    assert_eq!(format!("{:?}", seq), "MoveTo(3)[5,7,-2,-5,2,5]");
+    let parsed = seq.parse().unwrap();
+    assert_eq!(parsed.len(), 1);
+    assert_eq!(parsed[0].1.len(), 3);
+    assert!(parsed[0].1.iter().all(|&delta| delta != (0, 0)));
+
+    let single = screen::MultiPoint {
+        points: vec![screen::Point { x: 1, y: 1 }; 3],
+    };
+    assert_eq!(
+        format!(
+            "{:?}",
+            single
+                .encode_with_coincident(CoincidentPoints::Dedup)
+                .unwrap()
+        ),
+        "MoveTo(1)[1,1]"
     );
+    assert!(screen::MultiPoint::default()
+        .encode_with_coincident(CoincidentPoints::Dedup)
+        .unwrap()
+        .is_empty());
 }
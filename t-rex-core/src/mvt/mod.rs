@@ -3,10 +3,19 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+pub mod clip;
+#[cfg(test)]
+mod clip_test;
+pub mod geom_decoder;
+#[cfg(test)]
+mod geom_decoder_test;
 pub mod geom_encoder;
 #[cfg(test)]
 mod geom_encoder_test;
 pub mod tile;
 #[cfg(test)]
 mod tile_test;
+pub mod vector_layers;
+#[cfg(test)]
+mod vector_layers_test;
 pub mod vector_tile;
@@ -3,16 +3,23 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
-use crate::core::feature::{Feature, FeatureAttrValType};
+use crate::core::feature::{Feature, FeatureAttr, FeatureAttrValType};
 use crate::core::layer::Layer;
 use crate::core::screen;
 use crate::core::{geom, geom::GeometryType};
-use crate::mvt::geom_encoder::{CommandSequence, EncodableGeom};
+use crate::mvt::clip::ClipRect;
+use crate::mvt::geom_decoder::vertex_count;
+use crate::mvt::geom_encoder::{split_max_vertices, CommandSequence, EncodableGeom, EncodeError};
+use crate::mvt::vector_layers::VectorLayers;
 use crate::mvt::vector_tile;
 use flate2::{read::GzDecoder, write::GzEncoder, Compression};
 use protobuf::{error::ProtobufError, CodedOutputStream, Message};
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
 use std::fs::File;
 use std::io::{BufReader, Read, Write};
+use std::thread;
 use tile_grid::Extent;
 
 pub struct Tile<'a> {
@@ -22,8 +29,98 @@ pub struct Tile<'a> {
     // Values for current layer
     tile_size: i32,
     buffer_size: i32,
-    pixel_size_x: f64,
-    pixel_size_y: f64,
+    clip_rect: Option<ClipRect>,
+    no_clip: bool,
+    simplify_tolerance: i32,
+    quantize_grid: i32,
+    point_thinning: i32,
+    /// Grid cells of current layer occupied by a point feature
+    occupied_cells: RefCell<HashSet<(i32, i32)>>,
+    rounding: screen::Rounding,
+    transform: screen::PointTransform,
+    max_size: Option<u32>,
+    max_vertices: Option<usize>,
+    vector_layers: VectorLayers,
+}
+
+/// Encoded tile exceeding the maximal tile size
+#[derive(PartialEq, Debug)]
+pub struct TileSizeError {
+    /// Encoded size in bytes
+    pub size: u32,
+    pub max_size: u32,
+}
+
+impl fmt::Display for TileSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Tile size {} bytes exceeds maximum of {} bytes",
+            self.size, self.max_size
+        )
+    }
+}
+
+/// Simplification settings of a zoom level in screen units
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct ZoomSimplification {
+    /// Simplification tolerance (0: no simplification)
+    pub tolerance: i32,
+    /// Quantization grid (0: no snapping)
+    pub quantize_grid: i32,
+}
+
+/// Simplification settings by zoom level.
+/// Each entry applies from its zoom level up to the zoom level of the next entry.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ZoomSimplificationTable {
+    /// (minzoom, settings) sorted by minzoom
+    levels: Vec<(u8, ZoomSimplification)>,
+}
+
+impl ZoomSimplificationTable {
+    /// Table with settings from given zoom levels. Zoom levels below the
+    /// lowest zoom level of `levels` are not simplified.
+    pub fn new(mut levels: Vec<(u8, ZoomSimplification)>) -> ZoomSimplificationTable {
+        levels.sort_by_key(|&(zoom, _)| zoom);
+        ZoomSimplificationTable { levels }
+    }
+    /// Settings for zoom level `zoom`
+    pub fn get(&self, zoom: u8) -> ZoomSimplification {
+        self.levels
+            .iter()
+            .rev()
+            .find(|&&(minzoom, _)| minzoom <= zoom)
+            .map(|&(_, settings)| settings)
+            .unwrap_or_default()
+    }
+}
+
+impl Default for ZoomSimplificationTable {
+    /// Strong simplification at low zoom levels, decreasing to none from zoom level 14,
+    /// the usual maximal zoom level of vector tiles
+    fn default() -> Self {
+        let tolerance = |tolerance| ZoomSimplification {
+            tolerance,
+            quantize_grid: 0,
+        };
+        ZoomSimplificationTable::new(vec![
+            (0, tolerance(8)),
+            (6, tolerance(4)),
+            (9, tolerance(2)),
+            (12, tolerance(1)),
+            (14, tolerance(0)),
+        ])
+    }
+}
+
+/// Content statistics of an encoded tile
+#[derive(PartialEq, Debug)]
+pub struct TileStats {
+    pub features: usize,
+    pub vertices: usize,
+    /// Encoded size in bytes (uncompressed)
+    pub size: u32,
 }
 
 impl GeometryType {
@@ -54,7 +151,7 @@ impl ScreenGeom<geom::MultiPoint> for screen::MultiPoint {
         };
         for point in &multipoint.points {
             let pt = tile.point(point);
-            if tile.point_in_buffer(&pt) {
+            if tile.no_clip || tile.point_in_buffer(&pt) {
                 screen_geom.points.push(pt);
             }
         }
@@ -129,8 +226,17 @@ impl<'a> Tile<'a> {
             reverse_y,
             tile_size: 0,
             buffer_size: 0,
-            pixel_size_x: 0.0,
-            pixel_size_y: 0.0,
+            clip_rect: None,
+            no_clip: false,
+            simplify_tolerance: 0,
+            quantize_grid: 0,
+            point_thinning: 0,
+            occupied_cells: RefCell::new(HashSet::new()),
+            rounding: screen::Rounding::Truncate,
+            transform: screen::PointTransform::new(extent, 0, reverse_y),
+            max_size: None,
+            max_vertices: None,
+            vector_layers: VectorLayers::new(),
         };
         let default_layer = Layer::new("");
         tile.calc_layer_values(&default_layer);
@@ -148,21 +254,82 @@ impl<'a> Tile<'a> {
     }
 
     fn calc_layer_values(&mut self, layer: &Layer) {
+        self.occupied_cells.get_mut().clear();
         self.tile_size = layer.tile_size as i32;
         self.buffer_size = layer.buffer_size.unwrap_or(0) as i32;
-        self.pixel_size_x = (self.extent.maxx - self.extent.minx) / self.tile_size as f64;
-        self.pixel_size_y = (self.extent.maxy - self.extent.miny) / self.tile_size as f64;
+        self.no_clip = layer.no_clip;
+        self.clip_rect = layer
+            .buffer_size
+            .filter(|_| !self.no_clip)
+            .map(|_| ClipRect::tile(self.tile_size, self.buffer_size));
+        self.transform = screen::PointTransform::new(self.extent, layer.tile_size, self.reverse_y)
+            .with_rounding(self.rounding);
     }
 
-    pub fn point(&self, point: &geom::Point) -> screen::Point {
-        let mut screen_geom = screen::Point {
-            x: ((point.x - self.extent.minx) / self.pixel_size_x) as i32,
-            y: ((point.y - self.extent.miny) / self.pixel_size_y) as i32,
-        };
-        if self.reverse_y {
-            screen_geom.y = self.tile_size.saturating_sub(screen_geom.y)
+    /// Skip clipping of lines and polygons of the current layer, which are already
+    /// clipped by the datasource. Has to be called after `new_layer`.
+    pub fn set_preclipped(&mut self) {
+        self.clip_rect = None;
+    }
+
+    /// Simplify lines and polygons with given tolerance in screen units (0: no simplification)
+    pub fn set_simplify_tolerance(&mut self, tolerance: i32) {
+        self.simplify_tolerance = tolerance;
+    }
+
+    /// Simplify lines and polygons and snap coordinates according to the settings
+    /// of `table` for zoom level `zoom`
+    pub fn set_zoom_simplification(&mut self, table: &ZoomSimplificationTable, zoom: u8) {
+        let settings = table.get(zoom);
+        self.simplify_tolerance = settings.tolerance;
+        self.quantize_grid = settings.quantize_grid;
+    }
+
+    /// Rounding of world coordinates to screen units, applied to layers created afterwards.
+    /// Defaults to `Rounding::Truncate`, as used by earlier versions. Changing the mode
+    /// changes generated tiles, so cached tiles of the tileset should be regenerated
+    /// to avoid seams between old and new tiles.
+    pub fn set_rounding(&mut self, rounding: screen::Rounding) {
+        self.rounding = rounding;
+    }
+
+    /// Snap screen coordinates to a grid of `grid` units before encoding (0: no snapping)
+    pub fn set_quantize_grid(&mut self, grid: i32) {
+        self.quantize_grid = grid;
+    }
+
+    /// Drop point features falling into a cell of a grid with `grid` screen units
+    /// already occupied by a point feature of the same layer (0: keep all points).
+    /// Thins dense point layers at low zoom levels, keeping the first point per cell.
+    pub fn set_point_thinning(&mut self, grid: i32) {
+        self.point_thinning = grid;
+    }
+
+    /// Maximal number of vertices per feature. Lines with more vertices are split
+    /// into multiple features with the same id and attributes, polygons are skipped
+    /// with a warning, since splitting would change their topology.
+    pub fn set_max_vertices(&mut self, max_vertices: Option<usize>) {
+        self.max_vertices = max_vertices;
+    }
+
+    /// Maximal encoded size of the tile in bytes checked by `check_size`
+    pub fn set_max_size(&mut self, max_size: Option<u32>) {
+        self.max_size = max_size;
+    }
+
+    /// Encoded size of the tile with all added layers (uncompressed).
+    /// Returns an error if the maximal tile size is exceeded, so the caller can
+    /// e.g. simplify more or drop features.
+    pub fn check_size(&self) -> Result<u32, TileSizeError> {
+        let size = Self::size(&self.mvt_tile);
+        match self.max_size {
+            Some(max_size) if size > max_size => Err(TileSizeError { size, max_size }),
+            _ => Ok(size),
         }
-        screen_geom
+    }
+
+    pub fn point(&self, point: &geom::Point) -> screen::Point {
+        self.transform.point(point.x, point.y)
     }
 
     pub fn point_in_buffer(&self, point: &screen::Point) -> bool {
@@ -172,28 +339,92 @@ impl<'a> Tile<'a> {
             && point.y <= self.tile_size + self.buffer_size
     }
 
-    pub fn encode_geom(&self, geom: geom::GeometryType) -> CommandSequence {
-        match geom {
-            GeometryType::Point(ref g) => {
+    /// Convert geometry into (clipped and simplified) screen geometry.
+    /// Returns None for points outside of the tile buffer and drops those
+    /// of multipoints, unless clipping is disabled for the layer.
+    pub fn screen_geom(&self, geom: &geom::GeometryType) -> Option<screen::Geometry> {
+        let tolerance = self.simplify_tolerance;
+        let screen_geom = match geom {
+            &GeometryType::Point(ref g) => {
                 let pt = self.point(g);
-                if self.point_in_buffer(&pt) {
-                    pt.encode()
-                } else {
-                    CommandSequence::new() // empty
+                if !self.no_clip && !self.point_in_buffer(&pt) {
+                    return None;
+                }
+                screen::Geometry::Point(pt)
+            }
+            &GeometryType::MultiPoint(ref g) => {
+                screen::Geometry::MultiPoint(screen::MultiPoint::from_geom(&self, g))
+            }
+            &GeometryType::LineString(ref g) => {
+                let line = screen::LineString::from_geom(&self, g);
+                match self.clip_rect {
+                    Some(ref rect) => {
+                        screen::Geometry::MultiLineString(line.clip(rect).simplify(tolerance))
+                    }
+                    None => screen::Geometry::LineString(line.simplify(tolerance)),
+                }
+            }
+            &GeometryType::MultiLineString(ref g) => {
+                let mut lines = screen::MultiLineString::from_geom(&self, g);
+                if let Some(ref rect) = self.clip_rect {
+                    lines = lines.clip(rect);
                 }
+                screen::Geometry::MultiLineString(lines.simplify(tolerance))
             }
-            GeometryType::MultiPoint(ref g) => screen::MultiPoint::from_geom(&self, g).encode(),
-            GeometryType::LineString(ref g) => screen::LineString::from_geom(&self, g).encode(),
-            GeometryType::MultiLineString(ref g) => {
-                screen::MultiLineString::from_geom(&self, g).encode()
+            &GeometryType::Polygon(ref g) => {
+                let mut polygon = screen::Polygon::from_geom(&self, g);
+                if let Some(ref rect) = self.clip_rect {
+                    polygon = polygon.clip(rect);
+                }
+                screen::Geometry::Polygon(polygon.simplify(tolerance))
+            }
+            &GeometryType::MultiPolygon(ref g) => {
+                let mut polygons = screen::MultiPolygon::from_geom(&self, g);
+                if let Some(ref rect) = self.clip_rect {
+                    polygons = polygons.clip(rect);
+                }
+                screen::Geometry::MultiPolygon(polygons.simplify(tolerance))
             }
-            GeometryType::Polygon(ref g) => screen::Polygon::from_geom(&self, g).encode(),
-            GeometryType::MultiPolygon(ref g) => screen::MultiPolygon::from_geom(&self, g).encode(),
-            GeometryType::GeometryCollection(_) => panic!("GeometryCollection not supported"),
-            GeometryType::Geometry(_) => panic!("Geometry not supported"),
+            &GeometryType::GeometryCollection(_) => panic!("GeometryCollection not supported"),
+            &GeometryType::Geometry(_) => panic!("Geometry not supported"),
+        };
+        if self.quantize_grid > 1 {
+            return Some(screen_geom.quantize(self.quantize_grid));
+        }
+        Some(screen_geom)
+    }
+
+    pub fn encode_geom(&self, geom: geom::GeometryType) -> Result<CommandSequence, EncodeError> {
+        match self.screen_geom(&geom) {
+            Some(screen_geom) => screen_geom.encode(),
+            None => Ok(CommandSequence::new()), // empty
         }
     }
 
+    /// Encoded parts of a geometry with at most `max_vertices` vertices each
+    /// (see `set_max_vertices`). Empty parts are omitted.
+    pub fn encode_geom_parts(
+        &self,
+        geom: geom::GeometryType,
+    ) -> Result<Vec<CommandSequence>, EncodeError> {
+        let screen_geom = match self.screen_geom(&geom) {
+            Some(screen_geom) => screen_geom,
+            None => return Ok(Vec::new()),
+        };
+        let parts = match self.max_vertices {
+            Some(max_vertices) => split_max_vertices(&screen_geom, max_vertices)?,
+            None => vec![screen_geom],
+        };
+        let mut encoded = Vec::with_capacity(parts.len());
+        for part in parts {
+            let seq = part.encode()?;
+            if !seq.is_empty() {
+                encoded.push(seq);
+            }
+        }
+        Ok(encoded)
+    }
+
     pub fn add_feature_attribute(
         mvt_layer: &mut vector_tile::Tile_Layer,
         mvt_feature: &mut vector_tile::Tile_Feature,
@@ -223,12 +454,12 @@ impl<'a> Tile<'a> {
         mvt_feature.mut_tags().push(validx as u32);
     }
 
-    pub fn add_feature(&self, mut mvt_layer: &mut vector_tile::Tile_Layer, feature: &dyn Feature) {
-        let mut mvt_feature = vector_tile::Tile_Feature::new();
-        if let Some(fid) = feature.fid() {
-            mvt_feature.set_id(fid);
-        }
-        'attr: for attr in feature.attributes() {
+    fn add_feature_attributes(
+        mut mvt_layer: &mut vector_tile::Tile_Layer,
+        mut mvt_feature: &mut vector_tile::Tile_Feature,
+        attributes: &[FeatureAttr],
+    ) {
+        'attr: for attr in attributes {
             let mut mvt_value = vector_tile::Tile_Value::new();
             match attr.value {
                 FeatureAttrValType::String(ref v) => {
@@ -252,7 +483,7 @@ impl<'a> Tile<'a> {
                 FeatureAttrValType::Bool(v) => {
                     mvt_value.set_bool_value(v);
                 }
-                FeatureAttrValType::VarcharArray(v) => {
+                FeatureAttrValType::VarcharArray(ref v) => {
                     for array_val in v {
                         Tile::add_feature_attribute(
                             &mut mvt_layer,
@@ -271,21 +502,209 @@ impl<'a> Tile<'a> {
                 mvt_value,
             );
         }
-        if let Ok(geom) = feature.geometry() {
-            let g_type = geom.mvt_field_type();
-            let enc_geom = self.encode_geom(geom).vec();
-            if !enc_geom.is_empty() {
-                mvt_feature.set_field_type(g_type);
-                mvt_feature.set_geometry(enc_geom);
-                mvt_layer.mut_features().push(mvt_feature);
+    }
+
+    /// Add feature with geometry clipped to the tile buffer.
+    /// Returns false if the geometry is empty after clipping. The feature is
+    /// skipped then, before its attributes are added to the layer.
+    /// Lines exceeding the maximal number of vertices are added as multiple features.
+    pub fn add_feature(
+        &self,
+        mvt_layer: &mut vector_tile::Tile_Layer,
+        feature: &dyn Feature,
+    ) -> bool {
+        let geom = match feature.geometry() {
+            Ok(geom) => geom,
+            Err(_) => return false,
+        };
+        let g_type = geom.mvt_field_type();
+        let point = match geom {
+            GeometryType::Point(ref point) if self.point_thinning > 0 => Some(self.point(point)),
+            _ => None,
+        };
+        let enc_geoms = match self.encode_geom_parts(geom) {
+            Ok(enc_geoms) if !enc_geoms.is_empty() => enc_geoms,
+            Ok(_) => return false,
+            Err(e) => {
+                warn!("Feature {:?} skipped: {:?}", feature.fid(), e);
+                return false;
+            }
+        };
+        if let Some(point) = point {
+            let cell = (
+                point.x.div_euclid(self.point_thinning),
+                point.y.div_euclid(self.point_thinning),
+            );
+            if !self.occupied_cells.borrow_mut().insert(cell) {
+                return false;
+            }
+        }
+        let attributes = feature.attributes();
+        for enc_geom in enc_geoms {
+            let mut mvt_feature = vector_tile::Tile_Feature::new();
+            if let Some(fid) = feature.fid() {
+                mvt_feature.set_id(fid);
+            }
+            Tile::add_feature_attributes(mvt_layer, &mut mvt_feature, &attributes);
+            mvt_feature.set_field_type(g_type);
+            mvt_feature.set_geometry(enc_geom.into_vec());
+            mvt_layer.mut_features().push(mvt_feature);
+        }
+        true
+    }
+
+    /// Add feature with geometry already in screen coordinates
+    pub fn add_screen_feature(
+        mvt_layer: &mut vector_tile::Tile_Layer,
+        fid: Option<u64>,
+        attributes: &[FeatureAttr],
+        geom: &screen::Geometry,
+    ) {
+        let enc_geom = match geom.encode() {
+            Ok(enc_geom) if !enc_geom.is_empty() => enc_geom.into_vec(),
+            Ok(_) => return,
+            Err(e) => {
+                warn!("Feature {:?} skipped: {:?}", fid, e);
+                return;
+            }
+        };
+        let mut mvt_feature = vector_tile::Tile_Feature::new();
+        if let Some(fid) = fid {
+            mvt_feature.set_id(fid);
+        }
+        Tile::add_feature_attributes(mvt_layer, &mut mvt_feature, attributes);
+        mvt_feature.set_field_type(geom.geom_type());
+        mvt_feature.set_geometry(enc_geom);
+        mvt_layer.mut_features().push(mvt_feature);
+    }
+
+    /// Add point features with geometry in screen coordinates.
+    /// With `merge`, points with equal attributes are added as a single MultiPoint
+    /// feature without id, saving the per-feature overhead in dense point layers.
+    /// Merging changes feature identity: ids of merged points are dropped.
+    pub fn add_screen_points(
+        mvt_layer: &mut vector_tile::Tile_Layer,
+        points: &[(Option<u64>, Vec<FeatureAttr>, screen::Point)],
+        merge: bool,
+    ) {
+        if !merge {
+            for (fid, attributes, point) in points {
+                let geom = screen::Geometry::Point(point.clone());
+                Tile::add_screen_feature(mvt_layer, *fid, attributes, &geom);
             }
+            return;
+        }
+        // Indices of points with equal attributes, in order of first appearance
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for (idx, (_, attributes, _)) in points.iter().enumerate() {
+            match groups.iter_mut().find(|g| points[g[0]].1 == *attributes) {
+                Some(group) => group.push(idx),
+                None => groups.push(vec![idx]),
+            }
+        }
+        for group in groups {
+            let (fid, ref attributes, ref point) = points[group[0]];
+            let (fid, geom) = if group.len() == 1 {
+                (fid, screen::Geometry::Point(point.clone()))
+            } else {
+                let merged = group.iter().map(|&idx| points[idx].2.clone()).collect();
+                (
+                    None,
+                    screen::Geometry::MultiPoint(screen::MultiPoint::from_points(merged)),
+                )
+            };
+            Tile::add_screen_feature(mvt_layer, fid, attributes, &geom);
         }
     }
 
     pub fn add_layer(&mut self, mvt_layer: vector_tile::Tile_Layer) {
+        self.vector_layers.add_layer(&mvt_layer);
         self.mvt_tile.mut_layers().push(mvt_layer);
     }
 
+    /// Encode `layers` in parallel, one worker thread per layer.
+    /// `add_features` is called with a tile for the layer and the new MVT layer,
+    /// e.g. to set up the layer with `set_preclipped` and to add the layer features
+    /// with `add_feature`. Layers are added in the
+    /// order of `layers`, resulting in the same tile as sequential encoding.
+    /// Layers without features are not added.
+    pub fn add_layers_parallel<F>(&mut self, layers: &[&Layer], add_features: F)
+    where
+        F: Fn(&mut Tile, &Layer, &mut vector_tile::Tile_Layer) + Sync,
+    {
+        let add_features = &add_features;
+        let mvt_layers: Vec<_> = thread::scope(|s| {
+            let workers: Vec<_> = layers
+                .iter()
+                .map(|&layer| {
+                    let mut tile = self.layer_tile();
+                    s.spawn(move || {
+                        let mut mvt_layer = tile.new_layer(layer);
+                        add_features(&mut tile, layer, &mut mvt_layer);
+                        mvt_layer
+                    })
+                })
+                .collect();
+            workers.into_iter().map(|w| w.join().unwrap()).collect()
+        });
+        for mvt_layer in mvt_layers {
+            if !mvt_layer.get_features().is_empty() {
+                self.add_layer(mvt_layer);
+            }
+        }
+    }
+
+    /// Tile without layers with the same extent and encoding settings
+    fn layer_tile(&self) -> Tile<'a> {
+        let mut tile = Tile::new(self.extent, self.reverse_y);
+        tile.simplify_tolerance = self.simplify_tolerance;
+        tile.quantize_grid = self.quantize_grid;
+        tile.point_thinning = self.point_thinning;
+        tile.rounding = self.rounding;
+        tile.max_size = self.max_size;
+        tile.max_vertices = self.max_vertices;
+        tile
+    }
+
+    /// Tile without layers, e.g. because all features were clipped away
+    pub fn is_empty(&self) -> bool {
+        self.mvt_tile.get_layers().is_empty()
+    }
+
+    /// Names and attribute types of the added layers
+    pub fn vector_layers(&self) -> &VectorLayers {
+        &self.vector_layers
+    }
+
+    /// Add a serialized MVT layer message, e.g. generated from another datasource.
+    /// Geometries are taken over without re-encoding.
+    pub fn add_encoded_layer(&mut self, layer: &[u8]) -> Result<(), ProtobufError> {
+        let mvt_layer = vector_tile::Tile_Layer::parse_from_bytes(layer)?;
+        self.add_layer(mvt_layer);
+        Ok(())
+    }
+
+    /// Merge the layers of serialized (uncompressed) tiles into one tile.
+    /// Layer names have to be unique, so later layers with an already used name are skipped.
+    pub fn merge_tiles(tiles: &[&[u8]]) -> Result<vector_tile::Tile, ProtobufError> {
+        let mut merged = vector_tile::Tile::new();
+        for data in tiles {
+            let mut mvt_tile = vector_tile::Tile::parse_from_bytes(data)?;
+            for mvt_layer in mvt_tile.take_layers().into_iter() {
+                if merged
+                    .get_layers()
+                    .iter()
+                    .any(|l| l.get_name() == mvt_layer.get_name())
+                {
+                    warn!("Duplicate layer '{}' skipped", mvt_layer.get_name());
+                    continue;
+                }
+                merged.mut_layers().push(mvt_layer);
+            }
+        }
+        Ok(merged)
+    }
+
     pub fn write_to(mut out: &mut dyn Write, mvt_tile: &vector_tile::Tile) {
         let mut os = CodedOutputStream::new(&mut out);
         let _ = mvt_tile.write_to(&mut os);
@@ -325,12 +744,18 @@ impl<'a> Tile<'a> {
         v
     }
 
-    pub fn tile_content(tilegz: Vec<u8>, gzip: bool) -> Vec<u8> {
-        if gzip {
-            tilegz
+    /// Tile content from raw or gzip compressed `tile`, gzip compressed if `gzip` is set
+    pub fn tile_content(tile: Vec<u8>, gzip: bool) -> Vec<u8> {
+        let compressed = tile.starts_with(&[0x1f, 0x8b]);
+        if gzip == compressed {
+            tile
+        } else if gzip {
+            let mut gz = GzEncoder::new(Vec::with_capacity(tile.len()), Compression::default());
+            let _ = gz.write_all(&tile);
+            gz.finish().unwrap_or_default()
         } else {
-            let mut gz = GzDecoder::new(&tilegz[..]);
-            let mut unc_tile = Vec::with_capacity(tilegz.len());
+            let mut gz = GzDecoder::new(&tile[..]);
+            let mut unc_tile = Vec::with_capacity(tile.len());
             let _ = gz.read_to_end(&mut unc_tile);
             unc_tile
         }
@@ -344,4 +769,14 @@ impl<'a> Tile<'a> {
     pub fn size(mvt_tile: &vector_tile::Tile) -> u32 {
         mvt_tile.compute_size()
     }
+
+    /// Feature count, vertex count and size of all layers
+    pub fn stats(mvt_tile: &vector_tile::Tile) -> TileStats {
+        let features = mvt_tile.get_layers().iter().flat_map(|l| l.get_features());
+        TileStats {
+            features: features.clone().count(),
+            vertices: features.map(|f| vertex_count(f.get_geometry())).sum(),
+            size: Self::size(mvt_tile),
+        }
+    }
 }
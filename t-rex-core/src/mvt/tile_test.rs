@@ -10,7 +10,9 @@ use crate::core::geom::{GeometryType, Point};
 use crate::core::layer::Layer;
 use crate::core::screen;
 use crate::mvt::geom_encoder::EncodableGeom;
-use crate::mvt::tile::{ScreenGeom, Tile};
+use crate::mvt::tile::{
+    ScreenGeom, Tile, TileSizeError, TileStats, ZoomSimplification, ZoomSimplificationTable,
+};
 use crate::mvt::vector_tile;
 use std::fs::File;
 use tile_grid::Extent;
@@ -32,7 +34,7 @@ fn test_point_to_screen_coords() {
 
     let screen_pt = tile.point(&zh_mercator);
     assert_eq!(screen_pt, screen::Point { x: 15, y: 61 });
-    assert_eq!(screen_pt.encode().vec(), &[9, 30, 122]);
+    assert_eq!(screen_pt.encode().unwrap().as_slice(), &[9, 30, 122]);
     assert_eq!(
         tile.point(&geom::Point::new(extent.minx, extent.maxy, Some(3857))),
         screen::Point { x: 0, y: 0 }
@@ -73,6 +75,37 @@ fn test_point_to_screen_coords() {
     );
 }
 
+#[test]
+fn test_tile_extent() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 1000.0,
+        maxy: 1000.0,
+    };
+    for &(tile_size, expected) in &[(4096, 1024), (256, 64), (8192, 2048)] {
+        let mut tile = Tile::new(&extent, true);
+        let mut layer = Layer::new("points");
+        layer.tile_size = tile_size;
+        let mvt_layer = tile.new_layer(&layer);
+        assert_eq!(mvt_layer.get_extent(), tile_size);
+
+        let geom = GeometryType::Point(geom::Point::new(250.0, 750.0, Some(3857)));
+        let screen_pt = screen::Point {
+            x: expected,
+            y: expected,
+        };
+        assert_eq!(
+            tile.point(&geom::Point::new(250.0, 750.0, Some(3857))),
+            screen_pt
+        );
+        assert_eq!(
+            tile.encode_geom(geom).unwrap().as_slice(),
+            screen_pt.encode().unwrap().as_slice()
+        );
+    }
+}
+
 #[test]
 fn test_clipped_polygon() {
     use postgis::ewkb::{self, EwkbRead};
@@ -448,6 +481,7 @@ impl Feature for FeatureStruct {
     fn geometry(&self) -> Result<GeometryType, String> {
         match self.geometry {
             GeometryType::Point(ref g) => Ok(GeometryType::Point(g.clone())),
+            GeometryType::LineString(ref g) => Ok(GeometryType::LineString(g.clone())),
             // Return dummy geometry because of missing Clone impl
             _ => Ok(GeometryType::Point(Point::new(0.0, 0.0, None))),
         }
@@ -554,3 +588,652 @@ fn clip_points() {
 
     assert_eq!(mvt_layer.get_features().len(), 1);
 }
+
+#[test]
+fn test_screen_feature_roundtrip() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let mut tile = Tile::new(&extent, true);
+    let mut mvt_layer = tile.new_layer(&Layer::new("places"));
+
+    let attributes = vec![
+        FeatureAttr {
+            key: "name".to_string(),
+            value: FeatureAttrValType::String("Zürich".to_string()),
+        },
+        FeatureAttr {
+            key: "population".to_string(),
+            value: FeatureAttrValType::Int(400000),
+        },
+    ];
+    let geom = screen::Geometry::Point(screen::Point { x: 25, y: 17 });
+    Tile::add_screen_feature(&mut mvt_layer, Some(1), &attributes, &geom);
+    Tile::add_screen_feature(&mut mvt_layer, Some(2), &attributes[..1], &geom);
+    tile.add_layer(mvt_layer);
+
+    let bytes = Tile::tile_bytevec(&tile.mvt_tile);
+    let decoded = Tile::read_from(&mut &bytes[..]).unwrap();
+    let layer = &decoded.get_layers()[0];
+    assert_eq!(layer.get_name(), "places");
+    // Keys and values are shared between features
+    assert_eq!(layer.get_keys(), &["name", "population"]);
+    assert_eq!(layer.get_values()[0].get_string_value(), "Zürich");
+    assert_eq!(layer.get_values()[1].get_int_value(), 400000);
+    let features = layer.get_features();
+    assert_eq!(features.len(), 2);
+    assert_eq!(features[0].get_id(), 1);
+    assert_eq!(features[0].get_tags(), &[0, 0, 1, 1]);
+    assert_eq!(features[1].get_tags(), &[0, 0]);
+    assert_eq!(
+        features[0].get_field_type(),
+        vector_tile::Tile_GeomType::POINT
+    );
+    assert_eq!(features[0].get_geometry(), &[9, 50, 34]);
+}
+
+#[test]
+fn test_max_tile_size() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let mut tile = Tile::new(&extent, true);
+    tile.set_max_size(Some(1000));
+    let mut mvt_layer = tile.new_layer(&Layer::new("points"));
+    for i in 0..200 {
+        let attributes = vec![FeatureAttr {
+            key: "id".to_string(),
+            value: FeatureAttrValType::Int(i),
+        }];
+        let geom = screen::Geometry::Point(screen::Point::new(i as i32 * 20, 100));
+        Tile::add_screen_feature(&mut mvt_layer, Some(i as u64), &attributes, &geom);
+    }
+    tile.add_layer(mvt_layer);
+
+    let size = Tile::size(&tile.mvt_tile);
+    assert!(size > 1000);
+    assert_eq!(
+        tile.check_size(),
+        Err(TileSizeError {
+            size,
+            max_size: 1000
+        })
+    );
+
+    // Limit is inclusive
+    tile.set_max_size(Some(size));
+    assert_eq!(tile.check_size(), Ok(size));
+
+    tile.set_max_size(None);
+    assert_eq!(tile.check_size(), Ok(size));
+}
+
+#[test]
+fn test_tile_stats() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let mut tile = Tile::new(&extent, true);
+    let mut mvt_layer = tile.new_layer(&Layer::new("stats"));
+    let point = screen::Geometry::Point(screen::Point::new(10, 10));
+    Tile::add_screen_feature(&mut mvt_layer, None, &[], &point);
+    let ring = screen::LineString::from_points(vec![
+        screen::Point::new(0, 0),
+        screen::Point::new(10, 0),
+        screen::Point::new(10, 10),
+        screen::Point::new(0, 10),
+        screen::Point::new(0, 0),
+    ]);
+    let polygon = screen::Geometry::Polygon(screen::Polygon::from_rings(vec![ring]));
+    Tile::add_screen_feature(&mut mvt_layer, None, &[], &polygon);
+    tile.add_layer(mvt_layer);
+
+    // Closing point of the ring is encoded as ClosePath
+    assert_eq!(
+        Tile::stats(&tile.mvt_tile),
+        TileStats {
+            features: 2,
+            vertices: 1 + 4,
+            size: Tile::size(&tile.mvt_tile),
+        }
+    );
+}
+
+#[test]
+fn test_merge_tiles() {
+    use protobuf::Message;
+
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let single_layer_tile = |name: &str, tile_size: u32, geom: screen::Geometry| {
+        let mut tile = Tile::new(&extent, true);
+        let mut layer = Layer::new(name);
+        layer.tile_size = tile_size;
+        let mut mvt_layer = tile.new_layer(&layer);
+        Tile::add_screen_feature(&mut mvt_layer, Some(1), &[], &geom);
+        tile.add_layer(mvt_layer);
+        Tile::tile_bytevec(&tile.mvt_tile)
+    };
+    let points = single_layer_tile(
+        "points",
+        4096,
+        screen::Geometry::Point(screen::Point::new(25, 17)),
+    );
+    let lines = single_layer_tile(
+        "lines",
+        512,
+        screen::Geometry::LineString(screen::LineString::from_points(vec![
+            screen::Point::new(2, 2),
+            screen::Point::new(2, 10),
+        ])),
+    );
+
+    let merged = Tile::merge_tiles(&[&points, &lines, &points]).unwrap();
+    // Read back serialized tile
+    let bytes = Tile::tile_bytevec(&merged);
+    let mvt_tile = Tile::read_from(&mut bytes.as_slice()).unwrap();
+    let layers = mvt_tile.get_layers();
+    // Duplicate layer is skipped
+    assert_eq!(layers.len(), 2);
+    assert_eq!(layers[0].get_name(), "points");
+    assert_eq!(layers[0].get_extent(), 4096);
+    assert_eq!(layers[0].get_features()[0].get_geometry(), &[9, 50, 34]);
+    assert_eq!(layers[1].get_name(), "lines");
+    assert_eq!(layers[1].get_extent(), 512);
+    assert_eq!(
+        layers[1].get_features()[0].get_geometry(),
+        &[9, 4, 4, 10, 0, 16]
+    );
+
+    // Serialized layer message
+    let mut tile = Tile::new(&extent, true);
+    let layer_bytes = layers[1].write_to_bytes().unwrap();
+    tile.add_encoded_layer(&layer_bytes).unwrap();
+    assert_eq!(tile.mvt_tile.get_layers()[0], layers[1]);
+    assert!(tile.add_encoded_layer(&[0xff, 0xff]).is_err());
+}
+
+#[test]
+fn test_merged_points() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let label = |name: &str| {
+        vec![FeatureAttr {
+            key: "class".to_string(),
+            value: FeatureAttrValType::String(name.to_string()),
+        }]
+    };
+    let mut points: Vec<(Option<u64>, Vec<FeatureAttr>, screen::Point)> = (0..100)
+        .map(|i| {
+            (
+                Some(i as u64),
+                label("city"),
+                screen::Point::new(i * 40, 100),
+            )
+        })
+        .collect();
+    points.push((Some(100), label("capital"), screen::Point::new(2000, 2000)));
+
+    let encoded_layer = |merge| {
+        let mut tile = Tile::new(&extent, true);
+        let mut mvt_layer = tile.new_layer(&Layer::new("labels"));
+        Tile::add_screen_points(&mut mvt_layer, &points, merge);
+        mvt_layer
+    };
+    let single = encoded_layer(false);
+    let merged = encoded_layer(true);
+    assert_eq!(single.get_features().len(), 101);
+    assert_eq!(merged.get_features().len(), 2);
+    let size = |layer: &vector_tile::Tile_Layer| protobuf::Message::compute_size(layer);
+    assert!(
+        size(&merged) * 2 < size(&single),
+        "merged: {} bytes, single: {} bytes",
+        size(&merged),
+        size(&single)
+    );
+
+    // Merged points lose their ids, a single point keeps it
+    let features = merged.get_features();
+    assert_eq!(
+        features[0].get_field_type(),
+        vector_tile::Tile_GeomType::POINT
+    );
+    assert!(!features[0].has_id());
+    assert_eq!(
+        features[0].get_geometry()[0],
+        (100 << 3) | 1 // MoveTo(100)
+    );
+    assert_eq!(features[1].get_id(), 100);
+    assert_eq!(features[1].get_geometry(), &[9, 4000, 4000]);
+}
+
+#[test]
+fn test_empty_after_clip() {
+    let extent = Extent {
+        minx: 958826.08,
+        miny: 5987771.04,
+        maxx: 978393.96,
+        maxy: 6007338.92,
+    };
+    let mut tile = Tile::new(&extent, true);
+    let mut layer = Layer::new("points");
+    layer.tile_size = 256;
+    let mut mvt_layer = tile.new_layer(&layer);
+
+    for x in &[940000.0, 990000.0] {
+        let feature = FeatureStruct {
+            fid: Some(1),
+            attributes: vec![FeatureAttr {
+                key: "name".to_string(),
+                value: FeatureAttrValType::String("outside".to_string()),
+            }],
+            geometry: GeometryType::Point(geom::Point::new(*x, 6002729.0, Some(3857))),
+        };
+        assert!(!tile.add_feature(&mut mvt_layer, &feature));
+    }
+    // Attributes of skipped features are not added
+    assert!(mvt_layer.get_features().is_empty());
+    assert!(mvt_layer.get_keys().is_empty());
+    assert!(mvt_layer.get_values().is_empty());
+    assert!(tile.is_empty());
+    assert_eq!(Tile::size(&tile.mvt_tile), 0);
+}
+
+#[test]
+fn test_no_clip() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let line = GeometryType::LineString(geom::LineString {
+        points: vec![
+            Point::new(-100.0, 100.0, Some(3857)),
+            Point::new(5000.0, 100.0, Some(3857)),
+        ],
+        srid: Some(3857),
+    });
+    let unclipped = screen::LineString {
+        points: vec![screen::Point::new(-100, 100), screen::Point::new(5000, 100)],
+    };
+
+    let mut tile = Tile::new(&extent, false);
+    let mut layer = Layer::new("lines");
+    layer.buffer_size = Some(10);
+    tile.new_layer(&layer);
+    assert_ne!(
+        tile.screen_geom(&line).unwrap().encode().unwrap(),
+        unclipped.encode().unwrap()
+    );
+    // Geometries clipped by the datasource are not clipped again
+    tile.set_preclipped();
+    assert_eq!(
+        tile.screen_geom(&line).unwrap().encode().unwrap(),
+        unclipped.encode().unwrap()
+    );
+
+    // Pre-clipped input is encoded unchanged
+    layer.no_clip = true;
+    tile.new_layer(&layer);
+    assert_eq!(tile.encode_geom(line).unwrap(), unclipped.encode().unwrap());
+    // Points outside of the buffer are kept
+    let point = GeometryType::Point(Point::new(5000.0, 100.0, Some(3857)));
+    assert!(tile.screen_geom(&point).is_some());
+    let multipoint = GeometryType::MultiPoint(geom::MultiPoint {
+        points: vec![
+            Point::new(100.0, 100.0, Some(3857)),
+            Point::new(5000.0, 100.0, Some(3857)),
+        ],
+        srid: Some(3857),
+    });
+    match tile.screen_geom(&multipoint) {
+        Some(screen::Geometry::MultiPoint(mp)) => assert_eq!(mp.points.len(), 2),
+        other => panic!("Unexpected geometry {:?}", other),
+    }
+    // Only points within the buffer are kept with clipping
+    layer.no_clip = false;
+    tile.new_layer(&layer);
+    match tile.screen_geom(&multipoint) {
+        Some(screen::Geometry::MultiPoint(mp)) => assert_eq!(mp.points.len(), 1),
+        other => panic!("Unexpected geometry {:?}", other),
+    }
+}
+
+#[test]
+fn test_add_layers_parallel() {
+    let extent = Extent {
+        minx: 958826.08,
+        miny: 5987771.04,
+        maxx: 978393.96,
+        maxy: 6007338.92,
+    };
+    let layers: Vec<Layer> = ["points", "places", "outside", "labels"]
+        .iter()
+        .map(|name| Layer::new(name))
+        .collect();
+    let layers: Vec<&Layer> = layers.iter().collect();
+    let add_features = |tile: &mut Tile, layer: &Layer, mvt_layer: &mut vector_tile::Tile_Layer| {
+        // Features of layer "outside" are clipped away
+        let x0 = if layer.name == "outside" {
+            990000.0
+        } else {
+            960000.0
+        };
+        for i in 0..20 {
+            let feature = FeatureStruct {
+                fid: Some(i),
+                attributes: vec![FeatureAttr {
+                    key: format!("{}_key{}", layer.name, i % 3),
+                    value: FeatureAttrValType::Int(i as i64 % 5),
+                }],
+                geometry: GeometryType::Point(geom::Point::new(
+                    x0 + i as f64 * 500.0,
+                    6002729.0 - i as f64 * 300.0,
+                    Some(3857),
+                )),
+            };
+            tile.add_feature(mvt_layer, &feature);
+        }
+    };
+
+    let mut sequential = Tile::new(&extent, true);
+    for layer in &layers {
+        let mut mvt_layer = sequential.new_layer(layer);
+        add_features(&mut sequential, layer, &mut mvt_layer);
+        if !mvt_layer.get_features().is_empty() {
+            sequential.add_layer(mvt_layer);
+        }
+    }
+
+    let mut parallel = Tile::new(&extent, true);
+    parallel.add_layers_parallel(&layers, add_features);
+    let names: Vec<_> = parallel
+        .mvt_tile
+        .get_layers()
+        .iter()
+        .map(|l| l.get_name())
+        .collect();
+    assert_eq!(names, vec!["points", "places", "labels"]);
+    assert_eq!(
+        Tile::tile_bytevec(&parallel.mvt_tile),
+        Tile::tile_bytevec(&sequential.mvt_tile)
+    );
+}
+
+#[test]
+fn test_add_layers_parallel_preclipped() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let layers: Vec<Layer> = ["clipped", "preclipped"]
+        .iter()
+        .map(|name| {
+            let mut layer = Layer::new(name);
+            layer.buffer_size = Some(10);
+            layer
+        })
+        .collect();
+    let layers: Vec<&Layer> = layers.iter().collect();
+    let add_features = |tile: &mut Tile, layer: &Layer, mvt_layer: &mut vector_tile::Tile_Layer| {
+        // Geometries clipped by the datasource are not clipped again
+        if layer.name == "preclipped" {
+            tile.set_preclipped();
+        }
+        let feature = FeatureStruct {
+            fid: Some(1),
+            attributes: vec![],
+            geometry: GeometryType::LineString(geom::LineString {
+                points: vec![
+                    Point::new(-100.0, 100.0, Some(3857)),
+                    Point::new(5000.0, 100.0, Some(3857)),
+                ],
+                srid: Some(3857),
+            }),
+        };
+        tile.add_feature(mvt_layer, &feature);
+    };
+
+    let mut sequential = Tile::new(&extent, false);
+    for layer in &layers {
+        let mut mvt_layer = sequential.new_layer(layer);
+        add_features(&mut sequential, layer, &mut mvt_layer);
+        sequential.add_layer(mvt_layer);
+    }
+    let mvt_layers = sequential.mvt_tile.get_layers();
+    assert_ne!(
+        mvt_layers[0].get_features()[0].get_geometry(),
+        mvt_layers[1].get_features()[0].get_geometry()
+    );
+
+    let mut parallel = Tile::new(&extent, false);
+    parallel.add_layers_parallel(&layers, add_features);
+    assert_eq!(
+        Tile::tile_bytevec(&parallel.mvt_tile),
+        Tile::tile_bytevec(&sequential.mvt_tile)
+    );
+}
+
+#[test]
+fn test_zoom_simplification() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    // Zigzag line with an amplitude of 3 screen units
+    let line = GeometryType::LineString(geom::LineString {
+        points: (0..100)
+            .map(|i| Point::new(i as f64 * 10.0, 100.0 + (i % 2) as f64 * 3.0, Some(3857)))
+            .collect(),
+        srid: Some(3857),
+    });
+    let table = ZoomSimplificationTable::default();
+    let point_count = |zoom| {
+        let mut tile = Tile::new(&extent, false);
+        tile.set_zoom_simplification(&table, zoom);
+        tile.new_layer(&Layer::new("lines"));
+        match tile.screen_geom(&line) {
+            Some(screen::Geometry::LineString(line)) => line.points.len(),
+            _ => panic!("unexpected geometry"),
+        }
+    };
+    assert_eq!(point_count(14), 100);
+    assert!(point_count(4) < point_count(10));
+    assert_eq!(point_count(4), 2);
+
+    // Default curve decreases to no simplification
+    let tolerances: Vec<i32> = (0..=22).map(|zoom| table.get(zoom).tolerance).collect();
+    assert!(tolerances.windows(2).all(|w| w[0] >= w[1]));
+    assert_eq!(tolerances[22], 0);
+
+    let settings = |tolerance, quantize_grid| ZoomSimplification {
+        tolerance,
+        quantize_grid,
+    };
+    let table = ZoomSimplificationTable::new(vec![(10, settings(1, 0)), (5, settings(4, 16))]);
+    assert_eq!(table.get(4), ZoomSimplification::default());
+    assert_eq!(table.get(5), settings(4, 16));
+    assert_eq!(table.get(9), settings(4, 16));
+    assert_eq!(table.get(18), settings(1, 0));
+}
+
+#[test]
+fn test_point_thinning() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let point_feature = |fid, x, y| FeatureStruct {
+        fid: Some(fid),
+        attributes: vec![],
+        geometry: GeometryType::Point(Point::new(x, y, Some(3857))),
+    };
+    // Cluster within the cell (64, 64)-(128, 128) and a point in the neighbour cell
+    let features = vec![
+        point_feature(1, 70.0, 70.0),
+        point_feature(2, 71.0, 70.0),
+        point_feature(3, 127.0, 127.0),
+        point_feature(4, 100.0, 64.0),
+        point_feature(5, 128.0, 100.0),
+    ];
+    let layer = Layer::new("points");
+    let fids = |tile: &mut Tile| {
+        let mut mvt_layer = tile.new_layer(&layer);
+        for feature in &features {
+            tile.add_feature(&mut mvt_layer, feature);
+        }
+        mvt_layer
+            .get_features()
+            .iter()
+            .map(|f| f.get_id())
+            .collect::<Vec<_>>()
+    };
+
+    let mut tile = Tile::new(&extent, false);
+    assert_eq!(fids(&mut tile), vec![1, 2, 3, 4, 5]);
+    tile.set_point_thinning(64);
+    assert_eq!(fids(&mut tile), vec![1, 5]);
+    // Occupied cells are reset for each layer
+    assert_eq!(fids(&mut tile), vec![1, 5]);
+    tile.set_point_thinning(1);
+    assert_eq!(fids(&mut tile), vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn test_max_vertices() {
+    use crate::mvt::geom_decoder::decode_lines;
+
+    /// Line or polygon feature, since `FeatureStruct` returns points only
+    struct ShapeFeature {
+        polygon: bool,
+        coords: Vec<(f64, f64)>,
+    }
+    impl Feature for ShapeFeature {
+        fn fid(&self) -> Option<u64> {
+            Some(7)
+        }
+        fn attributes(&self) -> Vec<FeatureAttr> {
+            vec![FeatureAttr {
+                key: "name".to_string(),
+                value: FeatureAttrValType::String("shape".to_string()),
+            }]
+        }
+        fn geometry(&self) -> Result<GeometryType, String> {
+            let line = geom::LineString {
+                points: self
+                    .coords
+                    .iter()
+                    .map(|&(x, y)| Point::new(x, y, Some(3857)))
+                    .collect(),
+                srid: Some(3857),
+            };
+            Ok(if self.polygon {
+                GeometryType::Polygon(geom::Polygon {
+                    rings: vec![line],
+                    srid: Some(3857),
+                })
+            } else {
+                GeometryType::LineString(line)
+            })
+        }
+    }
+
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let max = 10;
+    // Zigzag line with 2N+1 vertices
+    let line = ShapeFeature {
+        polygon: false,
+        coords: (0..2 * max + 1)
+            .map(|i| (100.0 + 10.0 * i as f64, 100.0 + 10.0 * (i % 2) as f64))
+            .collect(),
+    };
+    let layer = Layer::new("lines");
+
+    let mut tile = Tile::new(&extent, false);
+    let mut mvt_layer = tile.new_layer(&layer);
+    assert!(tile.add_feature(&mut mvt_layer, &line));
+    assert_eq!(mvt_layer.get_features().len(), 1);
+
+    tile.set_max_vertices(Some(max));
+    let mut mvt_layer = tile.new_layer(&layer);
+    assert!(tile.add_feature(&mut mvt_layer, &line));
+    let features = mvt_layer.get_features();
+    // Parts share their end points: ceil(2N / (N-1)) parts
+    assert_eq!(features.len(), 3);
+    let parts: Vec<_> = features
+        .iter()
+        .map(|f| {
+            assert_eq!(f.get_id(), 7);
+            assert_eq!(f.get_tags(), &[0, 0]);
+            decode_lines(f.get_geometry()).unwrap().lines.remove(0)
+        })
+        .collect();
+    assert!(parts.iter().all(|part| part.points.len() <= max));
+    for pair in parts.windows(2) {
+        assert_eq!(pair[0].points.last(), pair[1].points.first());
+    }
+    let vertices: usize = parts.iter().map(|part| part.points.len()).sum();
+    assert_eq!(vertices, 2 * max + 1 + parts.len() - 1);
+
+    // Polygons are not split
+    let polygon = ShapeFeature {
+        polygon: true,
+        coords: (0..2 * max)
+            .map(|i| {
+                let angle = i as f64 * std::f64::consts::PI / max as f64;
+                (1000.0 + 500.0 * angle.cos(), 1000.0 + 500.0 * angle.sin())
+            })
+            .collect(),
+    };
+    let mut mvt_layer = tile.new_layer(&layer);
+    assert!(!tile.add_feature(&mut mvt_layer, &polygon));
+    assert!(mvt_layer.get_features().is_empty());
+}
+
+#[test]
+fn test_tile_content() {
+    let mut mvt_tile = vector_tile::Tile::new();
+    let mut mvt_layer = vector_tile::Tile_Layer::new();
+    mvt_layer.set_version(2);
+    mvt_layer.set_name(String::from("points"));
+    mvt_tile.mut_layers().push(mvt_layer);
+    let tile = Tile::tile_bytevec(&mvt_tile);
+    let tilegz = Tile::tile_bytevec_gz(&mvt_tile);
+
+    assert_eq!(Tile::tile_content(tilegz.clone(), true), tilegz);
+    assert_eq!(Tile::tile_content(tilegz, false), tile);
+    assert_eq!(Tile::tile_content(tile.clone(), false), tile);
+    // Raw tiles are compressed once
+    let gz = Tile::tile_content(tile.clone(), true);
+    assert_eq!(&gz[0..2], &[0x1f, 0x8b]);
+    assert_eq!(Tile::tile_content(gz, false), tile);
+}
@@ -0,0 +1,136 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+//! Layer descriptions collected from encoded tiles
+//! https://github.com/mapbox/tilejson-spec/tree/master/3.0.0#33-vector_layers
+
+use crate::mvt::vector_tile;
+use std::collections::BTreeMap;
+
+/// Attribute type as used in TileJSON `fields` descriptions
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FieldType {
+    String,
+    Number,
+    Boolean,
+    /// Values of different types
+    Mixed,
+}
+
+impl FieldType {
+    fn of_value(value: &vector_tile::Tile_Value) -> FieldType {
+        if value.has_string_value() {
+            FieldType::String
+        } else if value.has_bool_value() {
+            FieldType::Boolean
+        } else {
+            FieldType::Number
+        }
+    }
+    fn as_str(&self) -> &'static str {
+        match self {
+            FieldType::String => "String",
+            FieldType::Number => "Number",
+            FieldType::Boolean => "Boolean",
+            FieldType::Mixed => "Mixed",
+        }
+    }
+}
+
+/// Observed layer with attribute types and zoom levels
+#[derive(Clone, PartialEq, Debug)]
+pub struct VectorLayer {
+    pub id: String,
+    pub fields: BTreeMap<String, FieldType>,
+    pub minzoom: Option<u8>,
+    pub maxzoom: Option<u8>,
+}
+
+/// Layers observed in encoded tiles, in order of first appearance
+#[derive(Clone, Default, Debug)]
+pub struct VectorLayers {
+    layers: Vec<VectorLayer>,
+}
+
+impl VectorLayers {
+    pub fn new() -> VectorLayers {
+        VectorLayers::default()
+    }
+    pub fn layers(&self) -> &[VectorLayer] {
+        &self.layers
+    }
+    fn layer_mut(&mut self, name: &str) -> &mut VectorLayer {
+        let idx = match self.layers.iter().position(|l| l.id == name) {
+            Some(idx) => idx,
+            None => {
+                self.layers.push(VectorLayer {
+                    id: name.to_string(),
+                    fields: BTreeMap::new(),
+                    minzoom: None,
+                    maxzoom: None,
+                });
+                self.layers.len() - 1
+            }
+        };
+        &mut self.layers[idx]
+    }
+    /// Record layer name and attribute types of all features of an encoded layer
+    pub fn add_layer(&mut self, mvt_layer: &vector_tile::Tile_Layer) {
+        let keys = mvt_layer.get_keys();
+        let values = mvt_layer.get_values();
+        let layer = self.layer_mut(mvt_layer.get_name());
+        for feature in mvt_layer.get_features() {
+            for tag in feature.get_tags().chunks_exact(2) {
+                let (key, value) = match (keys.get(tag[0] as usize), values.get(tag[1] as usize)) {
+                    (Some(key), Some(value)) => (key, value),
+                    _ => continue,
+                };
+                let field_type = FieldType::of_value(value);
+                layer
+                    .fields
+                    .entry(key.clone())
+                    .and_modify(|t| {
+                        if *t != field_type {
+                            *t = FieldType::Mixed
+                        }
+                    })
+                    .or_insert(field_type);
+            }
+        }
+    }
+    /// Record all layers of an encoded tile at zoom level `zoom`
+    pub fn add_tile(&mut self, mvt_tile: &vector_tile::Tile, zoom: u8) {
+        for mvt_layer in mvt_tile.get_layers() {
+            self.add_layer(mvt_layer);
+            let layer = self.layer_mut(mvt_layer.get_name());
+            layer.minzoom = Some(layer.minzoom.map_or(zoom, |z| z.min(zoom)));
+            layer.maxzoom = Some(layer.maxzoom.map_or(zoom, |z| z.max(zoom)));
+        }
+    }
+    /// TileJSON `vector_layers` array. Zoom levels are included if known.
+    pub fn to_json(&self) -> serde_json::Value {
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let fields: serde_json::Map<String, serde_json::Value> = layer
+                    .fields
+                    .iter()
+                    .map(|(name, t)| (name.clone(), json!(t.as_str())))
+                    .collect();
+                let mut layer_json = json!({
+                    "id": layer.id,
+                    "fields": fields,
+                });
+                if let (Some(minzoom), Some(maxzoom)) = (layer.minzoom, layer.maxzoom) {
+                    layer_json["minzoom"] = json!(minzoom);
+                    layer_json["maxzoom"] = json!(maxzoom);
+                }
+                layer_json
+            })
+            .collect();
+        serde_json::Value::Array(layers)
+    }
+}
@@ -0,0 +1,87 @@
+//
+// Copyright (c) Pirmin Kalberer. All rights reserved.
+// Licensed under the MIT License. See LICENSE file in the project root for full license information.
+//
+
+use crate::core::feature::{FeatureAttr, FeatureAttrValType};
+use crate::core::layer::Layer;
+use crate::core::screen;
+use crate::mvt::tile::Tile;
+use crate::mvt::vector_layers::{FieldType, VectorLayers};
+use tile_grid::Extent;
+
+fn attr(key: &str, value: FeatureAttrValType) -> FeatureAttr {
+    FeatureAttr {
+        key: key.to_string(),
+        value,
+    }
+}
+
+#[test]
+fn test_vector_layers() {
+    let extent = Extent {
+        minx: 0.0,
+        miny: 0.0,
+        maxx: 4096.0,
+        maxy: 4096.0,
+    };
+    let point = screen::Geometry::Point(screen::Point::new(10, 10));
+    let mut tile = Tile::new(&extent, true);
+    let mut mvt_layer = tile.new_layer(&Layer::new("places"));
+    Tile::add_screen_feature(
+        &mut mvt_layer,
+        None,
+        &[
+            attr("name", FeatureAttrValType::String("Bern".to_string())),
+            attr("population", FeatureAttrValType::Int(133_000)),
+            attr("capital", FeatureAttrValType::Bool(true)),
+            attr("code", FeatureAttrValType::Int(351)),
+        ],
+        &point,
+    );
+    Tile::add_screen_feature(
+        &mut mvt_layer,
+        None,
+        &[
+            attr("name", FeatureAttrValType::String("Zürich".to_string())),
+            attr("population", FeatureAttrValType::Double(421_000.0)),
+            attr("code", FeatureAttrValType::String("ZH".to_string())),
+        ],
+        &point,
+    );
+    tile.add_layer(mvt_layer);
+    let mvt_layer = tile.new_layer(&Layer::new("empty"));
+    tile.add_layer(mvt_layer);
+
+    let layers = tile.vector_layers().layers();
+    assert_eq!(layers.len(), 2);
+    assert_eq!(layers[0].fields.get("code"), Some(&FieldType::Mixed));
+    assert_eq!(
+        tile.vector_layers().to_json(),
+        json!([
+            {
+                "id": "places",
+                "fields": {
+                    "capital": "Boolean",
+                    "code": "Mixed",
+                    "name": "String",
+                    "population": "Number"
+                }
+            },
+            {
+                "id": "empty",
+                "fields": {}
+            }
+        ])
+    );
+
+    // Zoom range over multiple tiles
+    let mut vector_layers = VectorLayers::new();
+    vector_layers.add_tile(&tile.mvt_tile, 12);
+    vector_layers.add_tile(&tile.mvt_tile, 5);
+    vector_layers.add_tile(&tile.mvt_tile, 8);
+    let json = vector_layers.to_json();
+    assert_eq!(json[0]["minzoom"], json!(5));
+    assert_eq!(json[0]["maxzoom"], json!(12));
+    assert_eq!(json[0]["fields"]["name"], json!("String"));
+}
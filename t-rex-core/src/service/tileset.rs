@@ -6,6 +6,7 @@
 use crate::core::config::Config;
 use crate::core::config::{TilesetCacheCfg, TilesetCfg};
 use crate::core::layer::Layer;
+use crate::mvt::tile::{ZoomSimplification, ZoomSimplificationTable};
 use tile_grid::Extent;
 
 #[derive(Clone, Debug)]
@@ -40,6 +41,8 @@ pub struct Tileset {
     pub start_zoom: Option<u8>,
     pub layers: Vec<Layer>,
     pub cache_limits: Option<CacheLimits>,
+    /// Simplification of tile geometries by zoom level
+    pub zoom_simplification: Option<ZoomSimplificationTable>,
 }
 
 pub static WORLD_EXTENT: Extent = Extent {
@@ -105,6 +108,22 @@ impl<'a> Config<'a, TilesetCfg> for Tileset {
             },
             None => None,
         };
+        let zoom_simplification = tileset_cfg.zoom_simplification.as_ref().map(|levels| {
+            ZoomSimplificationTable::new(
+                levels
+                    .iter()
+                    .map(|cfg| {
+                        (
+                            cfg.minzoom,
+                            ZoomSimplification {
+                                tolerance: cfg.tolerance,
+                                quantize_grid: cfg.quantize_grid,
+                            },
+                        )
+                    })
+                    .collect(),
+            )
+        });
         let extent = match &tileset_cfg.extent {
             Some(cfg) => Some(Extent::from(cfg)),
             None => None,
@@ -119,6 +138,7 @@ impl<'a> Config<'a, TilesetCfg> for Tileset {
             start_zoom: tileset_cfg.start_zoom.clone(),
             layers: layers,
             cache_limits: cache_limits,
+            zoom_simplification,
         })
     }
     fn gen_config() -> String {
@@ -156,6 +176,7 @@ fn test_zoom() {
         }),
         layers: vec![layer],
         cache_limits: None,
+        zoom_simplification: None,
     };
 
     assert_eq!(tileset.minzoom(), 0);
@@ -198,14 +198,6 @@ impl DatasourceType for GdalDatasource {
                 );
             }
         }
-        if layer.buffer_size.is_some() {
-            if layer.geometry_type != Some("POINT".to_string()) {
-                warn!(
-                    "Layer '{}': Clipping with buffer_size not supported for GDAL layers",
-                    layer.name
-                );
-            }
-        }
     }
     fn retrieve_features<F>(
         &self,
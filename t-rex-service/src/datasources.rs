@@ -85,6 +85,12 @@ impl DatasourceType for Datasource {
             }
         }
     }
+    fn clips_geometries(&self, layer: &Layer) -> bool {
+        match self {
+            &Datasource::Postgis(ref ds) => ds.clips_geometries(layer),
+            &Datasource::Gdal(ref ds) => ds.clips_geometries(layer),
+        }
+    }
 }
 
 impl<'a> Config<'a, DatasourceCfg> for Datasource {
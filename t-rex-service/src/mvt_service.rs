@@ -4,13 +4,14 @@
 //
 
 use crate::datasources::{Datasource, Datasources};
+use log::Level;
 use pbr::ProgressBar;
 use percent_encoding::percent_decode;
 use serde_json;
 use std::cmp;
-use std::io::{stderr, Stderr, Stdout};
+use std::io::{stderr, Read, Stderr, Stdout};
 use std::time::Instant;
-use t_rex_core::cache::{Cache, Tilecache};
+use t_rex_core::cache::{tile_path, valid_tile, Cache, CacheError, Encoding, Tilecache};
 use t_rex_core::core::layer::Layer;
 use t_rex_core::core::stats::Statistics;
 use t_rex_core::core::{ApplicationCfg, Config};
@@ -87,20 +88,27 @@ impl MvtService {
             tileset, zoom, xtile, ytile, extent
         );
         let mut tile = Tile::new(&extent, true);
+        if let Some(table) = self
+            .get_tileset(tileset)
+            .and_then(|ts| ts.zoom_simplification.as_ref())
+        {
+            tile.set_zoom_simplification(table, zoom);
+        }
         for layer in self.get_tileset_layers(tileset) {
             if zoom >= layer.minzoom() && zoom <= layer.maxzoom(self.grid.maxzoom()) {
                 let mut mvt_layer = tile.new_layer(layer);
+                let ds = self.ds(&layer).unwrap();
+                if ds.clips_geometries(layer) {
+                    tile.set_preclipped();
+                }
                 let now = Instant::now();
-                let num_features = self.ds(&layer).unwrap().retrieve_features(
-                    tileset,
-                    &layer,
-                    &extent,
-                    zoom,
-                    &self.grid,
-                    |feat| {
-                        tile.add_feature(&mut mvt_layer, feat);
-                    },
-                );
+                let mut num_added = 0;
+                let num_features =
+                    ds.retrieve_features(tileset, &layer, &extent, zoom, &self.grid, |feat| {
+                        if tile.add_feature(&mut mvt_layer, feat) {
+                            num_added += 1;
+                        }
+                    });
                 let elapsed = now.elapsed();
                 if let Some(ref mut stats) = stats {
                     stats.add(
@@ -113,14 +121,36 @@ impl MvtService {
                     );
                 }
                 debug!(
-                    "{}/{}/{}/{} layer {}: {} features",
-                    tileset, zoom, xtile, ytile, layer.name, num_features
+                    "{}/{}/{}/{} layer {}: {} features, {} after clipping",
+                    tileset, zoom, xtile, ytile, layer.name, num_features, num_added
                 );
-                if num_features > 0 {
+                // Features with empty geometries are not added
+                if num_added > 0 {
                     tile.add_layer(mvt_layer);
                 }
             }
         }
+        if tile.is_empty() {
+            // Not cached by `tile_cached`
+            debug!(
+                "{}/{}/{}/{} empty after clipping",
+                tileset, zoom, xtile, ytile
+            );
+            return tile.mvt_tile;
+        }
+        if log_enabled!(Level::Debug) {
+            let tile_stats = Tile::stats(&tile.mvt_tile);
+            debug!(
+                "{}/{}/{}/{} encoded: {} features, {} vertices, {} bytes",
+                tileset,
+                zoom,
+                xtile,
+                ytile,
+                tile_stats.features,
+                tile_stats.vertices,
+                tile_stats.size
+            );
+        }
         tile.mvt_tile
     }
     /// Fetch or create vector tile from input at x, y, z
@@ -139,7 +169,7 @@ impl MvtService {
         } else {
             ytile
         };
-        let path = format!("{}/{}/{}/{}.pbf", tileset, zoom, xtile, ytile);
+        let path = tile_path(tileset, zoom, xtile, ytile);
 
         let ts = self
             .get_tileset(tileset)
@@ -148,14 +178,18 @@ impl MvtService {
         if zoom < ts.minzoom() || zoom > ts.maxzoom() {
             return None;
         }
+        if self.grid.srid == 3857 && !valid_tile(zoom, xtile, ytile) {
+            warn!("Invalid tile {}", path);
+            return None;
+        }
 
         let mut tile: Option<Vec<u8>> = None;
         if ts.is_cachable_at(zoom) {
-            self.cache.read(&path, |f| {
-                let mut data = Vec::new();
-                let _ = f.read_to_end(&mut data);
-                tile = Some(data);
-            });
+            match self.read_cached(&path) {
+                Ok(data) => tile = data,
+                // Regenerate tile and overwrite broken cache entry
+                Err(ioerr) => error!("Error reading {}: {}", path, ioerr),
+            }
         } else {
             debug!(
                 "Cache : read ignored for tileset {} at zoom {}",
@@ -164,17 +198,17 @@ impl MvtService {
         }
 
         // Return tile from cache
-        if let Some(tilegz) = tile {
-            return Some(Tile::tile_content(tilegz, gzip));
+        if let Some(tile) = tile {
+            return Some(Tile::tile_content(tile, gzip));
         }
 
         // Request tile and write into cache
         let mvt_tile = self.tile(tileset, xtile, y, zoom, stats);
         // Spec: A Vector Tile SHOULD contain at least one layer.
         if mvt_tile.get_layers().len() > 0 {
-            let tilegz = Tile::tile_bytevec_gz(&mvt_tile);
+            let tile = Self::cache_bytevec(&mvt_tile, self.cache.preferred_encoding());
             if ts.is_cachable_at(zoom) {
-                if let Err(ioerr) = self.cache.write(&path, &tilegz) {
+                if let Err(ioerr) = self.cache.write(&path, &tile) {
                     error!("Error writing {}: {}", path, ioerr);
                 }
             } else {
@@ -183,7 +217,7 @@ impl MvtService {
                     ts.name, zoom
                 );
             }
-            Some(Tile::tile_content(tilegz, gzip))
+            Some(Tile::tile_content(tile, gzip))
         } else {
             // We don't save empty tiles
             // When serving from file cache return 204 No Content
@@ -192,6 +226,36 @@ impl MvtService {
             None
         }
     }
+    /// Entity tag of the cached tile at x, y, z, as stored in the cache metadata
+    pub fn tile_etag(&self, tileset: &str, xtile: u32, ytile: u32, zoom: u8) -> Option<String> {
+        let ts = self.get_tileset(tileset)?;
+        if !ts.is_cachable_at(zoom) {
+            return None;
+        }
+        let path = tile_path(tileset, zoom, xtile, ytile);
+        self.cache.read_meta(&path).map(|meta| meta.etag)
+    }
+    /// Tile encoded for writing into a cache with preferred encoding `encoding`.
+    /// Tiles are stored gzip compressed, except by backends compressing them
+    /// with brotli on their own.
+    fn cache_bytevec(mvt_tile: &vector_tile::Tile, encoding: Encoding) -> Vec<u8> {
+        match encoding {
+            Encoding::Identity | Encoding::Gzip => Tile::tile_bytevec_gz(mvt_tile),
+            Encoding::Brotli => Tile::tile_bytevec(mvt_tile),
+        }
+    }
+    /// Cached tile, gzip compressed tiles as stored
+    fn read_cached(&self, path: &str) -> Result<Option<Vec<u8>>, CacheError> {
+        let mut data = Vec::new();
+        let hit = self.cache.read_encoded(path, |f, encoding| {
+            match encoding {
+                Encoding::Identity | Encoding::Gzip => f.read_to_end(&mut data),
+                Encoding::Brotli => encoding.decoder(f).read_to_end(&mut data),
+            }
+            .map(|_| ())
+        })?;
+        Ok(if hit { Some(data) } else { None })
+    }
     fn progress_bar(&self, msg: &str, limits: &ExtentInt) -> ProgressBar<Stdout> {
         let tiles =
             (limits.maxx as u64 - limits.minx as u64) * (limits.maxy as u64 - limits.miny as u64);
@@ -315,6 +379,7 @@ impl MvtService {
         // compiled on top of GEOS 3.5.0 is able to support an arbitrary number of threads
         let task_queue_size = cmp::min(num_cpus::get() * 2, 64);
         let mut tasks = Vec::with_capacity(task_queue_size);
+        let encoding = self.cache.preferred_encoding();
         let griditer = GridIterator::new(ts_minzoom, ts_maxzoom, limits.clone());
         let mut tileno: u64 = 0;
         let mut pb = ProgressBar::new(0);
@@ -340,7 +405,7 @@ impl MvtService {
             } else {
                 ytile
             };
-            let path = format!("{}/{}/{}/{}.pbf", tileset_name, zoom, xtile, y);
+            let path = tile_path(tileset_name, zoom, xtile, y);
             let cache_exists = self.cache.exists(&path);
             if overwrite || !cache_exists {
                 // Entry doesn't exist, or overwrite is forced, so generate it
@@ -356,8 +421,8 @@ impl MvtService {
                     .await
                     .unwrap();
                     if mvt_tile.get_layers().len() > 0 {
-                        let tilegz = Tile::tile_bytevec_gz(&mvt_tile);
-                        if let Err(ioerr) = cache.write(&path, &tilegz) {
+                        let tile = Self::cache_bytevec(&mvt_tile, encoding);
+                        if let Err(ioerr) = cache.write(&path, &tile) {
                             error!("Error writing {}: {}", path, ioerr);
                         }
                     } else if overwrite && cache_exists {
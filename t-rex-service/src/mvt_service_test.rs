@@ -116,6 +116,7 @@ fn mvt_service() -> MvtService {
         }),
         layers: vec![layer],
         cache_limits: None,
+        zoom_simplification: None,
     };
     let mut service = MvtService {
         datasources: datasources,
@@ -327,6 +328,54 @@ fn test_generate() {
     );
 }
 
+#[test]
+#[ignore]
+fn test_tile_cached_compressed() {
+    use std::{env, fs};
+    use t_rex_core::cache::{etag, Encoding, Filecache};
+    use t_rex_core::mvt::tile::Tile;
+
+    let mut service = mvt_service();
+    let mvt_tile = service.tile("points", 33, 22, 6, None);
+    let expected = Tile::tile_bytevec(&mvt_tile);
+
+    for compression in vec![Encoding::Identity, Encoding::Gzip, Encoding::Brotli] {
+        let mut dir = env::temp_dir();
+        dir.push("t_rex_test_tile_cached");
+        let _ = fs::remove_dir_all(&dir);
+        service.cache = Tilecache::Filecache(Filecache {
+            basepath: dir.clone(),
+            compression,
+            ..Default::default()
+        });
+        // Generated and cached tile
+        let tile = service.tile_cached("points", 33, 41, 6, false, None);
+        assert_eq!(tile, Some(expected.clone()));
+        // Stored gzip compressed without configured compression, as before
+        if compression != Encoding::Brotli {
+            let suffix = if compression == Encoding::Gzip {
+                ".gz"
+            } else {
+                ""
+            };
+            let data = fs::read(dir.join(format!("points/6/33/41.pbf{}", suffix))).unwrap();
+            assert!(data.starts_with(&[0x1f, 0x8b]));
+        }
+        // Tile read from cache is compressed only once
+        let tile = service.tile_cached("points", 33, 41, 6, false, None);
+        assert_eq!(tile, Some(expected.clone()));
+        let tilegz = service
+            .tile_cached("points", 33, 41, 6, true, None)
+            .unwrap();
+        assert_eq!(Tile::tile_content(tilegz, false), expected);
+        // Entity tag of the uncompressed tile from the cache metadata
+        assert_eq!(
+            service.tile_etag("points", 33, 41, 6),
+            Some(etag(&expected))
+        );
+    }
+}
+
 #[test]
 fn test_gen_config() {
     #[cfg(feature = "with-gdal")]
@@ -359,6 +408,8 @@ name = "points"
 #maxzoom = 22
 #attribution = "© Contributeurs de OpenStreetMap" # Acknowledgment of ownership, authorship or copyright.
 #cache_limits = {{minzoom = 0, maxzoom = 22, no_cache = false}}
+# Simplification in screen units from given zoom level up to the next entry
+#zoom_simplification = [{{minzoom = 0, tolerance = 8}}, {{minzoom = 9, tolerance = 2}}, {{minzoom = 14, tolerance = 0}}]
 
 [[tileset.layer]]
 name = "points"
@@ -377,6 +428,27 @@ geometry_type = "POINT"
 #[cache.file]
 #base = "/tmp/mvtcache"
 #baseurl = "http://example.com/tiles"
+# Directory layout: "simple" ({z}/{x}/{y}.pbf) or "fanout"
+#layout = "simple"
+# Tile row numbering: "xyz" or "tms" (y flipped)
+#scheme = "xyz"
+#gzip = false
+# Compression of stored tiles: "none", "gzip" or "brotli" (overrides gzip)
+#compression = "gzip"
+# Maximal age of cached tiles in seconds
+#max_age = 86400
+# Maximal total size of cached files in bytes. Oldest files are removed first.
+#max_bytes = 10000000000
+# Don't store empty tiles
+#skip_empty = false
+# Mark skipped empty tiles with zero-byte files, so they are not generated again
+#empty_sentinel = false
+# Verify checksums of cached tiles
+#checksum = false
+# Flush written tiles to disk before returning (slower writes)
+#sync = false
+# Remove empty directories and files in cache directory on startup
+#fsck = false
 "#,
         gdal_ds_cfg
     );
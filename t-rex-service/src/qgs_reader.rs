@@ -163,6 +163,7 @@ pub fn read_qgs(fname: &str) -> (Datasources, Tileset) {
         start_zoom: None,
         layers: Vec::new(),
         cache_limits: None,
+        zoom_simplification: None,
     };
     for qgslayer in projectlayers.find_all("maplayer") {
         let layertype = qgslayer.get_attr("type").expect("Missing attribute 'type'");
@@ -77,8 +77,9 @@ pub fn service_from_args(config: &ApplicationCfg, args: &ArgMatches) -> MvtServi
         let cache = match args.value_of("cache") {
             None => Tilecache::Nocache(Nocache),
             Some(dir) => Tilecache::Filecache(Filecache {
-                basepath: dir.to_string(),
+                basepath: dir.into(),
                 baseurl: None,
+                ..Default::default()
             }),
         };
         let simplify = bool::from_str(args.value_of("simplify").unwrap_or("true")).unwrap_or(false);
@@ -121,6 +122,7 @@ pub fn service_from_args(config: &ApplicationCfg, args: &ArgMatches) -> MvtServi
                         start_zoom: None,
                         layers: vec![l],
                         cache_limits: None,
+                        zoom_simplification: None,
                     };
                     tilesets.push(tileset);
                 }
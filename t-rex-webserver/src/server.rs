@@ -3,6 +3,7 @@
 // Licensed under the MIT License. See LICENSE file in the project root for full license information.
 //
 
+use crate::cache::{etag, Cache};
 use crate::core::config::ApplicationCfg;
 use crate::mvt_service::MvtService;
 use crate::runtime_config::{config_from_args, service_from_args};
@@ -19,6 +20,7 @@ use log::Level;
 use num_cpus;
 use open;
 use std::collections::HashMap;
+use std::process;
 use std::str;
 use std::str::FromStr;
 
@@ -138,12 +140,36 @@ async fn tile_pbf(
                 .and_then(|headerstr| Some(headerstr.contains("gzip")))
         })
         .unwrap_or(false);
+    // Tag of the uncompressed tile stored in the cache metadata,
+    // checked before reading or generating the tile
+    let cached_tag = {
+        let service = service.clone();
+        let tileset = tileset.clone();
+        web::block(move || service.tile_etag(&tileset, x, y, z)).await?
+    };
+    if let Some(tag) = cached_tag.as_ref() {
+        if if_none_match(&req, tag) {
+            return Ok(HttpResponse::NotModified()
+                .insert_header((header::ETAG, tag.clone()))
+                .finish());
+        }
+    }
     // rust-postgres starts its own Tokio runtime
     // without blocking we get 'Cannot start a runtime from within a runtime'
     let tile = web::block(move || service.tile_cached(&tileset, x, y, z, gzip, None)).await?;
     let resp = match tile {
         Some(tile) => {
+            // Computed for tiles not cached before
+            let tag = cached_tag.unwrap_or_else(|| etag(&tile));
+            if if_none_match(&req, &tag) {
+                return Ok(HttpResponse::NotModified()
+                    .insert_header((header::ETAG, tag))
+                    .finish());
+            }
             let mut r = HttpResponse::Ok();
+            r.insert_header((header::ETAG, tag));
+            // Same tag for compressed and uncompressed responses
+            r.insert_header((header::VARY, "Accept-Encoding"));
             r.content_type("application/x-protobuf");
             if gzip {
                 // data is already gzip compressed
@@ -158,6 +184,16 @@ async fn tile_pbf(
     Ok(resp)
 }
 
+/// Client has a cached response with entity tag `tag`
+fn if_none_match(req: &HttpRequest, tag: &str) -> bool {
+    req.headers()
+        .get_all(header::IF_NONE_MATCH)
+        .filter_map(|headerval| headerval.to_str().ok())
+        .flat_map(|headerstr| headerstr.split(','))
+        .map(|t| t.trim().trim_start_matches("W/"))
+        .any(|t| t == tag || t == "*")
+}
+
 lazy_static! {
     static ref STATIC_FILES: StaticFiles = StaticFiles::init();
 }
@@ -221,6 +257,10 @@ pub async fn webserver(args: ArgMatches<'static>) -> std::io::Result<()> {
     let svc_config = config.clone();
     let service = web::block(move || {
         let mut service = service_from_args(&svc_config, &args);
+        if let Err(err) = service.cache.healthcheck() {
+            println!("Cache not usable - {}: {}", service.cache.info(), err);
+            process::exit(1)
+        }
         service.prepare_feature_queries();
         service.init_cache();
         service